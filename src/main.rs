@@ -395,6 +395,7 @@ impl AppUpdate for AppModel {
 
 
 fn main() {
+    env_logger::init();
     gst::init().expect("无法初始化 GStreamer");
     gtk::init().map(|_| adw::init()).expect("无法初始化 GTK4");
     let model = AppModel {
@@ -86,12 +86,36 @@ pub struct PreferencesModel {
     pub default_video_url: Url,
     #[derivative(Default(value="60"))]
     pub default_input_sending_rate: u16,
+    #[derivative(Default(value="5"))]
+    pub slave_reconnect_attempts: u32, // 为 0 时不自动重连
+    #[derivative(Default(value="8889"))]
+    pub slave_discovery_broadcast_port: u16,
     #[derivative(Default(value="true"))]
     pub default_keep_video_display_ratio: bool,
     pub default_video_decoder: VideoDecoder,
     pub default_colorspace_conversion: ColorspaceConversion,
     #[derivative(Default(value="64"))]
     pub default_param_tuner_graph_view_point_num_limit: u16,
+    #[derivative(Default(value="false"))]
+    pub param_tuner_force_full_upload: bool,
+    #[derivative(Default(value="false"))]
+    pub param_tuner_preserve_local_edits: bool,
+    #[derivative(Default(value="Duration::from_millis(100)"))]
+    pub param_tuner_preview_interval: Duration,
+    #[derivative(Default(value="false"))]
+    pub param_tuner_pretty_json: bool,
+    #[derivative(Default(value="50.0"))]
+    pub param_tuner_pwm_base_frequency: f64,
+    #[derivative(Default(value="false"))]
+    pub param_tuner_feedback_outlier_rejection: bool,
+    #[derivative(Default(value="10.0"))]
+    pub param_tuner_feedback_outlier_limit: f64,
+    #[derivative(Default(value="true"))]
+    pub param_tuner_keep_history_on_reconnect: bool,
+    #[derivative(Default(value="false"))]
+    pub param_tuner_export_downsampling: bool,
+    #[derivative(Default(value="1000"))]
+    pub param_tuner_export_point_num: u32,
     #[derivative(Default(value="Duration::from_secs(10)"))]
     pub pipeline_timeout: Duration,
     #[derivative(Default(value="false"))]
@@ -102,6 +126,8 @@ pub struct PreferencesModel {
     pub video_sync_record_use_separate_directory: bool,
     #[derivative(Default(value="200"))]
     pub default_video_latency: u32,
+    #[derivative(Default(value="false"))]
+    pub firmware_update_dry_run: bool,
 }
 
 impl PreferencesModel {
@@ -120,12 +146,25 @@ pub enum PreferencesMsg {
     SetImageSaveFormat(ImageFormat),
     SetInitialSlaveNum(u8),
     SetInputSendingRate(u16),
+    SetSlaveReconnectAttempts(u32),
+    SetSlaveDiscoveryBroadcastPort(u16),
     SetDefaultKeepVideoDisplayRatio(bool),
     SetDefaultVideoDecoderCodec(VideoCodec),
     SetDefaultVideoDecoderCodecProvider(VideoCodecProvider),
     SetDefaultVideoEncoderCodec(VideoCodec),
     SetDefaultVideoEncoderCodecProvider(VideoCodecProvider),
     SetDefaultParameterTunerGraphViewPointNumberLimit(u16),
+    SetParameterTunerForceFullUpload(bool),
+    SetParameterTunerPreserveLocalEdits(bool),
+    SetParameterTunerPreviewInterval(Duration),
+    SetParameterTunerPrettyJson(bool),
+    SetParameterTunerPwmBaseFrequency(f64),
+    SetParameterTunerFeedbackOutlierRejection(bool),
+    SetParameterTunerFeedbackOutlierLimit(f64),
+    SetParameterTunerKeepHistoryOnReconnect(bool),
+    SetParameterTunerExportDownsampling(bool),
+    SetParameterTunerExportPointNumber(u32),
+    SetFirmwareUpdateDryRun(bool),
     SetDefaultColorspaceConversion(ColorspaceConversion),
     SetDefaultReencodeRecordingVideo(bool),
     SetDefaultUseDecodebin(bool),
@@ -224,6 +263,32 @@ impl Widgets<PreferencesModel, AppModel> for PreferencesWidgets {
                             }
                          },
                     },
+                    add = &ActionRow {
+                        set_title: "自动重连次数",
+                        set_subtitle: "连接意外断开（如下位机重启）后自动尝试重新连接的最大次数，每次重试的间隔逐渐加长，设为 0 以禁用自动重连",
+                        add_suffix = &SpinButton::with_range(0.0, 100.0, 1.0) {
+                            set_value: track!(model.changed(PreferencesModel::slave_reconnect_attempts()), model.slave_reconnect_attempts as f64),
+                            set_digits: 0,
+                            set_valign: Align::Center,
+                            set_can_focus: false,
+                            connect_value_changed(sender) => move |button| {
+                                send!(sender, PreferencesMsg::SetSlaveReconnectAttempts(button.value() as u32));
+                            }
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "广播探测端口",
+                        set_subtitle: "搜索下位机时向该 UDP 端口发送广播探测，用于发现不支持 mDNS 的下位机",
+                        add_suffix = &SpinButton::with_range(1.0, 65535.0, 1.0) {
+                            set_value: track!(model.changed(PreferencesModel::slave_discovery_broadcast_port()), model.slave_discovery_broadcast_port as f64),
+                            set_digits: 0,
+                            set_valign: Align::Center,
+                            set_can_focus: false,
+                            connect_value_changed(sender) => move |button| {
+                                send!(sender, PreferencesMsg::SetSlaveDiscoveryBroadcastPort(button.value() as u16));
+                            }
+                        },
+                    },
                 },
             },
             add = &PreferencesPage {
@@ -511,6 +576,155 @@ impl Widgets<PreferencesModel, AppModel> for PreferencesWidgets {
                             },
                         },
                     },
+                    add = &ActionRow {
+                        set_title: "写入完整参数",
+                        set_subtitle: "写入参数时总是发送全部参数，而不是仅发送修改过的参数，可避免下位机参数与界面不一致",
+                        add_suffix: param_tuner_force_full_upload_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::param_tuner_force_full_upload()), *model.get_param_tuner_force_full_upload()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetParameterTunerForceFullUpload(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&param_tuner_force_full_upload_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "保留本地修改",
+                        set_subtitle: "重新连接后收到的下位机参数与尚未保存的本地修改不一致时，询问保留哪一方，而不是直接覆盖",
+                        add_suffix: param_tuner_preserve_local_edits_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::param_tuner_preserve_local_edits()), *model.get_param_tuner_preserve_local_edits()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetParameterTunerPreserveLocalEdits(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&param_tuner_preserve_local_edits_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "预览发送间隔",
+                        set_subtitle: "调整参数时向下位机发送预览值的间隔（毫秒），低带宽链路可适当增大",
+                        add_suffix = &SpinButton::with_range(20.0, 2000.0, 10.0) {
+                            set_value: track!(model.changed(PreferencesModel::param_tuner_preview_interval()), model.param_tuner_preview_interval.as_millis() as f64),
+                            set_digits: 0,
+                            set_valign: Align::Center,
+                            set_can_focus: false,
+                            connect_value_changed(sender) => move |button| {
+                                send!(sender, PreferencesMsg::SetParameterTunerPreviewInterval(Duration::from_millis(button.value() as u64)));
+                            },
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "格式化发送的数据包",
+                        set_subtitle: "以带缩进与换行的 JSON 发送参数调校数据包，便于抓包查看；部分下位机解析器不支持，仅在调试协议时开启",
+                        add_suffix: param_tuner_pretty_json_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::param_tuner_pretty_json()), *model.get_param_tuner_pretty_json()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetParameterTunerPrettyJson(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&param_tuner_pretty_json_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "PWM 基准频率",
+                        set_subtitle: "未校准时的 PWM 输出频率（Hz），用于换算频率校准后的实际频率；下位机报告基准频率时以下位机为准",
+                        add_suffix = &SpinButton::with_range(1.0, 100000.0, 1.0) {
+                            set_value: track!(model.changed(PreferencesModel::param_tuner_pwm_base_frequency()), model.param_tuner_pwm_base_frequency),
+                            set_digits: 0,
+                            set_valign: Align::Center,
+                            set_can_focus: false,
+                            connect_value_changed(sender) => move |button| {
+                                send!(sender, PreferencesMsg::SetParameterTunerPwmBaseFrequency(button.value()));
+                            },
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "丢弃异常反馈值",
+                        set_subtitle: "绝对值超过上限的反馈值不绘制在图表中并记录到日志，避免个别异常数据破坏自动缩放；调参报告中仍保留原始值",
+                        add_suffix: param_tuner_feedback_outlier_rejection_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::param_tuner_feedback_outlier_rejection()), *model.get_param_tuner_feedback_outlier_rejection()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetParameterTunerFeedbackOutlierRejection(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&param_tuner_feedback_outlier_rejection_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "反馈值上限",
+                        set_subtitle: "换算前的原始反馈值的绝对值上限，超过该值视为异常",
+                        set_sensitive: track!(model.changed(PreferencesModel::param_tuner_feedback_outlier_rejection()), *model.get_param_tuner_feedback_outlier_rejection()),
+                        add_suffix = &SpinButton::with_range(0.1, 100000.0, 0.1) {
+                            set_value: track!(model.changed(PreferencesModel::param_tuner_feedback_outlier_limit()), model.param_tuner_feedback_outlier_limit),
+                            set_digits: 1,
+                            set_valign: Align::Center,
+                            set_can_focus: false,
+                            connect_value_changed(sender) => move |button| {
+                                send!(sender, PreferencesMsg::SetParameterTunerFeedbackOutlierLimit(button.value()));
+                            },
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "重连后保留图表",
+                        set_subtitle: "连接短暂中断并自动重连后继续在原有曲线后绘制反馈，并以虚线标记中断位置；关闭时重连后清空图表",
+                        add_suffix: param_tuner_keep_history_on_reconnect_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::param_tuner_keep_history_on_reconnect()), *model.get_param_tuner_keep_history_on_reconnect()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetParameterTunerKeepHistoryOnReconnect(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&param_tuner_keep_history_on_reconnect_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "降采样导出反馈记录",
+                        set_subtitle: "调参报告中的反馈记录只保留各区间的最小值与最大值，以减小文件体积；关闭时导出全部采样点，与图表的显示精度无关",
+                        add_suffix: param_tuner_export_downsampling_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::param_tuner_export_downsampling()), *model.get_param_tuner_export_downsampling()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetParameterTunerExportDownsampling(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&param_tuner_export_downsampling_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "导出采样点数",
+                        set_subtitle: "降采样后每个控制环最多导出的采样点数",
+                        set_sensitive: track!(model.changed(PreferencesModel::param_tuner_export_downsampling()), *model.get_param_tuner_export_downsampling()),
+                        add_suffix = &SpinButton::with_range(2.0, 1000000.0, 100.0) {
+                            set_value: track!(model.changed(PreferencesModel::param_tuner_export_point_num()), model.param_tuner_export_point_num as f64),
+                            set_digits: 0,
+                            set_valign: Align::Center,
+                            set_can_focus: false,
+                            connect_value_changed(sender) => move |button| {
+                                send!(sender, PreferencesMsg::SetParameterTunerExportPointNumber(button.value() as u32));
+                            },
+                        },
+                    },
+                },
+                add = &PreferencesGroup {
+                    set_title: "固件更新",
+                    set_description: Some("配置固件更新向导的调试选项"),
+                    add = &ActionRow {
+                        set_title: "模拟更新",
+                        set_subtitle: "不连接下位机也可打开固件更新向导，传输过程仅为模拟，不会向下位机写入任何数据，可用于培训操作人员",
+                        add_suffix: firmware_update_dry_run_switch = &Switch {
+                            set_active: track!(model.changed(PreferencesModel::firmware_update_dry_run()), *model.get_firmware_update_dry_run()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, PreferencesMsg::SetFirmwareUpdateDryRun(state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&firmware_update_dry_run_switch),
+                    },
                 },
             },
         }
@@ -538,11 +752,24 @@ impl ComponentUpdate<AppModel> for PreferencesModel {
             PreferencesMsg::SetVideoSavePath(path) => self.set_video_save_path(path),
             PreferencesMsg::SetInitialSlaveNum(num) => self.set_initial_slave_num(num),
             PreferencesMsg::SetInputSendingRate(rate) => self.set_default_input_sending_rate(rate),
+            PreferencesMsg::SetSlaveReconnectAttempts(attempts) => self.set_slave_reconnect_attempts(attempts),
+            PreferencesMsg::SetSlaveDiscoveryBroadcastPort(port) => self.set_slave_discovery_broadcast_port(port),
             PreferencesMsg::SetDefaultKeepVideoDisplayRatio(value) => self.set_default_keep_video_display_ratio(value),
             PreferencesMsg::SaveToFile => serde_json::to_string_pretty(&self).ok().and_then(|json| fs::write(get_preference_path(), json).ok()).unwrap(),
             PreferencesMsg::SetImageSavePath(path) => self.set_image_save_path(path),
             PreferencesMsg::SetImageSaveFormat(format) => self.set_image_save_format(format),
             PreferencesMsg::SetDefaultParameterTunerGraphViewPointNumberLimit(limit) => self.set_default_param_tuner_graph_view_point_num_limit(limit),
+            PreferencesMsg::SetParameterTunerForceFullUpload(force) => self.set_param_tuner_force_full_upload(force),
+            PreferencesMsg::SetParameterTunerPreserveLocalEdits(preserve) => self.set_param_tuner_preserve_local_edits(preserve),
+            PreferencesMsg::SetParameterTunerPreviewInterval(interval) => self.set_param_tuner_preview_interval(interval),
+            PreferencesMsg::SetParameterTunerPrettyJson(pretty) => self.set_param_tuner_pretty_json(pretty),
+            PreferencesMsg::SetParameterTunerPwmBaseFrequency(frequency) => self.set_param_tuner_pwm_base_frequency(frequency),
+            PreferencesMsg::SetParameterTunerFeedbackOutlierRejection(enabled) => self.set_param_tuner_feedback_outlier_rejection(enabled),
+            PreferencesMsg::SetParameterTunerFeedbackOutlierLimit(limit) => self.set_param_tuner_feedback_outlier_limit(limit),
+            PreferencesMsg::SetParameterTunerKeepHistoryOnReconnect(keep) => self.set_param_tuner_keep_history_on_reconnect(keep),
+            PreferencesMsg::SetParameterTunerExportDownsampling(downsampling) => self.set_param_tuner_export_downsampling(downsampling),
+            PreferencesMsg::SetParameterTunerExportPointNumber(point_num) => self.set_param_tuner_export_point_num(point_num),
+            PreferencesMsg::SetFirmwareUpdateDryRun(dry_run) => self.set_firmware_update_dry_run(dry_run),
             PreferencesMsg::OpenVideoDirectory => gtk::show_uri(None as Option<&PreferencesWindow>, glib::filename_to_uri(self.get_video_save_path().to_str().unwrap(), None).unwrap().as_str(), gdk::CURRENT_TIME),
             PreferencesMsg::OpenImageDirectory => gtk::show_uri(None as Option<&PreferencesWindow>, glib::filename_to_uri(self.get_image_save_path().to_str().unwrap(), None).unwrap().as_str(), gdk::CURRENT_TIME),
             PreferencesMsg::SetDefaultColorspaceConversion(conversion) => self.set_default_colorspace_conversion(conversion),
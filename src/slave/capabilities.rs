@@ -0,0 +1,134 @@
+/* capabilities.rs
+ *
+ * Copyright 2021-2022 Bohong Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::{str::FromStr, convert::TryFrom};
+
+use serde::{Serialize, Deserialize};
+
+use super::firmware_update::SlaveFirmwareCompression;
+
+/// 上位机实现的通讯协议版本，主版本号不同的下位机无法正常通讯
+pub const HOST_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 1, minor: 0 };
+
+/// 通讯协议版本，在数据包中表示为 "主版本号.次版本号" 形式的字符串
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    pub fn is_compatible_with(&self, other: &ProtocolVersion) -> bool {
+        self.major == other.major
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.split_once('.').unwrap_or((s, "0"));
+        match (major.trim().parse(), minor.trim().parse()) {
+            (Ok(major), Ok(minor)) => Ok(ProtocolVersion { major, minor }),
+            _ => Err(format!("无效的协议版本“{}”", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for ProtocolVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ProtocolVersion> for String {
+    fn from(version: ProtocolVersion) -> Self {
+        version.to_string()
+    }
+}
+
+impl ToString for ProtocolVersion {
+    fn to_string(&self) -> String {
+        format!("{}.{}", self.major, self.minor)
+    }
+}
+
+/// 上位机告知自身协议版本的握手数据包，仅在下位机的功能声明中包含协议版本后发送，旧版下位机不会收到
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveHelloValuePacket {
+    pub protocol_version: ProtocolVersion,
+    pub app_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveHelloPacket {
+    pub hello: SlaveHelloValuePacket,
+}
+
+impl Default for SlaveHelloPacket {
+    fn default() -> Self {
+        SlaveHelloPacket {
+            hello: SlaveHelloValuePacket {
+                protocol_version: HOST_PROTOCOL_VERSION,
+                app_version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        }
+    }
+}
+
+/// 下位机在连接建立后发送的功能声明。
+///
+/// 未发送声明的下位机视为只支持最小功能集（`Default`），上位机不会向其发送可选功能的数据包，
+/// 声明中缺少的字段同样按不支持处理。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    #[serde(default)]
+    pub protocol_version: Option<ProtocolVersion>, // 未声明时视为与上位机兼容的旧版下位机，上位机也不向其发送握手数据包
+    #[serde(default)]
+    pub compression: Vec<String>, // 固件传输支持的压缩算法，如 "gzip"、"lz4"
+    #[serde(default)]
+    pub flow_control: bool, // 固件传输是否支持按窗口确认的流量控制
+    #[serde(default)]
+    pub ping: bool, // 是否回复延迟测量数据包
+    #[serde(default)]
+    pub propeller_telemetry: bool, // 是否发送各推进器的电流、温度等遥测数据
+    #[serde(default)]
+    pub control_loops: Vec<String>, // 可调校的控制环，为空时使用默认的控制环
+    #[serde(default)]
+    pub length_prefixed_frames: bool, // 参数调校与固件更新的数据包是否使用长度前缀分帧，见 `frame::FrameFormat`
+}
+
+impl Capabilities {
+    /// 下位机声明的协议版本与上位机不兼容时返回该版本
+    pub fn incompatible_protocol_version(&self) -> Option<ProtocolVersion> {
+        self.protocol_version.filter(|version| !version.is_compatible_with(&HOST_PROTOCOL_VERSION))
+    }
+
+    pub fn supports_compression(&self, compression: SlaveFirmwareCompression) -> bool {
+        compression == SlaveFirmwareCompression::None || self.compression.iter()
+            .any(|algorithm| serde_json::to_value(compression).map_or(false, |value| value.as_str().map_or(false, |name| algorithm.eq_ignore_ascii_case(name))))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveCapabilitiesPacket {
+    pub capabilities: Capabilities,
+}
@@ -0,0 +1,334 @@
+/* discovery.rs
+ *
+ * Copyright 2021-2022 Bohong Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet}, rc::Rc, net::{Ipv4Addr, SocketAddr}, time::{Duration, Instant}, io::{Error as IOError, ErrorKind}};
+
+use async_std::{net::UdpSocket, io, task};
+use glib::{MainContext, Sender, PRIORITY_DEFAULT, Continue};
+use gtk::{prelude::*, Align, Button, Dialog, Label, ListBox, ResponseType, ScrolledWindow, SelectionMode};
+use adw::{prelude::*, ActionRow};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use serde::{Serialize, Deserialize};
+use url::Url;
+
+/// 下位机通过 mDNS 广播的服务类型
+pub const ROV_SERVICE_TYPE: &str = "_rov._tcp.local.";
+/// 发送广播探测后等待下位机回复的时间
+pub const BROADCAST_DISCOVERY_DURATION: Duration = Duration::from_secs(2);
+const BROADCAST_DISCOVERY_MAGIC: &str = "rov-host";
+
+/// 不支持 mDNS 的下位机通过 UDP 广播探测发现，探测数据包为 `{"discover":"rov-host"}`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveDiscoveryProbePacket {
+    discover: String,
+}
+
+impl Default for SlaveDiscoveryProbePacket {
+    fn default() -> Self {
+        SlaveDiscoveryProbePacket { discover: BROADCAST_DISCOVERY_MAGIC.to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveDiscoveryReplyValuePacket {
+    pub name: String,
+    pub firmware_version: Option<String>,
+    pub port: u16, // 下位机接受连接的 TCP 端口
+}
+
+/// 下位机收到探测后向来源地址回复的数据包，回复的来源地址即为下位机的地址
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveDiscoveryReplyPacket {
+    pub vehicle: SlaveDiscoveryReplyValuePacket,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredVehicle {
+    pub id: String, // 用于识别同一台下位机的后续通告，mDNS 为服务的完整名称
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+    pub firmware_version: Option<String>,
+}
+
+impl DiscoveredVehicle {
+    pub fn slave_url(&self) -> Option<Url> {
+        Url::parse(&format!("tcp://{}:{}", self.address, self.port)).ok()
+    }
+}
+
+#[derive(Debug)]
+pub enum DiscoveryEvent {
+    Found(DiscoveredVehicle),
+    Removed(String),
+    Failed(String),
+    BroadcastFinished,
+}
+
+/// 在后台任务中持续搜索局域网中的下位机，返回的守护进程在关闭对话框时停止；
+/// 系统不支持组播等原因导致无法搜索时发送 `DiscoveryEvent::Failed` 并返回 `None`
+pub fn start_discovery(sender: Sender<DiscoveryEvent>) -> Option<ServiceDaemon> {
+    let result = ServiceDaemon::new().and_then(|daemon| daemon.browse(ROV_SERVICE_TYPE).map(|receiver| (daemon, receiver)));
+    let (daemon, receiver) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            log::warn!("无法启动 mDNS 搜索：{}", err);
+            sender.send(DiscoveryEvent::Failed(err.to_string())).unwrap_or_default();
+            return None;
+        },
+    };
+    task::spawn_blocking(move || {
+        while let Ok(event) = receiver.recv() { // 守护进程停止后通道关闭，任务随之结束
+            let event = match event {
+                ServiceEvent::ServiceResolved(info) => match info.get_addresses().iter().next() {
+                    Some(address) => DiscoveryEvent::Found(DiscoveredVehicle {
+                        id: info.get_fullname().to_string(),
+                        name: info.get_fullname().trim_end_matches(ROV_SERVICE_TYPE).trim_end_matches('.').to_string(),
+                        address: address.to_string(),
+                        port: info.get_port(),
+                        firmware_version: None,
+                    }),
+                    None => continue,
+                },
+                ServiceEvent::ServiceRemoved(_, fullname) => DiscoveryEvent::Removed(fullname),
+                _ => continue,
+            };
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    Some(daemon)
+}
+
+/// 向 `target` 发送探测并在 `duration` 内收集回复，每个地址只报告第一次回复；`found` 返回 `false` 时提前结束
+async fn probe_vehicles<F: FnMut(DiscoveredVehicle) -> bool>(target: SocketAddr, duration: Duration, mut found: F) -> Result<(), IOError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&serde_json::to_vec(&SlaveDiscoveryProbePacket::default()).unwrap(), target).await?;
+    let deadline = Instant::now() + duration;
+    let mut replied = HashSet::new(); // 下位机可能从多个网络接口或因重发而多次回复
+    let mut buffer = [0u8; 1024];
+    loop {
+        let (len, source): (usize, SocketAddr) = match io::timeout(deadline.saturating_duration_since(Instant::now()), socket.recv_from(&mut buffer)).await {
+            Ok(received) => received,
+            Err(err) if err.kind() == ErrorKind::TimedOut => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        match serde_json::from_slice::<SlaveDiscoveryReplyPacket>(&buffer[..len]) {
+            Ok(_) if !replied.insert(source.ip()) => (),
+            Ok(SlaveDiscoveryReplyPacket { vehicle }) => {
+                let vehicle = DiscoveredVehicle {
+                    id: format!("broadcast:{}", source.ip()),
+                    name: vehicle.name,
+                    address: source.ip().to_string(),
+                    port: vehicle.port,
+                    firmware_version: vehicle.firmware_version,
+                };
+                if !found(vehicle) {
+                    return Ok(());
+                }
+            },
+            Err(err) => log::debug!("忽略来自 {} 的无效探测回复：{}", source, err),
+        }
+    }
+}
+
+/// 向 `broadcast_port` 发送广播探测，在 `BROADCAST_DISCOVERY_DURATION` 内收集下位机的回复，结束后发送 `DiscoveryEvent::BroadcastFinished`
+pub fn start_broadcast_discovery(broadcast_port: u16, sender: Sender<DiscoveryEvent>) {
+    task::spawn(async move {
+        let target = SocketAddr::from((Ipv4Addr::BROADCAST, broadcast_port));
+        let found_sender = sender.clone();
+        if let Err(err) = probe_vehicles(target, BROADCAST_DISCOVERY_DURATION, move |vehicle| found_sender.send(DiscoveryEvent::Found(vehicle)).is_ok()).await {
+            log::warn!("无法发送广播探测：{}", err);
+        }
+        sender.send(DiscoveryEvent::BroadcastFinished).unwrap_or_default();
+    });
+}
+
+/// 列出通过 mDNS 与 UDP 广播探测搜索到的下位机，按地址去重，随通告的到达与过期实时更新，选择后通过 `callback` 返回连接 URL
+pub fn discovery_dialog<T: IsA<gtk::Window>, F: Fn(Url) + 'static>(window: Option<&T>, broadcast_port: u16, callback: F) -> Dialog {
+    let (sender, receiver) = MainContext::channel(PRIORITY_DEFAULT);
+    start_broadcast_discovery(broadcast_port, sender.clone());
+    let daemon = start_discovery(sender);
+    let callback = Rc::new(callback);
+    let rows: Rc<RefCell<HashMap<String, (String, ActionRow)>>> = Default::default(); // 地址到通告标识与对应行的映射
+    let mdns_error: RefCell<Option<String>> = Default::default();
+    let broadcast_finished = Cell::new(false);
+    relm4_macros::view! {
+        dialog = Dialog {
+            set_title: Some("搜索下位机"),
+            set_modal: true,
+            set_transient_for: window,
+            set_default_width: 400,
+            set_default_height: 300,
+            add_button: args!("取消", ResponseType::Cancel),
+        }
+    }
+    relm4_macros::view! {
+        status_label = Label {
+            set_wrap: true,
+            set_margin_top: 12,
+            set_margin_bottom: 12,
+            add_css_class: "dim-label",
+            set_label: "正在搜索局域网中的下位机……",
+        }
+    }
+    relm4_macros::view! {
+        list_box = ListBox {
+            add_css_class: "boxed-list",
+            set_selection_mode: SelectionMode::None,
+            set_visible: false,
+            set_margin_start: 12,
+            set_margin_end: 12,
+            set_margin_bottom: 12,
+        }
+    }
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    content.append(&status_label);
+    content.append(&list_box);
+    relm4_macros::view! {
+        scrolled_window = ScrolledWindow {
+            set_vexpand: true,
+            set_child: Some(&content),
+        }
+    }
+    dialog.content_area().append(&scrolled_window);
+    receiver.attach(None, glib::clone!(@weak dialog, @weak list_box, @weak status_label => @default-return Continue(false), move |event| {
+        match event {
+            DiscoveryEvent::Found(vehicle) => {
+                let mut rows = rows.borrow_mut();
+                rows.retain(|address, (id, row)| { // 同一通告的地址变化，或同一地址被另一种方式发现时替换原有的行
+                    let stale = *id == vehicle.id || *address == vehicle.address;
+                    if stale {
+                        list_box.remove(row);
+                    }
+                    !stale
+                });
+                if let Some(url) = vehicle.slave_url() {
+                    let subtitle = match &vehicle.firmware_version {
+                        Some(version) => format!("{}:{}，固件版本 {}", vehicle.address, vehicle.port, version),
+                        None => format!("{}:{}", vehicle.address, vehicle.port),
+                    };
+                    relm4_macros::view! {
+                        row = ActionRow {
+                            set_title: &vehicle.name,
+                            set_subtitle: &subtitle,
+                            add_suffix = &Button {
+                                set_label: "连接",
+                                set_valign: Align::Center,
+                                connect_clicked: glib::clone!(@weak dialog, @strong callback => move |_button| {
+                                    callback(url.clone());
+                                    dialog.response(ResponseType::Accept);
+                                }),
+                            },
+                        }
+                    }
+                    list_box.append(&row);
+                    rows.insert(vehicle.address, (vehicle.id, row));
+                }
+            },
+            DiscoveryEvent::Removed(fullname) => {
+                rows.borrow_mut().retain(|_address, (id, row)| {
+                    if *id == fullname {
+                        list_box.remove(row);
+                    }
+                    *id != fullname
+                });
+            },
+            DiscoveryEvent::Failed(err) => *mdns_error.borrow_mut() = Some(err),
+            DiscoveryEvent::BroadcastFinished => broadcast_finished.set(true),
+        }
+        let found = !rows.borrow().is_empty();
+        list_box.set_visible(found);
+        match (found, mdns_error.borrow().as_ref()) {
+            (true, _) => status_label.set_label("选择要连接的下位机，列表随下位机上下线自动更新"),
+            (false, Some(err)) if broadcast_finished.get() => status_label.set_label(&format!("当前系统无法使用 mDNS（{}），广播探测也未找到下位机，请手动输入连接 URL", err)),
+            (false, None) if broadcast_finished.get() => status_label.set_label("尚未找到下位机，列表将在下位机上线时自动更新"),
+            (false, _) => status_label.set_label("正在搜索局域网中的下位机……"),
+        }
+        Continue(true)
+    }));
+    dialog.connect_response(move |dialog, _response| {
+        if let Some(daemon) = &daemon {
+            if let Err(err) = daemon.shutdown() {
+                log::warn!("无法停止 mDNS 搜索：{}", err);
+            }
+        }
+        dialog.destroy();
+    });
+    dialog.show();
+    dialog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_packet_round_trip() {
+        let json = serde_json::to_string(&SlaveDiscoveryProbePacket::default()).unwrap();
+        assert_eq!(json, r#"{"discover":"rov-host"}"#);
+        assert_eq!(serde_json::from_str::<SlaveDiscoveryProbePacket>(&json).unwrap(), SlaveDiscoveryProbePacket::default());
+    }
+
+    #[test]
+    fn reply_packet_round_trip() {
+        let reply = SlaveDiscoveryReplyPacket {
+            vehicle: SlaveDiscoveryReplyValuePacket { name: "rov-1".to_string(), firmware_version: Some("1.2.0".to_string()), port: 8888 },
+        };
+        let json = serde_json::to_string(&reply).unwrap();
+        assert_eq!(serde_json::from_str::<SlaveDiscoveryReplyPacket>(&json).unwrap(), reply);
+        // 旧版固件的回复不包含固件版本
+        let reply = serde_json::from_str::<SlaveDiscoveryReplyPacket>(r#"{"vehicle":{"name":"rov-2","port":8888}}"#).unwrap();
+        assert_eq!(reply.vehicle.firmware_version, None);
+    }
+
+    #[test]
+    fn loopback_probe_collects_deduplicated_replies() {
+        task::block_on(async {
+            let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+            let target = responder.local_addr().unwrap();
+            let responder_task = task::spawn(async move {
+                let mut buffer = [0u8; 1024];
+                let (len, source) = responder.recv_from(&mut buffer).await.unwrap();
+                assert_eq!(serde_json::from_slice::<SlaveDiscoveryProbePacket>(&buffer[..len]).unwrap(), SlaveDiscoveryProbePacket::default());
+                let reply = serde_json::to_vec(&SlaveDiscoveryReplyPacket {
+                    vehicle: SlaveDiscoveryReplyValuePacket { name: "rov-1".to_string(), firmware_version: None, port: 8888 },
+                }).unwrap();
+                responder.send_to(b"not json", source).await.unwrap(); // 无效的回复被忽略
+                for _ in 0..2 {
+                    responder.send_to(&reply, source).await.unwrap();
+                }
+            });
+            let mut vehicles = Vec::new();
+            probe_vehicles(target, Duration::from_millis(500), |vehicle| {
+                vehicles.push(vehicle);
+                true
+            }).await.unwrap();
+            responder_task.await;
+            assert_eq!(vehicles, vec![DiscoveredVehicle {
+                id: "broadcast:127.0.0.1".to_string(),
+                name: "rov-1".to_string(),
+                address: "127.0.0.1".to_string(),
+                port: 8888,
+                firmware_version: None,
+            }]);
+        });
+    }
+}
@@ -16,32 +16,1043 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::{path::PathBuf, fmt::Debug};
+use std::{cell::{RefCell, Cell}, collections::{HashMap, VecDeque}, rc::Rc, path::{Path, PathBuf}, fmt::Debug, io::{Error as IOError, ErrorKind, Read, Write, Seek, SeekFrom}, time::{Duration, Instant, SystemTime}, net::SocketAddr, sync::{Arc, Mutex}};
 use async_std::{io::ReadExt, net::TcpStream, task, prelude::*};
 
-use glib::Sender;
+use glib::{Sender, DateTime};
 use glib_macros::clone;
-use gtk::{Align, Box as GtkBox, Orientation, prelude::*, FileFilter, ProgressBar, FileChooserAction, Button};
-use adw::{HeaderBar, PreferencesGroup, StatusPage, Window, prelude::*, ActionRow, Carousel};
-use once_cell::unsync::OnceCell;
-use relm4::{send, MicroWidgets, MicroModel};
+use gtk::{Align, Box as GtkBox, Orientation, prelude::*, FileFilter, ProgressBar, Label, Entry, FileChooserAction, Button, StringList, Image, CheckButton, DropTarget, gdk, SpinButton, DropDown, Spinner, MessageDialog, MessageType, ResponseType, Dialog, ScrolledWindow, Inhibit, ToggleButton};
+use adw::{HeaderBar, PreferencesGroup, StatusPage, Window, prelude::*, ActionRow, Carousel, ComboRow, ToastOverlay, Toast, ExpanderRow, Clamp};
+use once_cell::{unsync::OnceCell, sync::Lazy};
+use relm4::{send, MicroWidgets, MicroModel, factory::{FactoryPrototype, FactoryVec}};
 use relm4_macros::micro_widget;
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use sha2::{Sha256, Digest};
+use flate2::write::GzEncoder;
+use strum::IntoEnumIterator;
+use url::Url;
+use strum_macros::EnumIter;
 use derivative::*;
 
 use crate::prelude::*;
+use crate::preferences::get_data_path;
 use crate::slave::SlaveTcpMsg;
-use crate::ui::generic::select_path;
+use crate::ui::generic::{select_path, validate_spin_button_input};
 
-use super::SlaveMsg;
+use super::{SlaveMsg, ExclusiveTcpActivity, frame::{FrameFormat, FrameReader, FRAME_LENGTH_PREFIX_BYTES, MAX_FRAME_BYTES, write_frame}, latency::SlavePongPacket, capabilities::Capabilities};
 
 pub enum SlaveFirmwareUpdaterMsg {
     StartUpload,
     NextStep,
+    PreviousStep,
+    RetryUpdate,
     FirmwareFileSelected(PathBuf),
-    FirmwareUploadProgressUpdated(f32),
-    FirmwareUploadFailed,
+    FirmwareUploadProgressUpdated(FirmwareUploadProgress),
+    FirmwarePreparing,
+    FirmwareUploadFinished(Result<(), String>),
+    SetCompression(FirmwareCompression),
+    SetFirmwareSlot(FirmwareSlot),
+    SetUpdateTarget(usize),
+    BrowseFirmwareFile,
+    QueryFirmwareInfo,
+    FirmwareInfoReceived(Option<SlaveFirmwareInfo>),
+    FirmwareValidated(PathBuf, FirmwareValidation, Option<FirmwareManifest>, Option<String>, Option<u64>),
+    AddFirmwarePart(PathBuf),
+    RemoveFirmwarePart(usize),
+    SetFirmwarePartTarget(usize, FirmwareTarget),
+    FirmwarePartStarted(usize),
+    FirmwarePartFinished(usize, Result<(), String>),
+    SetReinstallConfirmed(bool),
+    FilesDropped(Vec<PathBuf>),
+    SetValidationOverridden(bool),
+    SetBoardMismatchOverridden(bool),
+    FirmwareSidecarChecked(PathBuf, Option<FirmwareSidecarCheck>),
+    SetSidecarMismatchOverridden(bool),
+    SetFirmwareUrl(String),
+    StartDownload,
+    FirmwareDownloadProgressUpdated(u64, Option<u64>),
+    FirmwareDownloadFinished(Result<PathBuf, String>),
+    FirmwareDigestComputed(PathBuf, u64, String),
+    SelectRecentFirmware(usize),
+    RemoveRecentFirmware(usize),
+    SetVerifyAfterUpdate(bool),
+    SetAckedTransfer(bool),
+    SetBackupBeforeUpdate(bool),
+    StartBackup,
+    FirmwareBackupProgressUpdated(u64, u64),
+    FirmwareBackupFinished(Result<PathBuf, String>),
+    SetChunkSize(usize),
+    SetChunkDelay(Duration),
+    SetWriteRetryLimit(u32),
+    SetArmingCheckSkipped(bool),
+    DisarmVehicle,
+    ShowUpdateHistory,
+    CancelUpdate,
+    SetTransferPaused(bool),
+    FirmwarePauseTimedOut(Instant),
+    CloseRequested,
+    AbortAndClose,
+    DisarmFinished(Result<SlaveFirmwareInfo, String>),
+    FirmwareRebootPhaseChanged(FirmwareRebootPhase),
+    FirmwareDigestChecked(FirmwareDigestCheck),
+    FirmwareWriteProgressUpdated(f64),
+    FirmwareVerificationFinished(Result<SlaveFirmwareInfo, String>),
+    SlaveConnectionChanged(Option<TcpStream>),
+    CapabilitiesReceived(Capabilities),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirmwareValidation {
+    Valid(String),
+    Warning(String),
+    Invalid(String),
+    Empty, // 文件不包含任何数据，无法忽略
+    Unreadable(String), // 文件不存在或没有读取权限，无法忽略
+}
+
+impl FirmwareValidation {
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            FirmwareValidation::Valid(_) => "emblem-ok-symbolic",
+            FirmwareValidation::Warning(_) => "dialog-warning-symbolic",
+            FirmwareValidation::Invalid(_) | FirmwareValidation::Empty | FirmwareValidation::Unreadable(_) => "dialog-error-symbolic",
+        }
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, FirmwareValidation::Invalid(_) | FirmwareValidation::Empty | FirmwareValidation::Unreadable(_))
+    }
+
+    /// 校验错误可由用户选择忽略，空文件与无法读取的文件除外
+    pub fn is_overridable(&self) -> bool {
+        matches!(self, FirmwareValidation::Invalid(_))
+    }
+
+    fn map_message<F: FnOnce(String) -> String>(self, f: F) -> Self {
+        match self {
+            FirmwareValidation::Valid(msg) => FirmwareValidation::Valid(f(msg)),
+            FirmwareValidation::Warning(msg) => FirmwareValidation::Warning(f(msg)),
+            FirmwareValidation::Invalid(msg) => FirmwareValidation::Invalid(f(msg)),
+            FirmwareValidation::Empty => FirmwareValidation::Empty,
+            FirmwareValidation::Unreadable(msg) => FirmwareValidation::Unreadable(msg),
+        }
+    }
+}
+
+impl ToString for FirmwareValidation {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareValidation::Valid(msg) | FirmwareValidation::Warning(msg) | FirmwareValidation::Invalid(msg) => msg.clone(),
+            FirmwareValidation::Empty => "文件为空，没有可写入的数据".to_string(),
+            FirmwareValidation::Unreadable(msg) => format!("无法读取固件文件：{}", msg),
+        }
+    }
+}
+
+/// 打开文件并读取元数据，确认文件存在、可读且不是文件夹
+fn check_firmware_readable(path: &Path) -> Result<(), IOError> {
+    let metadata = std::fs::File::open(path)?.metadata()?;
+    if metadata.is_file() {
+        Ok(())
+    } else {
+        Err(IOError::new(ErrorKind::InvalidInput, "不是文件"))
+    }
+}
+
+/// 与固件文件同名的校验文件（如 `firmware.bin.sha256`）的校验结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirmwareSidecarCheck {
+    Matched { algorithm: &'static str, file_name: String },
+    Mismatch { algorithm: &'static str, file_name: String, expected: String, actual: String },
+    Invalid { file_name: String, error: String }, // 校验文件无法读取或不包含该固件的校验值
+}
+
+impl FirmwareSidecarCheck {
+    fn is_mismatch(&self) -> bool {
+        matches!(self, FirmwareSidecarCheck::Mismatch { .. })
+    }
+
+    fn icon_name(&self) -> &'static str {
+        match self {
+            FirmwareSidecarCheck::Matched { .. } => "emblem-ok-symbolic",
+            FirmwareSidecarCheck::Mismatch { .. } => "dialog-error-symbolic",
+            FirmwareSidecarCheck::Invalid { .. } => "dialog-warning-symbolic",
+        }
+    }
+}
+
+impl ToString for FirmwareSidecarCheck {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareSidecarCheck::Matched { algorithm, file_name } => format!("与 {} 中的 {} 校验值一致", file_name, algorithm),
+            FirmwareSidecarCheck::Mismatch { algorithm, file_name, expected, actual } => format!("与 {} 中的 {} 校验值不符，文件可能已损坏或下载不完整\n期望 {}\n实际 {}", file_name, algorithm, expected, actual),
+            FirmwareSidecarCheck::Invalid { file_name, error } => format!("无法使用 {}：{}", file_name, error),
+        }
+    }
+}
+
+const FIRMWARE_SIDECAR_ALGORITHMS: [(&str, usize); 2] = [("sha256", 64), ("md5", 32)]; // (扩展名, 十六进制校验值长度)，按优先级排列
+
+/// 从校验文件中取出 `file_name` 的校验值，支持 `sha256sum` 等工具输出的“校验值 文件名”格式（文件名前可带表示二进制模式的 `*`）、
+/// BSD 风格的“SHA256 (文件名) = 校验值”格式与只包含校验值的格式；只有一行记录时不要求文件名一致
+fn parse_checksum_file(content: &str, file_name: &str, digest_len: usize) -> Option<String> {
+    let is_digest = |text: &str| text.len() == digest_len && text.chars().all(|c| c.is_ascii_hexdigit());
+    let entries: Vec<(Option<&str>, &str)> = content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            if let Some((name, digest)) = line.split_once(") = ").filter(|(name, _)| name.contains(" (")) {
+                let name = name.split_once(" (").map(|(_, name)| name)?;
+                return is_digest(digest.trim()).then(|| (Some(name), digest.trim()));
+            }
+            match line.split_once(char::is_whitespace) {
+                Some((digest, name)) => is_digest(digest).then(|| (Some(name.trim().trim_start_matches('*')), digest)),
+                None => is_digest(line).then(|| (None, line)),
+            }
+        })
+        .collect();
+    let digest = match entries.as_slice() {
+        [(_, digest)] => Some(*digest),
+        entries => entries.iter().find(|(name, _)| name.map_or(false, |name| Path::new(name).file_name().map_or(false, |name| name == file_name))).map(|(_, digest)| *digest),
+    };
+    digest.map(str::to_ascii_lowercase)
+}
+
+/// 查找与固件同名的校验文件（`firmware.bin.sha256` 或 `firmware.sha256`）并校验固件文件本身（不做格式转换），没有校验文件时返回 `None`
+fn check_firmware_sidecar(path: &Path) -> Option<FirmwareSidecarCheck> {
+    let file_name = path.file_name()?.to_str()?;
+    let (sidecar, algorithm, digest_len) = FIRMWARE_SIDECAR_ALGORITHMS.iter().find_map(|&(algorithm, digest_len)| {
+        [path.with_file_name(format!("{}.{}", file_name, algorithm)), path.with_extension(algorithm)].into_iter()
+            .find(|sidecar| sidecar.is_file())
+            .map(|sidecar| (sidecar, algorithm, digest_len))
+    })?;
+    let sidecar_name = sidecar.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+    let invalid = |error: String| FirmwareSidecarCheck::Invalid { file_name: sidecar_name.clone(), error };
+    let expected = match std::fs::read_to_string(&sidecar) {
+        Ok(content) => match parse_checksum_file(&content, file_name, digest_len) {
+            Some(expected) => expected,
+            None => return Some(invalid(format!("未找到 {} 的 {} 校验值", file_name, algorithm))),
+        },
+        Err(err) => return Some(invalid(err.to_string())),
+    };
+    let actual = (|| -> Result<String, IOError> {
+        let mut file = std::fs::File::open(path)?;
+        Ok(if algorithm == "sha256" {
+            let mut sha256 = Sha256::new();
+            std::io::copy(&mut file, &mut sha256)?;
+            format!("{:x}", sha256.finalize())
+        } else {
+            let mut md5_context = md5::Context::new();
+            std::io::copy(&mut file, &mut md5_context)?;
+            format!("{:x}", md5_context.compute())
+        })
+    })();
+    Some(match actual {
+        Ok(actual) if actual == expected => FirmwareSidecarCheck::Matched { algorithm, file_name: sidecar_name },
+        Ok(actual) => FirmwareSidecarCheck::Mismatch { algorithm, file_name: sidecar_name, expected, actual },
+        Err(err) => invalid(err.to_string()),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FirmwareFormat {
+    Binary, IntelHex, Container,
+}
+
+impl ToString for FirmwareFormat {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareFormat::Binary => "二进制",
+            FirmwareFormat::IntelHex => "Intel HEX",
+            FirmwareFormat::Container => "固件包",
+        }.to_string()
+    }
+}
+
+impl FirmwareFormat {
+    fn from_path(path: &std::path::Path) -> FirmwareFormat {
+        match path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase).as_deref() {
+            Some("hex") | Some("ihex") => FirmwareFormat::IntelHex,
+            Some("rovfw") => FirmwareFormat::Container,
+            _ => FirmwareFormat::Binary,
+        }
+    }
+}
+
+/// 解析 Intel HEX 文件并拼接为从最低地址开始的连续二进制镜像，记录校验和错误或地址不连续时返回错误
+fn parse_intel_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut segments: Vec<(u32, Vec<u8>)> = Vec::new();
+    let mut base_address = 0u32;
+    for (line_index, line) in text.lines().enumerate().map(|(index, line)| (index + 1, line.trim())).filter(|(_, line)| !line.is_empty()) {
+        let record = line.strip_prefix(':').ok_or_else(|| format!("第 {} 行不是有效的 HEX 记录", line_index))?;
+        if record.len() % 2 != 0 || record.len() < 10 {
+            return Err(format!("第 {} 行的记录长度无效", line_index));
+        }
+        let bytes = (0..record.len()).step_by(2)
+            .map(|index| u8::from_str_radix(&record[index..index + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| format!("第 {} 行包含非十六进制字符", line_index))?;
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(format!("第 {} 行校验和错误", line_index));
+        }
+        let len = bytes[0] as usize;
+        if bytes.len() != len + 5 {
+            return Err(format!("第 {} 行的数据长度与记录不符", line_index));
+        }
+        let address = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..4 + len];
+        match bytes[3] {
+            0x00 => segments.push((base_address.wrapping_add(address), data.to_vec())),
+            0x01 => break,
+            0x02 if len == 2 => base_address = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+            0x04 if len == 2 => base_address = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16,
+            0x03 | 0x05 => (), // 起始地址记录与镜像内容无关
+            record_type => return Err(format!("第 {} 行包含无法识别的记录类型 {:02X}", line_index, record_type)),
+        }
+    }
+    segments.sort_by_key(|(address, _)| *address);
+    let mut image = Vec::new();
+    let mut next_address = None;
+    for (address, data) in segments {
+        match next_address {
+            Some(next_address) if address != next_address => return Err(format!("镜像在地址 0x{:08X} 处不连续", next_address)),
+            _ => (),
+        }
+        next_address = Some(address.checked_add(data.len() as u32).ok_or_else(|| format!("地址 0x{:08X} 处的数据超出 32 位地址空间", address))?);
+        image.extend(data);
+    }
+    Ok(image)
+}
+
+const FIRMWARE_CONTAINER_MAGIC: &[u8] = b"ROVFW001";
+const FIRMWARE_CONTAINER_HEADER_SIZE: usize = 4096;
+
+/// 固件包文件头中的描述信息。
+///
+/// 固件包由固定 `FIRMWARE_CONTAINER_HEADER_SIZE` 字节的文件头与固件镜像拼接而成，文件头以 `FIRMWARE_CONTAINER_MAGIC` 开头，
+/// 随后为 JSON 格式的描述信息，其余部分以 `\0` 填充。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareManifest {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub board: String,
+    #[serde(default)]
+    pub build_date: String,
+    #[serde(default)]
+    pub changelog: String,
+}
+
+/// 读取固件包的文件头，不以魔数开头的普通文件返回 `None`
+fn read_firmware_manifest(path: &std::path::Path) -> Result<Option<FirmwareManifest>, IOError> {
+    let mut header = Vec::with_capacity(FIRMWARE_CONTAINER_HEADER_SIZE);
+    std::fs::File::open(path)?.take(FIRMWARE_CONTAINER_HEADER_SIZE as u64).read_to_end(&mut header)?;
+    if !header.starts_with(FIRMWARE_CONTAINER_MAGIC) {
+        return Ok(None);
+    }
+    if header.len() < FIRMWARE_CONTAINER_HEADER_SIZE {
+        return Err(IOError::new(ErrorKind::UnexpectedEof, "固件包的文件头不完整"));
+    }
+    let json = header[FIRMWARE_CONTAINER_MAGIC.len()..].split(|byte| *byte == 0).next().unwrap();
+    serde_json::from_slice(json).map(Some)
+        .map_err(|err| IOError::new(ErrorKind::InvalidData, format!("无法解析固件包的文件头：{}", err)))
+}
+
+/// 固件的数据来源，二进制文件在传输时直接从磁盘流式读取（跳过固件包的文件头），Intel HEX 文件需要整体解析，体积较小，转换后的镜像保存在内存中
+#[derive(Debug)]
+enum FirmwareSource {
+    File { path: PathBuf, offset: u64 },
+    Image(Vec<u8>),
+}
+
+impl FirmwareSource {
+    async fn open(path: &std::path::Path) -> Result<(FirmwareFormat, FirmwareSource, Option<FirmwareManifest>), IOError> {
+        match FirmwareFormat::from_path(path) {
+            FirmwareFormat::IntelHex => {
+                let text = async_std::fs::read_to_string(path).await?;
+                let image = task::spawn_blocking(move || parse_intel_hex(&text)).await
+                    .map_err(|err| IOError::new(ErrorKind::InvalidData, format!("无法解析 Intel HEX 文件：{}", err)))?;
+                Ok((FirmwareFormat::IntelHex, FirmwareSource::Image(image), None))
+            },
+            format => {
+                let path = path.to_path_buf();
+                let manifest_path = path.clone();
+                match (format, task::spawn_blocking(move || read_firmware_manifest(&manifest_path)).await?) {
+                    (_, Some(manifest)) => Ok((FirmwareFormat::Container, FirmwareSource::File { path, offset: FIRMWARE_CONTAINER_HEADER_SIZE as u64 }, Some(manifest))),
+                    (FirmwareFormat::Container, None) => Err(IOError::new(ErrorKind::InvalidData, "文件不是有效的固件包")),
+                    (format, None) => Ok((format, FirmwareSource::File { path, offset: 0 }, None)),
+                }
+            },
+        }
+    }
+
+    /// 以阻塞方式读取固件，应在 `task::spawn_blocking` 中调用
+    fn reader(&self) -> Result<Box<dyn Read + Send>, IOError> {
+        match self {
+            FirmwareSource::File { path, offset } => {
+                let mut file = std::fs::File::open(path)?;
+                file.seek(SeekFrom::Start(*offset))?;
+                Ok(Box::new(std::io::BufReader::new(file)))
+            },
+            FirmwareSource::Image(image) => Ok(Box::new(std::io::Cursor::new(image.clone()))),
+        }
+    }
+
+    fn len(&self) -> Result<u64, IOError> {
+        match self {
+            FirmwareSource::File { path, offset } => Ok(std::fs::metadata(path)?.len().saturating_sub(*offset)),
+            FirmwareSource::Image(image) => Ok(image.len() as u64),
+        }
+    }
+}
+
+/// 仅统计写入字节数的 `Write`，用于在不保存压缩结果的情况下得到压缩后的大小
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FirmwareDigest {
+    size: u64,
+    md5: String,
+    sha256: Option<String>,
+    compressed_size: Option<u64>,
+}
+
+/// 分块读取一遍固件，计算大小与校验值（均针对未压缩的固件），需要时同时计算压缩后的大小
+fn digest_firmware(source: &FirmwareSource, use_sha256: bool, compress: bool) -> Result<FirmwareDigest, IOError> {
+    let mut reader = source.reader()?;
+    let mut md5_context = md5::Context::new();
+    let mut sha256 = use_sha256.then(Sha256::new);
+    let mut encoder = compress.then(|| GzEncoder::new(ByteCounter(0), flate2::Compression::best()));
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        let chunk = &buf[..len];
+        size += len as u64;
+        md5_context.consume(chunk);
+        if let Some(sha256) = sha256.as_mut() {
+            sha256.update(chunk);
+        }
+        if let Some(encoder) = encoder.as_mut() {
+            encoder.write_all(chunk)?;
+        }
+    }
+    Ok(FirmwareDigest {
+        size,
+        md5: format!("{:x}", md5_context.compute()),
+        sha256: sha256.map(|sha256| format!("{:x}", sha256.finalize())),
+        compressed_size: encoder.map(|encoder| encoder.finish().map(|counter| counter.0)).transpose()?,
+    })
+}
+
+/// 校验值缓存的键，路径、修改时间与大小均未变化时认为文件未被修改
+type FirmwareDigestKey = (PathBuf, Option<SystemTime>, u64);
+
+static FIRMWARE_DIGEST_CACHE: Lazy<Mutex<HashMap<FirmwareDigestKey, FirmwareDigest>>> = Lazy::new(Default::default);
+
+fn firmware_digest_key(path: &Path) -> Option<FirmwareDigestKey> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((path.to_path_buf(), metadata.modified().ok(), metadata.len()))
+}
+
+/// 查找包含所需校验值的缓存
+fn cached_firmware_digest(path: &Path, use_sha256: bool, compress: bool) -> Option<FirmwareDigest> {
+    let key = firmware_digest_key(path)?;
+    FIRMWARE_DIGEST_CACHE.lock().unwrap().get(&key)
+        .filter(|digest| (!use_sha256 || digest.sha256.is_some()) && (!compress || digest.compressed_size.is_some()))
+        .cloned()
+}
+
+/// 与 `digest_firmware` 相同，但重复写入同一文件时直接使用缓存的结果；键在读取前获取，读取期间文件被修改时缓存不会命中
+fn digest_firmware_cached(path: &Path, source: &FirmwareSource, use_sha256: bool, compress: bool) -> Result<FirmwareDigest, IOError> {
+    if let Some(digest) = cached_firmware_digest(path, use_sha256, compress) {
+        return Ok(digest);
+    }
+    let key = firmware_digest_key(path);
+    let digest = digest_firmware(source, use_sha256, compress)?;
+    if let Some(key) = key {
+        FIRMWARE_DIGEST_CACHE.lock().unwrap().insert(key, digest.clone());
+    }
+    Ok(digest)
+}
+
+const FIRMWARE_FILE_SUFFIXES: [&str; 3] = ["bin", "hex", "rovfw"];
+const SBC_IMAGE_FILE_SUFFIXES: [&str; 1] = ["img.gz"];
+const SBC_IMAGE_CHUNK_SIZE: usize = 16 * 1024;
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// 下位机通过 `update_targets` 报告的更新对象名称
+fn update_target_name(target: &str) -> String {
+    match target {
+        "mcu" => "主控单片机（MCU）".to_string(),
+        "sbc" => "伴随计算机（SBC）".to_string(),
+        target => target.to_string(),
+    }
+}
+
+/// 更新对象可接受的文件后缀，伴随计算机使用压缩的系统镜像
+fn update_target_file_suffixes(target: Option<&str>) -> &'static [&'static str] {
+    match target {
+        Some("sbc") => &SBC_IMAGE_FILE_SUFFIXES,
+        _ => &FIRMWARE_FILE_SUFFIXES,
+    }
+}
+
+/// 更新对象的默认数据块大小，系统镜像较大且伴随计算机写入较快，使用更大的数据块
+fn update_target_chunk_size(target: Option<&str>) -> usize {
+    match target {
+        Some("sbc") => SBC_IMAGE_CHUNK_SIZE,
+        _ => FIRMWARE_CHUNK_SIZE,
+    }
+}
+
+fn firmware_file_suffix_matches(path: &Path, suffixes: &[&str]) -> bool {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_lowercase();
+    suffixes.iter().any(|suffix| file_name.ends_with(&format!(".{}", suffix)))
+}
+const FIRMWARE_MIN_SIZE: u64 = 256;
+const FIRMWARE_MAX_SIZE: u64 = 16 * 1024 * 1024 * 1024;
+const FIRMWARE_HEADER_SIZE: usize = 64;
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELF_MACHINE_OFFSET: usize = 18;
+
+/// 检查固件大小，并根据文件头识别 Gzip 压缩镜像、ELF 可执行文件或 ARM Cortex-M 向量表（栈顶指针位于 SRAM 且复位向量为 Thumb 地址）
+fn validate_firmware(bytes: &[u8], size: u64) -> FirmwareValidation {
+    if size == 0 {
+        return FirmwareValidation::Empty;
+    }
+    if size < FIRMWARE_MIN_SIZE || size > FIRMWARE_MAX_SIZE || bytes.len() < FIRMWARE_HEADER_SIZE {
+        return FirmwareValidation::Invalid(format!("固件大小 {} 字节超出有效范围（{} ~ {} 字节）", size, FIRMWARE_MIN_SIZE, FIRMWARE_MAX_SIZE));
+    }
+    if bytes.starts_with(GZIP_MAGIC) {
+        return FirmwareValidation::Valid("Gzip 压缩镜像".to_string());
+    }
+    if bytes.starts_with(ELF_MAGIC) {
+        let machine = u16::from_le_bytes([bytes[ELF_MACHINE_OFFSET], bytes[ELF_MACHINE_OFFSET + 1]]);
+        return match machine {
+            40 => FirmwareValidation::Valid("ELF 可执行文件（ARM）".to_string()),
+            183 => FirmwareValidation::Valid("ELF 可执行文件（AArch64）".to_string()),
+            machine => FirmwareValidation::Warning(format!("ELF 可执行文件的目标架构（e_machine = {}）可能与下位机不符", machine)),
+        };
+    }
+    let stack_pointer = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let reset_vector = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if stack_pointer & 0xFF00_0000 == 0x2000_0000 && reset_vector & 1 == 1 {
+        FirmwareValidation::Valid(format!("ARM Cortex-M 固件（复位向量 0x{:08X}）", reset_vector))
+    } else {
+        FirmwareValidation::Invalid("无法识别固件文件头，可能不是下位机固件".to_string())
+    }
+}
+
+#[derive(EnumIter, PartialEq, Clone, Copy, Debug)]
+pub enum FirmwareCompression {
+    Auto, None, Gzip,
+}
+
+impl ToString for FirmwareCompression {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareCompression::Auto => "自动",
+            FirmwareCompression::None => "不压缩",
+            FirmwareCompression::Gzip => "Gzip",
+        }.to_string()
+    }
+}
+
+impl Default for FirmwareCompression {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl FirmwareCompression {
+    /// 根据压缩后的大小决定实际使用的压缩方式，自动模式下仅在压缩后体积更小时使用 Gzip
+    fn resolve(&self, size: u64, compressed_size: Option<u64>) -> SlaveFirmwareCompression {
+        match (self, compressed_size) {
+            (FirmwareCompression::Gzip, _) => SlaveFirmwareCompression::Gzip,
+            (FirmwareCompression::Auto, Some(compressed_size)) if compressed_size < size => SlaveFirmwareCompression::Gzip,
+            _ => SlaveFirmwareCompression::None,
+        }
+    }
+}
+
+/// 数据包中声明的固件压缩方式，序列化为 "none"、"gzip" 或 "lz4"
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SlaveFirmwareCompression {
+    None, Gzip, Lz4,
+}
+
+/// 双分区下位机写入的目标分区，自动模式下由下位机选择当前未运行的分区
+#[derive(EnumIter, PartialEq, Clone, Copy, Debug)]
+pub enum FirmwareSlot {
+    Auto, A, B,
+}
+
+impl ToString for FirmwareSlot {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareSlot::Auto => "自动",
+            FirmwareSlot::A => "A 分区",
+            FirmwareSlot::B => "B 分区",
+        }.to_string()
+    }
+}
+
+impl Default for FirmwareSlot {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+impl FirmwareSlot {
+    fn target_slot(&self) -> Option<&'static str> {
+        match self {
+            FirmwareSlot::Auto => None,
+            FirmwareSlot::A => Some("a"),
+            FirmwareSlot::B => Some("b"),
+        }
+    }
+}
+
+/// 写入完成后比较下位机闪存中固件校验值的结果，不一致时作为更新失败处理
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirmwareDigestCheck {
+    Matched(&'static str), // 参与比较的校验算法
+    Unavailable,
+}
+
+/// 更新完成后等待下位机重启的阶段
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FirmwareRebootPhase {
+    Disconnecting, Rebooting, Online,
+}
+
+impl ToString for FirmwareRebootPhase {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareRebootPhase::Disconnecting => "正在等待下位机重启...",
+            FirmwareRebootPhase::Rebooting => "下位机正在重启...",
+            FirmwareRebootPhase::Online => "下位机已重新上线",
+        }.to_string()
+    }
+}
+
+/// 写入目标，决定下位机将数据写入何处
+#[derive(EnumIter, PartialEq, Clone, Copy, Debug)]
+pub enum FirmwareTarget {
+    Firmware, Filesystem,
+}
+
+impl ToString for FirmwareTarget {
+    fn to_string(&self) -> String {
+        match self {
+            FirmwareTarget::Firmware => "固件",
+            FirmwareTarget::Filesystem => "文件系统镜像",
+        }.to_string()
+    }
+}
+
+impl FirmwareTarget {
+    fn packet_value(&self) -> &'static str {
+        match self {
+            FirmwareTarget::Firmware => "firmware",
+            FirmwareTarget::Filesystem => "filesystem",
+        }
+    }
+}
+
+const FIRMWARE_UPDATER_LAST_PAGE: u32 = 3; // 欢迎页、文件选择页、进度页、结果页
+
+/// `NextStep` 后的页面，已在结果页时保持不变
+fn next_firmware_updater_page(page: u32) -> u32 {
+    (page + 1).min(FIRMWARE_UPDATER_LAST_PAGE)
+}
+
+/// `PreviousStep` 后的页面：文件选择页返回欢迎页，进度页与结果页均返回文件选择页
+fn previous_firmware_updater_page(page: u32) -> u32 {
+    if page > 1 { 1 } else { 0 }
+}
+
+const FIRMWARE_CHUNK_SIZE: usize = 1024;
+const FIRMWARE_CHUNK_SIZE_MIN: usize = 256;
+const FIRMWARE_CHUNK_SIZE_MAX: usize = 64 * 1024;
+const FIRMWARE_CHUNK_DELAY_MAX: Duration = Duration::from_secs(1);
+const FIRMWARE_WRITE_RETRY_DEFAULT: u32 = 3;
+const FIRMWARE_WRITE_RETRY_MAX: u32 = 10;
+const FIRMWARE_WRITE_BACKOFF_INITIAL: Duration = Duration::from_millis(100);
+const FIRMWARE_WRITE_BACKOFF_MAX: Duration = Duration::from_secs(2);
+const FIRMWARE_PROGRESS_STEP: usize = 1024; // 较大的数据块按该长度分段写入并更新进度，避免进度条大幅跳动
+const FIRMWARE_PROGRESS_REPORT_INTERVAL: Duration = Duration::from_millis(50); // 上传进度消息的最短间隔，避免大量消息阻塞主循环
+const FIRMWARE_PROGRESS_SMOOTHING: f64 = 0.2; // 进度条每帧向目标进度移动剩余距离的比例
+const FIRMWARE_DRY_RUN_DURATION: Duration = Duration::from_secs(5); // 模拟更新时传输进度走完所需的时间
+const FIRMWARE_DRY_RUN_STEPS: u64 = 100;
+const FIRMWARE_PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100); // 暂停期间检查是否恢复传输的间隔
+const FIRMWARE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(2); // 暂停期间向支持保活的下位机发送保活数据包的间隔
+
+/// 以二进制单位（KiB、MiB、GiB）显示字节数
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if (value * 10.0).round() < 10240.0 { // 按保留一位小数后的值判断，避免显示为“1024.0 KiB”
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FirmwareUploadProgress {
+    sent: u64,
+    total: u64,
+    chunk_size: u64,
+    retries: u32, // 累计重传的数据块次数（逐块确认模式）或写入遇到暂时性错误后重试的次数
+    stalled: bool, // 流量控制模式下正在等待下位机确认
+}
+
+impl FirmwareUploadProgress {
+    fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.sent as f32 / self.total as f32).min(1.0)
+        }
+    }
+
+    fn chunks(&self, bytes: u64) -> u64 {
+        if self.chunk_size == 0 {
+            0
+        } else {
+            (bytes + self.chunk_size - 1) / self.chunk_size
+        }
+    }
+}
+
+impl ToString for FirmwareUploadProgress {
+    fn to_string(&self) -> String {
+        let description = format!("{} / {}，数据块 {}/{}", format_bytes(self.sent), format_bytes(self.total), self.chunks(self.sent), self.chunks(self.total));
+        let description = if self.retries > 0 {
+            format!("{}，已重传 {} 次", description, self.retries)
+        } else {
+            description
+        };
+        if self.stalled {
+            format!("{}，等待下位机处理…", description)
+        } else {
+            description
+        }
+    }
+}
+
+/// 以“分:秒”显示时长，超过一小时时显示“时:分:秒”
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// 限制上传进度消息的频率：仅在进度的千分比变化且距上次发送超过 `FIRMWARE_PROGRESS_REPORT_INTERVAL` 时发送，
+/// 传输完成或重试次数变化时总是发送
+struct FirmwareProgressReporter {
+    sender: Sender<SlaveFirmwareUpdaterMsg>,
+    last: Option<(Instant, u64, u32)>, // (发送时间, 千分比, 重试次数)
+}
+
+impl FirmwareProgressReporter {
+    fn new(sender: Sender<SlaveFirmwareUpdaterMsg>) -> FirmwareProgressReporter {
+        FirmwareProgressReporter { sender, last: None }
+    }
+
+    fn report(&mut self, progress: FirmwareUploadProgress) {
+        let permille = (progress.fraction() * 1000.0) as u64;
+        let due = match self.last {
+            None => true,
+            Some((instant, last_permille, retries)) => progress.sent >= progress.total || progress.retries != retries || (permille != last_permille && instant.elapsed() >= FIRMWARE_PROGRESS_REPORT_INTERVAL),
+        };
+        if due {
+            self.last = Some((Instant::now(), permille, progress.retries));
+            send!(self.sender, SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(progress));
+        }
+    }
+}
+
+const RECENT_FIRMWARE_LIMIT: usize = 8;
+
+/// 最近用于更新的固件文件，保存在应用数据目录中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentFirmwareFile {
+    path: PathBuf,
+    size: u64,
+    md5: String,
+    last_used: String,
+}
+
+fn recent_firmware_list_path() -> PathBuf {
+    get_data_path().join("recent_firmwares.json")
+}
+
+fn load_recent_firmware_files() -> Vec<RecentFirmwareFile> {
+    std::fs::read_to_string(recent_firmware_list_path()).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+fn last_firmware_directory_path() -> PathBuf {
+    get_data_path().join("last_firmware_directory")
+}
+
+/// 上次选择的固件文件所在的文件夹，用作文件选择对话框的初始位置
+fn load_last_firmware_directory() -> Option<PathBuf> {
+    std::fs::read_to_string(last_firmware_directory_path()).ok().map(|path| PathBuf::from(path.trim())).filter(|path| path.is_dir())
+}
+
+fn save_last_firmware_directory(path: &std::path::Path) {
+    if let Some(directory) = path.parent().filter(|directory| !directory.starts_with(firmware_cache_dir())) { // 下载的固件位于缓存文件夹，不记录
+        if let Err(err) = std::fs::write(last_firmware_directory_path(), directory.to_str().unwrap_or_default()) {
+            log::warn!("无法保存固件文件夹：{}", err);
+        }
+    }
+}
+
+/// 一次固件更新的记录，逐行追加保存在应用数据目录中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareUpdateRecord {
+    timestamp: String,
+    slave_address: String,
+    file_name: String,
+    size: u64,
+    md5: String, // 下位机未收到校验值（如发送前失败）时为空
+    duration: f64, // 秒
+    succeeded: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl FirmwareUpdateRecord {
+    fn description(&self) -> String {
+        let digest = if self.md5.is_empty() { String::new() } else { format!("，MD5 {}", &self.md5[..self.md5.len().min(8)]) };
+        let result = match &self.error {
+            Some(err) => format!("失败：{}", err),
+            None => "成功".to_string(),
+        };
+        format!("{}{}，用时 {:.0} 秒\n{}", format_bytes(self.size), digest, self.duration, result)
+    }
+}
+
+/// 正在进行的更新，结束时写入更新记录
+#[derive(Debug, Clone)]
+struct FirmwareUpdateAttempt {
+    started: Instant,
+    path: PathBuf,
+    size: u64,
+    md5: String,
+}
+
+fn firmware_update_history_path() -> PathBuf {
+    get_data_path().join("firmware_update_history.jsonl")
+}
+
+/// 追加一条更新记录，失败时仅记录日志，不影响更新结果
+fn append_firmware_update_record(record: &FirmwareUpdateRecord) {
+    let result = std::fs::OpenOptions::new().create(true).append(true).open(firmware_update_history_path())
+        .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(record).unwrap()));
+    if let Err(err) = result {
+        log::warn!("无法保存固件更新记录：{}", err);
+    }
+}
+
+/// 读取指定下位机的更新记录，最新的在前，无法解析的行被跳过
+fn load_firmware_update_history(slave_address: &str) -> Vec<FirmwareUpdateRecord> {
+    let history = std::fs::read_to_string(firmware_update_history_path()).unwrap_or_default();
+    let mut records: Vec<FirmwareUpdateRecord> = history.lines()
+        .filter_map(|line| serde_json::from_str::<FirmwareUpdateRecord>(line).ok())
+        .filter(|record| record.slave_address == slave_address)
+        .collect();
+    records.reverse();
+    records
+}
+
+fn firmware_update_history_dialog<T: IsA<gtk::Window>>(slave_address: &str, records: &[FirmwareUpdateRecord], window: &T) -> Dialog {
+    relm4_macros::view! {
+        group = PreferencesGroup {
+            set_title: &format!("下位机 {}", slave_address),
+            set_description: if records.is_empty() { Some("暂无该下位机的固件更新记录") } else { None },
+        }
+    }
+    for record in records {
+        relm4_macros::view! {
+            row = ActionRow {
+                set_title: &format!("{} · {}", record.timestamp, record.file_name),
+                set_subtitle: &record.description(),
+                set_subtitle_lines: 0,
+                add_prefix = &Image {
+                    set_icon_name: Some(if record.succeeded { "emblem-ok-symbolic" } else { "dialog-warning-symbolic" }),
+                },
+            }
+        }
+        group.add(&row);
+    }
+    relm4_macros::view! {
+        dialog = Dialog {
+            set_title: Some("固件更新记录"),
+            set_modal: true,
+            set_transient_for: Some(window),
+            set_default_width: 480,
+            add_button: args!("关闭", ResponseType::Close),
+            connect_response => move |dialog, _response| {
+                dialog.destroy();
+            }
+        }
+    }
+    relm4_macros::view! {
+        scrolled_window = ScrolledWindow {
+            set_vexpand: true,
+            set_min_content_height: 360,
+            set_child = Some(&Clamp) {
+                set_margin_top: 12,
+                set_margin_bottom: 12,
+                set_margin_start: 12,
+                set_margin_end: 12,
+                set_child: Some(&group),
+            },
+        }
+    }
+    dialog.content_area().append(&scrolled_window);
+    dialog.show();
+    dialog
+}
+
+fn save_recent_firmware_files(files: &[RecentFirmwareFile]) {
+    if let Err(err) = std::fs::write(recent_firmware_list_path(), serde_json::to_string_pretty(files).unwrap()) {
+        log::warn!("无法保存最近使用的固件列表：{}", err);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentFirmwareModel {
+    file: RecentFirmwareFile,
+    available: bool, // 文件已被移动或删除时为 `false`
+}
+
+impl RecentFirmwareModel {
+    fn new(file: RecentFirmwareFile) -> RecentFirmwareModel {
+        RecentFirmwareModel { available: file.path.is_file(), file }
+    }
+
+    fn description(&self) -> String {
+        if self.available {
+            format!("{}，MD5 {}，上次使用于 {}", format_bytes(self.file.size), &self.file.md5[..self.file.md5.len().min(8)], self.file.last_used)
+        } else {
+            format!("文件已被移动或删除：{}", self.file.path.to_str().unwrap_or_default())
+        }
+    }
+}
+
+#[relm4::factory_prototype(pub)]
+impl FactoryPrototype for RecentFirmwareModel {
+    type Factory = FactoryVec<Self>;
+    type Widgets = RecentFirmwareWidgets;
+    type View = GtkBox;
+    type Msg = SlaveFirmwareUpdaterMsg;
+
+    view! {
+        row = ActionRow {
+            set_title: self.file.path.file_name().and_then(|name| name.to_str()).unwrap_or_default(),
+            set_subtitle: &self.description(),
+            set_tooltip_text: self.file.path.to_str(),
+            set_activatable: self.available,
+            set_opacity: if self.available { 1.0 } else { 0.5 },
+            add_prefix = &Image {
+                set_icon_name: Some("document-open-recent-symbolic"),
+            },
+            add_suffix = &Button {
+                set_icon_name: "user-trash-symbolic",
+                set_css_classes: &["flat"],
+                set_valign: Align::Center,
+                set_tooltip_text: Some("从列表中移除"),
+                connect_clicked(sender, key) => move |_button| {
+                    send!(sender, SlaveFirmwareUpdaterMsg::RemoveRecentFirmware(key));
+                },
+            },
+            connect_activated(sender, key) => move |_row| {
+                send!(sender, SlaveFirmwareUpdaterMsg::SelectRecentFirmware(key));
+            },
+        }
+    }
+
+    fn position(&self, _index: &usize) {
+
+    }
+}
+
+/// 在所选固件之后依次写入的附加镜像
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirmwarePartModel {
+    path: PathBuf,
+    target: FirmwareTarget,
+}
+
+#[relm4::factory_prototype(pub)]
+impl FactoryPrototype for FirmwarePartModel {
+    type Factory = FactoryVec<Self>;
+    type Widgets = FirmwarePartWidgets;
+    type View = GtkBox;
+    type Msg = SlaveFirmwareUpdaterMsg;
+
+    view! {
+        row = ActionRow {
+            set_title: self.path.file_name().and_then(|name| name.to_str()).unwrap_or_default(),
+            set_subtitle: self.path.to_str().unwrap_or_default(),
+            add_prefix = &Image {
+                set_icon_name: Some("drive-harddisk-symbolic"),
+            },
+            add_suffix = &DropDown::from_strings(&FirmwareTarget::iter().map(|target| target.to_string()).collect::<Vec<_>>().iter().map(String::as_str).collect::<Vec<_>>()) {
+                set_valign: Align::Center,
+                set_selected: FirmwareTarget::iter().position(|target| target == self.target).unwrap_or_default() as u32,
+                connect_selected_notify(sender, key) => move |drop_down| {
+                    if let Some(target) = FirmwareTarget::iter().nth(drop_down.selected() as usize) {
+                        send!(sender, SlaveFirmwareUpdaterMsg::SetFirmwarePartTarget(key, target));
+                    }
+                },
+            },
+            add_suffix = &Button {
+                set_icon_name: "user-trash-symbolic",
+                set_css_classes: &["flat"],
+                set_valign: Align::Center,
+                set_tooltip_text: Some("移除"),
+                connect_clicked(sender, key) => move |_button| {
+                    send!(sender, SlaveFirmwareUpdaterMsg::RemoveFirmwarePart(key));
+                },
+            },
+        }
+    }
+
+    fn position(&self, _index: &usize) {
+
+    }
 }
 
 #[tracker::track(pub)]
@@ -51,8 +1062,382 @@ pub struct SlaveFirmwareUpdaterModel {
     current_page: u32,
     firmware_file_path: Option<PathBuf>,
     firmware_uploading_progress: f32,
+    firmware_uploading_details: FirmwareUploadProgress,
+    firmware_url: String,
+    firmware_downloading: bool,
+    #[no_eq]
+    download_task: Option<task::JoinHandle<()>>, // 返回上一步时用于取消下载
+    #[no_eq]
+    update_task: Option<task::JoinHandle<()>>, // 正在进行的上传或重启验证，用于中止更新
+    #[no_eq]
+    close_prompted: bool, // 更新期间关闭窗口，需要弹出对话框确认是否中止
+    closing: bool,
+    firmware_download_progress: (u64, Option<u64>), // (已接收字节数, 总字节数)
+    compression: FirmwareCompression,
+    firmware_slot: FirmwareSlot,
+    firmware_written_slot: Option<String>, // 本次更新写入的分区，下位机不支持双分区时为 `None`
+    update_targets: Vec<String>, // 下位机报告的更新对象，仅在变化时更新，避免重建选择列表
+    update_target: Option<String>, // 下位机未报告更新对象时为 `None`，数据包中不声明更新对象
     #[no_eq]
-    _tcp_stream: OnceCell<TcpStream>,
+    firmware_file_browsing: bool, // 设置后按更新对象的文件类型打开文件选择对话框
+    firmware_uploading: bool,
+    firmware_update_error: Option<String>,
+    device_firmware_info: Option<SlaveFirmwareInfo>,
+    firmware_info_querying: bool,
+    firmware_validation: Option<FirmwareValidation>, // 为 `None` 时表示尚未选择文件或正在校验
+    firmware_manifest: Option<FirmwareManifest>, // 选择的文件为固件包时从文件头读取的描述信息
+    firmware_md5: Option<String>, // 所选固件（不含固件包文件头）的 md5
+    firmware_image_size: Option<u64>, // 所选固件写入下位机后的大小，Gzip 压缩镜像为解压后的大小
+    firmware_preparing: bool, // 正在读取固件计算校验值，尚未开始传输
+    reinstall_confirmed: bool, // 所选固件与下位机当前固件相同时，用户确认仍要更新
+    validation_overridden: bool,
+    board_mismatch_overridden: bool, // 专家选项：固件与下位机的主板不符时仍允许更新
+    firmware_sidecar_check: Option<FirmwareSidecarCheck>, // 没有同名校验文件或尚未完成校验时为 `None`
+    sidecar_mismatch_overridden: bool, // 专家选项：固件与校验文件不符时仍允许更新
+    #[derivative(Default(value="true"))]
+    verify_after_update: bool,
+    acked_transfer: bool, // 仅在下位机声明支持时生效
+    #[derivative(Default(value="true"))]
+    backup_before_update: bool, // 仅在下位机声明支持读取固件时生效
+    firmware_backing_up: bool,
+    firmware_backup_progress: (u64, u64), // (已接收字节数, 总字节数)
+    firmware_verifying: bool,
+    firmware_reboot_phase: Option<FirmwareRebootPhase>,
+    firmware_digest_check: Option<FirmwareDigestCheck>, // 模拟更新或尚未写入完成时为 `None`
+    firmware_write_progress: Option<f64>, // 传输结束后下位机报告的闪存写入进度，下位机未报告时为 `None`
+    firmware_upload_timing: Option<(Instant, u64)>, // 当前部分收到第一个进度的时间与当时已发送的字节数（续传偏移），用于计算用时与剩余时间
+    firmware_paused_duration: Duration, // 当前部分累计暂停的时长，不计入用时
+    firmware_paused_since: Option<Instant>, // 传输暂停的时间，未暂停时为 `None`
+    firmware_pause_timed_out: bool, // 暂停时间已超过下位机报告的传输超时
+    #[no_eq]
+    firmware_transfer_paused: Arc<Mutex<bool>>, // 与上传任务共享的暂停标志，上传任务在发送每个数据块前检查
+    firmware_verification: Option<Result<SlaveFirmwareInfo, String>>, // 重启后查询到的固件信息或验证失败的原因
+    device_address: Option<SocketAddr>,
+    #[derivative(Default(value="FIRMWARE_CHUNK_SIZE"))]
+    chunk_size: usize,
+    chunk_delay: Duration, // 每个数据块发送后的等待时间，用于低速链路或下位机擦写 Flash 时限速
+    #[derivative(Default(value="FIRMWARE_WRITE_RETRY_DEFAULT"))]
+    write_retry_limit: u32, // 写入遇到暂时性错误时的最大重试次数
+    arming_check_skipped: bool, // 不检查推进器是否已上锁，仅用于不报告解锁状态的台架环境
+    #[no_eq]
+    disarm_prompted: bool, // 开始更新时推进器未确认上锁，需要弹出对话框询问是否上锁
+    #[no_eq]
+    firmware_update_attempt: Option<FirmwareUpdateAttempt>, // 模拟更新时为 `None`，不写入更新记录
+    #[no_eq]
+    update_history: Option<Vec<FirmwareUpdateRecord>>, // 设置后弹出更新记录对话框
+    disarming: bool,
+    #[no_eq]
+    #[derivative(Default(value="FactoryVec::new()"))]
+    recent_firmwares: FactoryVec<RecentFirmwareModel>,
+    #[no_eq]
+    #[derivative(Default(value="FactoryVec::new()"))]
+    extra_firmware_parts: FactoryVec<FirmwarePartModel>,
+    firmware_part_results: Vec<(String, Option<Result<(), String>>)>, // 本次更新各部分的名称与结果，结果为 `None` 表示未完成或已跳过
+    current_firmware_part: usize,
+    #[no_eq]
+    toast_messages: Rc<RefCell<VecDeque<String>>>,
+    dry_run: bool, // 模拟更新，不与下位机通信，用于培训操作人员
+    #[derivative(Default(value="FIRMWARE_DRY_RUN_DURATION"))]
+    dry_run_duration: Duration, // 模拟更新时每个部分的进度走完所需的时间
+    capabilities: Capabilities, // 下位机连接时声明的功能，未声明时为最小功能集
+    #[no_eq]
+    exclusive_tcp_activity: Rc<Cell<Option<ExclusiveTcpActivity>>>,
+    #[no_eq]
+    _tcp_stream: OnceCell<TcpStream>, // 模拟更新时为空
+}
+
+impl SlaveFirmwareUpdaterModel {
+    /// 输入的下载地址，仅接受 HTTP 与 HTTPS 协议
+    fn firmware_download_url(&self) -> Option<Url> {
+        Url::parse(self.firmware_url.trim()).ok().filter(|url| matches!(url.scheme(), "http" | "https"))
+    }
+
+    fn firmware_download_description(&self) -> String {
+        match self.firmware_download_progress {
+            (received, Some(total)) => format!("{} / {}", format_bytes(received), format_bytes(total)),
+            (received, None) => format!("已接收 {}", format_bytes(received)),
+        }
+    }
+
+    /// 固件包声明的目标主板与下位机的主板均已知且不一致时返回 (固件包主板, 下位机主板)
+    fn firmware_board_mismatch(&self) -> Option<(&str, &str)> {
+        match (&self.firmware_manifest, &self.device_firmware_info) {
+            (Some(manifest), Some(info)) if !manifest.board.is_empty() && !info.board.is_empty() && !manifest.board.eq_ignore_ascii_case(&info.board) => Some((manifest.board.as_str(), info.board.as_str())),
+            _ => None,
+        }
+    }
+
+    /// 固件或下位机未提供主板标识，无法确认固件适用于该下位机
+    fn firmware_board_unverified(&self) -> bool {
+        let firmware_board = self.firmware_manifest.as_ref().map_or(true, |manifest| manifest.board.is_empty());
+        let device_board = self.device_firmware_info.as_ref().map_or(true, |info| info.board.is_empty());
+        firmware_board || device_board
+    }
+
+    /// 固件超出下位机可写入分区的大小时返回 (固件大小, 分区大小)
+    fn firmware_capacity_exceeded(&self) -> Option<(u64, u64)> {
+        let size = self.firmware_image_size?;
+        let capacity = self.device_firmware_info.as_ref().map(|info| info.partition_size).filter(|capacity| *capacity > 0)?;
+        (size > capacity).then(|| (size, capacity))
+    }
+
+    fn firmware_capacity_description(&self) -> String {
+        let capacity = self.device_firmware_info.as_ref().map(|info| info.partition_size).filter(|capacity| *capacity > 0);
+        match (self.firmware_image_size, capacity) {
+            (Some(size), Some(capacity)) if size > capacity => format!("固件大小 {} 超出下位机可写入分区的大小 {}，无法写入，请确认固件与更新对象", format_bytes(size), format_bytes(capacity)),
+            (Some(size), Some(capacity)) => format!("固件 {}，可写入分区 {}", format_bytes(size), format_bytes(capacity)),
+            (_, None) => "下位机未报告可写入分区的大小，无法确认固件能否完整写入".to_string(),
+            (None, Some(capacity)) => format!("可写入分区 {}", format_bytes(capacity)),
+        }
+    }
+
+    fn firmware_sidecar_allowed(&self) -> bool {
+        self.firmware_sidecar_check.as_ref().map_or(true, |check| !check.is_mismatch()) || self.sidecar_mismatch_overridden
+    }
+
+    fn firmware_board_allowed(&self) -> bool {
+        self.firmware_board_mismatch().is_none() || self.board_mismatch_overridden
+    }
+
+    fn device_board(&self) -> &str {
+        self.device_firmware_info.as_ref().map(|info| info.board.as_str()).filter(|board| !board.is_empty()).unwrap_or("未知")
+    }
+
+    fn firmware_manifest_field<F: Fn(&FirmwareManifest) -> &String>(&self, f: F) -> &str {
+        self.firmware_manifest.as_ref().map(f).filter(|value| !value.is_empty()).map_or("未知", String::as_str)
+    }
+
+    fn set_recent_firmware_files(&mut self, files: Vec<RecentFirmwareFile>) {
+        let recent_firmwares = self.get_mut_recent_firmwares();
+        recent_firmwares.clear();
+        for file in files {
+            recent_firmwares.push(RecentFirmwareModel::new(file));
+        }
+    }
+
+    fn recent_firmware_files(&self) -> Vec<RecentFirmwareFile> {
+        self.recent_firmwares.iter().map(|model| model.file.clone()).collect()
+    }
+
+    /// 所选固件与下位机当前运行的固件相同时返回判断依据：优先比较 md5，下位机或文件不提供 md5 时比较固件包声明的版本与构建日期
+    fn firmware_already_installed(&self) -> Option<String> {
+        let info = self.device_firmware_info.as_ref()?;
+        if let Some(md5) = self.firmware_md5.as_ref().filter(|_| !info.md5.is_empty()) {
+            return md5.eq_ignore_ascii_case(&info.md5).then(|| format!("固件的 MD5（{}）与下位机当前固件相同", md5));
+        }
+        let manifest = self.firmware_manifest.as_ref()?;
+        let same_version = !manifest.version.is_empty() && manifest.version == info.version;
+        let same_build_date = manifest.build_date.is_empty() || info.build_date.is_empty() || manifest.build_date == info.build_date;
+        (same_version && same_build_date).then(|| format!("固件版本（{}）与下位机当前固件相同", manifest.version))
+    }
+
+    /// 参数调校正在使用下位机连接，此时写入固件会与调校数据包相互穿插
+    fn parameter_tuning_active(&self) -> bool {
+        !self.dry_run && self.exclusive_tcp_activity.get() == Some(ExclusiveTcpActivity::ParameterTuning)
+    }
+
+    /// 下位机未确认推进器已上锁，此时写入固件可能导致推进器意外转动
+    fn vehicle_possibly_armed(&self) -> bool {
+        !self.dry_run && !self.arming_check_skipped && self.device_firmware_info.as_ref().and_then(|info| info.armed) != Some(false)
+    }
+
+    /// 正在进行不可中断的操作，期间不允许切换页面；下载可以通过返回上一步取消
+    fn is_uninterruptible(&self) -> bool {
+        self.firmware_backing_up || self.firmware_uploading || self.firmware_verifying
+    }
+
+    /// 下位机报告的传输超时，暂停超过该时长时下位机可能中止更新
+    fn device_transfer_timeout(&self) -> Option<Duration> {
+        self.device_firmware_info.as_ref().map(|info| info.transfer_timeout).filter(|timeout| *timeout > 0).map(|timeout| Duration::from_secs(timeout as u64))
+    }
+
+    /// 当前部分实际传输的时长，不含暂停的时间
+    fn firmware_upload_elapsed(&self) -> Option<Duration> {
+        let (started, _) = self.firmware_upload_timing?;
+        let paused = self.firmware_paused_duration + self.firmware_paused_since.map_or(Duration::ZERO, |since| since.elapsed());
+        Some(started.elapsed().saturating_sub(paused))
+    }
+
+    fn firmware_upload_timing_description(&self) -> Option<String> {
+        if self.firmware_paused_since.is_some() {
+            return Some(match (self.firmware_pause_timed_out, self.device_transfer_timeout()) {
+                (true, Some(timeout)) => format!("⚠ 已暂停超过 {} 秒，下位机可能已中止更新", timeout.as_secs()),
+                (false, Some(timeout)) => format!("已暂停，下位机在 {} 秒内未收到数据时可能中止更新", timeout.as_secs()),
+                (_, None) => "已暂停".to_string(),
+            });
+        }
+        let elapsed = self.firmware_upload_elapsed()?;
+        let (_, start) = self.firmware_upload_timing?;
+        let progress = &self.firmware_uploading_details;
+        let sent = progress.sent.saturating_sub(start);
+        if sent == 0 || progress.sent >= progress.total {
+            return Some(format!("用时 {}", format_duration(elapsed)));
+        }
+        let remaining = elapsed.mul_f64(progress.total.saturating_sub(progress.sent) as f64 / sent as f64);
+        Some(format!("用时 {}，预计剩余 {}", format_duration(elapsed), format_duration(remaining)))
+    }
+
+    /// 清除暂停状态，上传开始、结束或中止时调用
+    fn reset_transfer_pause(&mut self) {
+        *self.firmware_transfer_paused.lock().unwrap() = false;
+        self.set_firmware_paused_since(None);
+        self.set_firmware_pause_timed_out(false);
+        self.set_firmware_paused_duration(Duration::ZERO);
+        self.set_firmware_upload_timing(None);
+    }
+
+    /// 正在上传固件或等待下位机重启，关闭窗口前需要确认
+    fn update_in_progress(&self) -> bool {
+        self.firmware_uploading || self.firmware_verifying
+    }
+
+    /// 启动独占连接的更新任务：任务结束或被 `CancelUpdate` 中止后连接恢复读取
+    fn spawn_update_task<F: Future<Output = ()> + Send + 'static>(&mut self, parent_sender: &Sender<SlaveMsg>, future: F) {
+        let (finished_sender, finished_receiver) = async_std::channel::bounded::<()>(1);
+        self.update_task = Some(task::spawn(async move {
+            let _finished_sender = finished_sender; // 任务结束或被取消时随之释放
+            future.await;
+        }));
+        let blocker = task::spawn(async move {
+            finished_receiver.recv().await.unwrap_or_default();
+            Ok(())
+        });
+        send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(ExclusiveTcpActivity::FirmwareUpdate, blocker)));
+    }
+
+    fn firmware_file_suffixes(&self) -> &'static [&'static str] {
+        update_target_file_suffixes(self.update_target.as_deref())
+    }
+
+    fn firmware_dual_bank(&self) -> bool {
+        self.device_firmware_info.as_ref().map_or(false, SlaveFirmwareInfo::is_dual_bank)
+    }
+
+    /// 本次更新将写入的分区
+    fn firmware_target_slot(&self) -> Option<String> {
+        let info = self.device_firmware_info.as_ref().filter(|info| info.is_dual_bank())?;
+        self.firmware_slot.target_slot().or_else(|| info.inactive_slot()).map(str::to_string)
+    }
+
+    fn firmware_slot_description(&self) -> Option<String> {
+        let slot = self.firmware_written_slot.as_ref()?;
+        let active_slot = self.device_firmware_info.as_ref().and_then(|info| info.active_slot.as_ref());
+        Some(match active_slot {
+            Some(active_slot) if active_slot.eq_ignore_ascii_case(slot) => format!("固件已写入 {} 分区（更新前运行的分区），重启后不会切换分区。", slot.to_uppercase()),
+            Some(active_slot) => format!("固件已写入 {} 分区，重启后将从 {} 分区切换至该分区。", slot.to_uppercase(), active_slot.to_uppercase()),
+            None => format!("固件已写入 {} 分区。", slot.to_uppercase()),
+        })
+    }
+
+    fn firmware_readback_available(&self) -> bool {
+        self.device_firmware_info.as_ref().map_or(false, |info| info.readback)
+    }
+
+    fn firmware_backup_description(&self) -> String {
+        let (received, total) = self.firmware_backup_progress;
+        format!("正在备份当前固件：{} / {}", format_bytes(received), format_bytes(total))
+    }
+
+    /// 下位机声明支持 Gzip 解压时才提供压缩选项，模拟更新时总是提供
+    fn gzip_available(&self) -> bool {
+        self.dry_run || self.capabilities.supports_compression(SlaveFirmwareCompression::Gzip)
+    }
+
+    fn acked_transfer_available(&self) -> bool {
+        self.device_firmware_info.as_ref().map_or(false, SlaveFirmwareInfo::supports_acked_transfer)
+    }
+
+    /// 本次更新依次写入的文件：所选固件在前，附加镜像按添加顺序在后
+    fn firmware_parts(&self) -> Vec<(PathBuf, FirmwareTarget)> {
+        self.firmware_file_path.iter().map(|path| (path.clone(), FirmwareTarget::Firmware))
+            .chain(self.extra_firmware_parts.iter().map(|part| (part.path.clone(), part.target)))
+            .collect()
+    }
+
+    fn firmware_uploading_description(&self) -> String {
+        let details = if self.firmware_preparing {
+            "正在准备固件…".to_string()
+        } else if self.firmware_write_progress.is_some() {
+            "数据已发送，下位机正在写入闪存…".to_string()
+        } else {
+            match self.firmware_upload_timing_description() {
+                Some(timing) => format!("{}\n{}", self.firmware_uploading_details.to_string(), timing),
+                None => self.firmware_uploading_details.to_string(),
+            }
+        };
+        match self.firmware_part_results.get(self.current_firmware_part) {
+            Some((name, _)) if self.firmware_part_results.len() > 1 => format!("第 {}/{} 部分，{}\n{}", self.current_firmware_part + 1, self.firmware_part_results.len(), name, details),
+            _ => details,
+        }
+    }
+
+    /// 多个部分时列出每个部分的结果
+    fn firmware_parts_description(&self) -> Option<String> {
+        (self.firmware_part_results.len() > 1).then(|| self.firmware_part_results.iter().map(|(name, result)| match result {
+            Some(Ok(())) => format!("{}：已写入", name),
+            Some(Err(err)) => format!("{}：失败（{}）", name, err),
+            None => format!("{}：已跳过", name),
+        }).collect::<Vec<_>>().join("\n"))
+    }
+
+    /// 用于日志的下位机地址
+    fn peer_address(&self) -> String {
+        self.device_address.map_or_else(|| "未知".to_string(), |address| address.to_string())
+    }
+
+    /// 结束当前更新并写入更新记录，`error` 为 `None` 表示成功
+    fn record_firmware_update(&mut self, error: Option<String>) {
+        if let Some(attempt) = self.firmware_update_attempt.take() {
+            append_firmware_update_record(&FirmwareUpdateRecord {
+                timestamp: DateTime::now_local().ok().and_then(|time| time.format("%Y-%m-%d %H:%M:%S").ok()).map(|time| time.to_string()).unwrap_or_default(),
+                slave_address: self.peer_address(),
+                file_name: attempt.path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string(),
+                size: attempt.size,
+                md5: attempt.md5,
+                duration: attempt.started.elapsed().as_secs_f64(),
+                succeeded: error.is_none(),
+                error,
+            });
+        }
+    }
+
+    fn firmware_update_succeeded(&self) -> bool {
+        self.firmware_uploading_progress >= 0.0 && !matches!(self.firmware_verification, Some(Err(_)))
+    }
+
+    fn firmware_result_description(&self) -> String {
+        let description = match (&self.firmware_update_error, &self.firmware_verification) {
+            (Some(err), _) => format!("{}\n请检查文件与网络连接是否正常。", err),
+            (None, _) if self.firmware_uploading_progress < 0.0 => "请检查文件与网络连接是否正常。".to_string(),
+            (None, None) => "机器人将自动重启，请稍后手动进行连接。".to_string(),
+            (None, Some(Ok(info))) if self.device_firmware_info.as_ref().map_or(false, |previous| previous.version == info.version && previous.build_date == info.build_date) =>
+                format!("下位机已重启，但固件版本（{}）与更新前相同，新固件可能未生效。", info.version),
+            (None, Some(Ok(info))) => format!("下位机已重启，当前固件版本为 {}。\n请手动重新连接下位机。", info.version),
+            (None, Some(Err(err))) => format!("固件已写入，但无法确认下位机正常启动：{}\n请检查下位机的电源与指示灯，必要时手动重启后重新连接。", err),
+        };
+        let description = match &self.firmware_digest_check {
+            Some(FirmwareDigestCheck::Matched(algorithm)) if self.firmware_update_error.is_none() => format!("下位机闪存中固件的 {} 校验值与发送的数据一致。\n{}", algorithm, description),
+            Some(FirmwareDigestCheck::Unavailable) if self.firmware_update_error.is_none() => format!("下位机不支持读取闪存校验值，写入的数据未经验证（校验不可用）。\n{}", description),
+            _ => description,
+        };
+        let description = match self.firmware_slot_description() {
+            Some(slot_description) if self.firmware_update_error.is_none() && self.firmware_uploading_progress >= 0.0 => format!("{}\n{}", slot_description, description),
+            _ => description,
+        };
+        let description = match self.firmware_parts_description() {
+            Some(parts_description) => format!("{}\n{}", parts_description, description),
+            None => description,
+        };
+        let description = match &self.update_target {
+            Some(target) => format!("更新对象：{}\n{}", update_target_name(target), description),
+            None => description,
+        };
+        if self.dry_run {
+            format!("这是一次模拟更新，没有向下位机写入任何数据。\n{}", description)
+        } else {
+            description
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,16 +1448,821 @@ pub struct SlaveFirmwareUpdatePacket {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SlaveFirmwarePacket {
     size: usize,
-    compression: String,
+    compression: SlaveFirmwareCompression,
+    md5: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>, // 仅在下位机声明支持时附带，旧版本下位机只校验 md5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>, // 断点续传时跳过的已传输字节数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transfer_mode: Option<String>, // 为 "acked" 时逐块确认，缺省为连续传输
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target_slot: Option<String>, // 双分区下位机写入的分区（"a" 或 "b"），缺省时由下位机选择
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>, // 多个部分时声明写入目标（"firmware" 或 "filesystem"），缺省为固件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    update_target: Option<String>, // 下位机报告多个更新对象时声明更新的对象（如 "mcu" 或 "sbc"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    window: Option<u32>, // 连续传输时的流量控制窗口（数据块数），缺省时不等待确认
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digest_mode: Option<String>, // 为 "trailing" 时 md5 为空，校验值在固件数据之后通过 firmware_digest 发送
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareDigestPacket {
+    firmware_digest: SlaveFirmwareDigest,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareKeepalivePacket {
+    firmware_keepalive: (),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareVerifyRequestPacket {
+    firmware_verify: (),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareVerifyPacket {
+    firmware_verify: SlaveFirmwareDigest,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlaveFirmwareDigest {
+    md5: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareUpdateStatusRequestPacket {
+    firmware_update_status: (),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareUpdateStatusPacket {
+    firmware_update_status: SlaveFirmwareUpdateStatus,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SlaveFirmwareUpdateStatus {
+    #[serde(default)]
+    md5: String,
+    #[serde(default)]
+    compression: Option<SlaveFirmwareCompression>, // 下位机没有未完成的固件时可能缺省
+    #[serde(default)]
+    received: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveGetFirmwareInfoPacket {
+    get_firmware_info: (),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareInfoPacket {
+    firmware_info: SlaveFirmwareInfo,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SlaveFirmwareInfo {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub build_date: String,
+    #[serde(default)]
+    pub board: String,
+    #[serde(default)]
+    pub digests: Vec<String>, // 下位机支持的校验算法，如 "md5"、"sha256"
+    #[serde(default)]
+    pub md5: String, // 当前运行的固件的 md5，旧版本下位机不提供
+    #[serde(default)]
+    pub transfer_modes: Vec<String>, // 下位机支持的传输方式，如 "acked"、"trailing_digest"
+    #[serde(default)]
+    pub flow_control_window: u32, // 下位机每接收该数量的数据块后回复一次确认，为 0 时不支持流量控制
+    #[serde(default)]
+    pub readback: bool, // 下位机是否支持读取当前运行的固件
+    #[serde(default)]
+    pub slots: Vec<String>, // 双分区下位机的分区列表，如 ["a", "b"]
+    #[serde(default)]
+    pub active_slot: Option<String>, // 当前运行的分区
+    #[serde(default)]
+    pub armed: Option<bool>, // 推进器是否已解锁，不报告解锁状态的下位机为 `None`
+    #[serde(default)]
+    pub partition_size: u64, // 可写入分区的大小（字节），为 0 时未报告
+    #[serde(default)]
+    pub transfer_timeout: u32, // 下位机在该时长（秒）内未收到固件数据时中止更新，为 0 时未报告
+    #[serde(default)]
+    pub update_targets: Vec<String>, // 同一连接后可更新的对象，如 ["mcu", "sbc"]，只有单片机的下位机不报告
+}
+
+impl SlaveFirmwareInfo {
+    pub fn supports_sha256(&self) -> bool {
+        self.digests.iter().any(|digest| digest.eq_ignore_ascii_case("sha256"))
+    }
+
+    pub fn is_dual_bank(&self) -> bool {
+        self.slots.len() >= 2
+    }
+
+    /// 自动模式下写入的分区，即第一个非当前运行的分区
+    fn inactive_slot(&self) -> Option<&str> {
+        self.slots.iter().map(String::as_str).find(|slot| self.active_slot.as_deref().map_or(true, |active| !active.eq_ignore_ascii_case(slot)))
+    }
+
+    pub fn supports_acked_transfer(&self) -> bool {
+        self.transfer_modes.iter().any(|mode| mode.eq_ignore_ascii_case("acked"))
+    }
+
+    /// 下位机是否接受在固件数据之后发送的校验值，此时上位机只需读取一遍固件
+    pub fn supports_trailing_digest(&self) -> bool {
+        self.transfer_modes.iter().any(|mode| mode.eq_ignore_ascii_case("trailing_digest"))
+    }
+
+    /// 下位机是否接受暂停传输期间发送的保活数据包
+    pub fn supports_keepalive(&self) -> bool {
+        self.transfer_modes.iter().any(|mode| mode.eq_ignore_ascii_case("keepalive"))
+    }
+
+    /// 下位机是否保存未完成的固件并回复传输进度查询，不支持时查询只会等待到超时
+    pub fn supports_resume(&self) -> bool {
+        self.transfer_modes.iter().any(|mode| mode.eq_ignore_ascii_case("resume"))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareChunkAckPacket {
+    firmware_chunk_ack: SlaveFirmwareChunkAck,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareChunkAck {
+    index: u32,
+    #[serde(default = "firmware_chunk_ack_ok_default")]
+    ok: bool,
+}
+
+fn firmware_chunk_ack_ok_default() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareWindowAckPacket {
+    firmware_window_ack: SlaveFirmwareWindowAck,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareWindowAck {
+    chunks: u32, // 本次传输中下位机已接收并处理的数据块数
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareReadRequestPacket {
+    firmware_read: (),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareReadPacket {
+    firmware_read: SlaveFirmwareReadHeader,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareReadHeader {
+    size: u64,
     md5: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveSetArmedPacket {
+    set_armed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveRebootPacket {
+    reboot: (),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareUpdateResultPacket {
+    firmware_update_result: SlaveFirmwareUpdateResult,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareWriteProgressPacket {
+    firmware_write_progress: f64, // 0 ~ 1
+}
+
+/// 传输结束后下位机可能发送的数据包：若干写入进度，最后是更新结果
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SlaveFirmwareWriteMessage {
+    Progress(SlaveFirmwareWriteProgressPacket),
+    Result(SlaveFirmwareUpdateResultPacket),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlaveFirmwareUpdateResult {
+    ok: bool,
+    #[serde(default)]
+    message: String,
+}
+
+const FIRMWARE_UPDATE_RESULT_TIMEOUT: Duration = Duration::from_secs(30);
+const FIRMWARE_QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+const FIRMWARE_VERIFY_TIMEOUT: Duration = Duration::from_secs(10); // 下位机需要读取整个固件计算校验值，超时后视为不支持校验
+const FIRMWARE_REBOOT_TIMEOUT: Duration = Duration::from_secs(60);
+const FIRMWARE_REBOOT_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+const FIRMWARE_DISCONNECT_TIMEOUT: Duration = Duration::from_secs(10); // 超过该时间仍未断开连接时不再等待，直接尝试重新连接
+const FIRMWARE_ONLINE_NOTICE_DURATION: Duration = Duration::from_secs(1); // 下位机重新上线后保留提示的时间，随后进入结果页
+const FIRMWARE_CHUNK_ACK_TIMEOUT: Duration = Duration::from_secs(3);
+const FIRMWARE_READ_TIMEOUT: Duration = Duration::from_secs(10);
+const FIRMWARE_WINDOW_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+const FIRMWARE_WINDOW_STALL_THRESHOLD: Duration = Duration::from_secs(1); // 等待确认超过该时长时在进度中提示
+const FIRMWARE_CHUNK_RETRY_LIMIT: u32 = 5;
+
+/// 从 `frame_reader` 读取下一个数据包；同一连接上的读取应共用一个 `frame_reader`，
+/// 一次读取包含多个数据包或超时取消读取时，已接收的字节留在其中供下次读取
+async fn receive_packet<T: DeserializeOwned>(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, timeout: Duration) -> Result<T, IOError> {
+    loop {
+        let json_string = async_std::io::timeout(timeout, frame_reader.read_frame(tcp_stream)).await
+            .map_err(|err| if err.kind() == ErrorKind::TimedOut { IOError::new(ErrorKind::TimedOut, "等待下位机回复超时") } else { err })?;
+        if SlavePongPacket::parse(&json_string).is_some() { // 独占连接前主连接测量延迟的回复，与本模块无关
+            continue;
+        }
+        return serde_json::from_str::<T>(&json_string)
+            .map_err(|err| IOError::new(ErrorKind::InvalidData, format!("无法识别来自于下位机的 JSON 数据包（{}）：“{}”", err, json_string)));
+    }
+}
+
+fn firmware_cache_dir() -> PathBuf {
+    dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("rov-host").join("firmware")
+}
+
+fn firmware_backup_dir() -> PathBuf {
+    get_data_path().join("firmware_backups")
+}
+
+async fn read_firmware_data(tcp_stream: &mut TcpStream, buf: &mut [u8]) -> Result<usize, IOError> {
+    let len = async_std::io::timeout(FIRMWARE_READ_TIMEOUT, tcp_stream.read(buf)).await
+        .map_err(|err| if err.kind() == ErrorKind::TimedOut { IOError::new(ErrorKind::TimedOut, "等待下位机发送固件超时") } else { err })?;
+    if len == 0 {
+        return Err(IOError::new(ErrorKind::ConnectionAborted, "下位机主动断开连接（EOF）"));
+    }
+    Ok(len)
+}
+
+/// 读取下位机当前运行的固件并保存至 `path`：下位机先回复包含大小与 md5 的 JSON 数据包，随后紧跟固件的原始数据，
+/// 保存后校验 md5，失败时删除不完整的备份文件
+async fn backup_firmware(tcp_stream: &mut TcpStream, framing: FrameFormat, path: PathBuf, sender: Sender<SlaveFirmwareUpdaterMsg>) -> Result<PathBuf, IOError> {
+    let json = serde_json::to_string(&SlaveFirmwareReadRequestPacket { firmware_read: () }).unwrap();
+    write_frame(tcp_stream, framing, &json).await?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut received_bytes = Vec::new();
+    // 数据包头与固件数据之间没有分隔，一次读取可能同时包含两者，解析数据包头后剩余的字节属于固件
+    let (header, consumed) = loop {
+        let len = read_firmware_data(tcp_stream, &mut buf).await?;
+        received_bytes.extend_from_slice(&buf[..len]);
+        match parse_backup_header(&received_bytes, framing)? {
+            Some(parsed) => break parsed,
+            None if received_bytes.len() > MAX_FRAME_BYTES + FRAME_LENGTH_PREFIX_BYTES => return Err(IOError::new(ErrorKind::InvalidData, "下位机未发送有效的固件数据包头")),
+            None => continue,
+        }
+    };
+    if header.size == 0 || header.size > FIRMWARE_MAX_SIZE {
+        return Err(IOError::new(ErrorKind::InvalidData, format!("下位机声明的固件大小 {} 字节无效", header.size)));
+    }
+    async_std::fs::create_dir_all(path.parent().unwrap()).await?;
+    let mut file = async_std::fs::File::create(&path).await?;
+    let result = async {
+        let mut md5_context = md5::Context::new();
+        let leftover = &received_bytes[consumed..received_bytes.len().min(consumed + header.size as usize)];
+        file.write_all(leftover).await?;
+        md5_context.consume(leftover);
+        let mut received = leftover.len() as u64;
+        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareBackupProgressUpdated(received, header.size));
+        while received < header.size {
+            let remaining = (header.size - received).min(buf.len() as u64) as usize;
+            let len = read_firmware_data(tcp_stream, &mut buf[..remaining]).await?;
+            file.write_all(&buf[..len]).await?;
+            md5_context.consume(&buf[..len]);
+            received += len as u64;
+            send!(sender, SlaveFirmwareUpdaterMsg::FirmwareBackupProgressUpdated(received, header.size));
+        }
+        file.flush().await?;
+        let md5 = format!("{:x}", md5_context.compute());
+        if !md5.eq_ignore_ascii_case(&header.md5) {
+            return Err(IOError::new(ErrorKind::InvalidData, format!("备份的固件 MD5 校验失败，期望 {}，实际 {}", header.md5, md5)));
+        }
+        Ok::<(), IOError>(())
+    }.await;
+    if result.is_err() {
+        async_std::fs::remove_file(&path).await.unwrap_or_default();
+    }
+    result.map(|_| path)
+}
+
+/// 从已接收的字节中解析固件数据包头，返回数据包头与其（含长度前缀）占用的字节数，字节不足一个完整的数据包头时返回 `None`；
+/// 旧格式下跳过数据包头之前的字节，长度前缀格式下按前缀声明的长度切分，不会将固件数据中的 `{` 误认为数据包头
+fn parse_backup_header(received_bytes: &[u8], framing: FrameFormat) -> Result<Option<(SlaveFirmwareReadHeader, usize)>, IOError> {
+    let header_error = |err: serde_json::Error| IOError::new(ErrorKind::InvalidData, format!("无法识别来自于下位机的固件数据包头（{}）", err));
+    match framing {
+        FrameFormat::Legacy => {
+            let start = received_bytes.iter().position(|&byte| byte == b'{').unwrap_or(received_bytes.len());
+            let mut packets = serde_json::Deserializer::from_slice(&received_bytes[start..]).into_iter::<SlaveFirmwareReadPacket>();
+            match packets.next() {
+                Some(Ok(SlaveFirmwareReadPacket { firmware_read: header })) => Ok(Some((header, start + packets.byte_offset()))),
+                Some(Err(err)) if !err.is_eof() => Err(header_error(err)),
+                _ => Ok(None),
+            }
+        },
+        FrameFormat::LengthPrefixed => {
+            let mut prefix = [0u8; FRAME_LENGTH_PREFIX_BYTES];
+            match received_bytes.get(..FRAME_LENGTH_PREFIX_BYTES) {
+                Some(bytes) => prefix.copy_from_slice(bytes),
+                None => return Ok(None),
+            }
+            let len = u32::from_be_bytes(prefix) as usize;
+            if len > MAX_FRAME_BYTES {
+                return Err(IOError::new(ErrorKind::InvalidData, format!("下位机固件数据包头声明的长度 {} 字节超过上限 {} 字节", len, MAX_FRAME_BYTES)));
+            }
+            match received_bytes.get(FRAME_LENGTH_PREFIX_BYTES..FRAME_LENGTH_PREFIX_BYTES + len) {
+                Some(payload) => serde_json::from_slice::<SlaveFirmwareReadPacket>(payload)
+                    .map(|SlaveFirmwareReadPacket { firmware_read: header }| Some((header, FRAME_LENGTH_PREFIX_BYTES + len)))
+                    .map_err(header_error),
+                None => Ok(None),
+            }
+        },
+    }
+}
+
+fn http_error(err: surf::Error) -> IOError {
+    IOError::new(ErrorKind::Other, format!("网络请求失败：{}", err))
+}
+
+/// 将固件下载到缓存目录，若服务器上存在同名的 `.sha256` 文件则校验下载的内容
+async fn download_firmware(url: Url, sender: Sender<SlaveFirmwareUpdaterMsg>) -> Result<PathBuf, IOError> {
+    let file_name = url.path_segments().and_then(|segments| segments.last()).filter(|name| !name.is_empty()).unwrap_or("firmware.bin").to_string();
+    let cache_dir = firmware_cache_dir();
+    async_std::fs::create_dir_all(&cache_dir).await?;
+    let path = cache_dir.join(file_name);
+    let mut response = surf::get(url.as_str()).await.map_err(http_error)?;
+    if !response.status().is_success() {
+        return Err(IOError::new(ErrorKind::Other, format!("服务器返回错误：{}", response.status())));
+    }
+    let total = response.len().map(|len| len as u64);
+    let mut body = response.take_body().into_reader();
+    let mut file = async_std::fs::File::create(&path).await?;
+    let mut sha256 = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut received = 0u64;
+    loop {
+        let len = body.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+        file.write_all(&buf[..len]).await?;
+        sha256.update(&buf[..len]);
+        received += len as u64;
+        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareDownloadProgressUpdated(received, total));
+    }
+    file.flush().await?;
+    if total.map_or(false, |total| total != received) {
+        return Err(IOError::new(ErrorKind::UnexpectedEof, format!("下载不完整，已接收 {} / {} 字节", received, total.unwrap())));
+    }
+    let mut sidecar_url = url.clone();
+    sidecar_url.set_path(&format!("{}.sha256", url.path()));
+    let mut response = surf::get(sidecar_url.as_str()).await.map_err(http_error)?;
+    if response.status().is_success() {
+        let text = response.body_string().await.map_err(http_error)?;
+        let expected = text.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+        let actual = format!("{:x}", sha256.finalize());
+        if expected != actual {
+            async_std::fs::remove_file(&path).await.unwrap_or_default();
+            return Err(IOError::new(ErrorKind::InvalidData, format!("SHA-256 校验失败，期望 {}，实际 {}", expected, actual)));
+        }
+    } else if response.status() != surf::StatusCode::NotFound {
+        log::warn!("无法获取固件校验文件 {}（{}），跳过 SHA-256 校验", sidecar_url, response.status());
+    }
+    Ok(path)
+}
+
+/// 逐块确认模式下发送一个数据块，块前附带序号与长度（各 4 字节，小端序），未在超时前收到对应序号的确认时重传，返回重传次数
+async fn send_acked_chunk(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, index: u32, chunk: &[u8]) -> Result<u32, IOError> {
+    let mut frame = Vec::with_capacity(chunk.len() + 8);
+    frame.extend_from_slice(&index.to_le_bytes());
+    frame.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    frame.extend_from_slice(chunk);
+    for retries in 0..=FIRMWARE_CHUNK_RETRY_LIMIT {
+        tcp_stream.write_all(&frame).await?;
+        tcp_stream.flush().await?;
+        match receive_packet::<SlaveFirmwareChunkAckPacket>(tcp_stream, frame_reader, FIRMWARE_CHUNK_ACK_TIMEOUT).await {
+            Ok(SlaveFirmwareChunkAckPacket { firmware_chunk_ack: SlaveFirmwareChunkAck { index: ack_index, ok: true } }) if ack_index == index => return Ok(retries),
+            Ok(SlaveFirmwareChunkAckPacket { firmware_chunk_ack: ack }) => log::warn!("数据块 {} 收到异常确认 {:?}，将重传", index, ack),
+            Err(err) if err.kind() == ErrorKind::TimedOut => log::warn!("数据块 {} 等待确认超时，将重传", index),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(IOError::new(ErrorKind::TimedOut, format!("数据块 {} 重传 {} 次后仍未得到下位机确认", index, FIRMWARE_CHUNK_RETRY_LIMIT)))
+}
+
+/// 等待下位机确认已处理前 `chunks` 个数据块，等待时间较长时通过 `stalled` 回调提示界面；
+/// 提示前的超时可能在读取到一半时取消读取，已接收的部分保留在 `frame_reader` 中，继续等待时不会丢失
+async fn wait_for_window_ack(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, chunks: u32, stalled: impl Fn(bool)) -> Result<(), IOError> {
+    let result = match receive_packet::<SlaveFirmwareWindowAckPacket>(tcp_stream, frame_reader, FIRMWARE_WINDOW_STALL_THRESHOLD).await {
+        Err(err) if err.kind() == ErrorKind::TimedOut => {
+            stalled(true);
+            let result = receive_packet::<SlaveFirmwareWindowAckPacket>(tcp_stream, frame_reader, FIRMWARE_WINDOW_ACK_TIMEOUT - FIRMWARE_WINDOW_STALL_THRESHOLD).await;
+            stalled(false);
+            result
+        },
+        result => result,
+    };
+    match result {
+        Ok(SlaveFirmwareWindowAckPacket { firmware_window_ack: SlaveFirmwareWindowAck { chunks: acked } }) if acked >= chunks => Ok(()),
+        Ok(SlaveFirmwareWindowAckPacket { firmware_window_ack: ack }) => Err(IOError::new(ErrorKind::InvalidData, format!("下位机确认已接收 {} 个数据块，少于已发送的 {} 个", ack.chunks, chunks))),
+        Err(err) if err.kind() == ErrorKind::TimedOut => Err(IOError::new(ErrorKind::TimedOut, format!("等待下位机确认前 {} 个数据块超时", chunks))),
+        Err(err) => Err(err),
+    }
+}
+
+/// 可能随后自行恢复的写入错误；连接被重置、管道断开等错误重试也无济于事，应立即判定为失败
+fn is_transient_write_error(err: &IOError) -> bool {
+    matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted | ErrorKind::TimedOut)
+}
+
+/// 第 `attempt` 次（从 1 开始）重试前的等待时间，逐次翻倍，不超过 `FIRMWARE_WRITE_BACKOFF_MAX`
+fn write_retry_backoff(attempt: u32) -> Duration {
+    FIRMWARE_WRITE_BACKOFF_INITIAL.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(FIRMWARE_WRITE_BACKOFF_MAX)
+}
+
+/// 与 `write_all` 相同，但遇到暂时性错误时按指数退避从中断处继续写入，至多重试 `retry_limit` 次，返回重试的次数
+async fn write_all_with_retry<W: async_std::io::Write + Unpin>(writer: &mut W, buf: &[u8], retry_limit: u32) -> Result<u32, IOError> {
+    let (mut written, mut retries) = (0, 0);
+    while written < buf.len() {
+        match writer.write(&buf[written..]).await {
+            Ok(0) => return Err(IOError::new(ErrorKind::WriteZero, "下位机不再接收数据")),
+            Ok(len) => written += len,
+            Err(err) if is_transient_write_error(&err) && retries < retry_limit => {
+                retries += 1;
+                let backoff = write_retry_backoff(retries);
+                log::warn!("写入固件数据时出错（{}），{:?} 后进行第 {} 次重试", err, backoff, retries);
+                task::sleep(backoff).await;
+            },
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(retries)
+}
+
+/// 为发送数据块时的错误附加数据块序号（从本次传输的第一个数据块开始计数），便于定位中断的位置
+fn chunk_error(index: u32, err: IOError) -> IOError {
+    IOError::new(err.kind(), format!("发送第 {} 个数据块时出错：{}", index, err))
+}
+
+/// 一次更新中所有部分共用的传输选项
+#[derive(Debug, Clone)]
+struct FirmwareTransferOptions {
+    compression: FirmwareCompression,
+    use_sha256: bool,
+    streamed: bool,
+    acked: bool,
+    window: Option<u32>,
+    target_slot: Option<&'static str>,
+    update_target: Option<String>,
+    chunk_size: usize,
+    chunk_delay: Duration,
+    write_retry_limit: u32,
+    paused: Arc<Mutex<bool>>,
+    keepalive: bool,
+    resumable: bool, // 下位机支持断点续传时才查询传输进度
+    framing: FrameFormat, // 数据包头、确认等数据包的分帧方式，固件数据本身不分帧
+}
+
+fn is_transfer_paused(paused: &Mutex<bool>) -> bool {
+    *paused.lock().unwrap()
+}
+
+/// 暂停期间等待恢复传输，`keepalive` 为真时定期发送保活数据包，避免下位机因长时间未收到数据而中止更新
+async fn wait_while_paused(tcp_stream: &mut TcpStream, framing: FrameFormat, paused: &Mutex<bool>, keepalive: bool) -> Result<(), IOError> {
+    let mut last_keepalive = Instant::now();
+    while is_transfer_paused(paused) {
+        if keepalive && last_keepalive.elapsed() >= FIRMWARE_KEEPALIVE_INTERVAL {
+            let json = serde_json::to_string(&SlaveFirmwareKeepalivePacket { firmware_keepalive: () }).unwrap();
+            write_frame(tcp_stream, framing, &json).await?;
+            last_keepalive = Instant::now();
+        }
+        task::sleep(FIRMWARE_PAUSE_POLL_INTERVAL).await;
+    }
+    Ok(())
+}
+
+/// 模拟更新：不使用连接，每个部分的进度在 `duration` 内分 `FIRMWARE_DRY_RUN_STEPS` 步走完，暂停期间不前进；
+/// 通过 `report` 发出与实际更新相同的消息，窗口无需区分两者
+async fn simulate_firmware_update(parts: Vec<(PathBuf, FirmwareTarget)>, chunk_size: u64, paused: Arc<Mutex<bool>>, duration: Duration, report: impl Fn(SlaveFirmwareUpdaterMsg)) {
+    for (index, (path, _)) in parts.into_iter().enumerate() {
+        report(SlaveFirmwareUpdaterMsg::FirmwarePartStarted(index));
+        let total = async_std::fs::metadata(&path).await.map_or(0, |metadata| metadata.len());
+        for step in 1..=FIRMWARE_DRY_RUN_STEPS {
+            while is_transfer_paused(&paused) {
+                task::sleep(FIRMWARE_PAUSE_POLL_INTERVAL).await;
+            }
+            task::sleep(duration / FIRMWARE_DRY_RUN_STEPS as u32).await;
+            let sent = total * step / FIRMWARE_DRY_RUN_STEPS;
+            report(SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(FirmwareUploadProgress { sent, total, chunk_size, retries: 0, stalled: false }));
+        }
+        report(SlaveFirmwareUpdaterMsg::FirmwarePartFinished(index, Ok(())));
+    }
+    report(SlaveFirmwareUpdaterMsg::FirmwareUploadFinished(Ok(())));
+}
+
+/// 传输一个文件并等待下位机确认写入结果；`target` 为 `None` 时不在数据包中声明写入目标，兼容只支持单个固件的下位机。
+/// 同一次更新的各部分共用 `frame_reader`，上一部分多读取的数据包留给下一部分
+async fn upload_firmware_part(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, path: PathBuf, target: Option<FirmwareTarget>, options: FirmwareTransferOptions, sender: Sender<SlaveFirmwareUpdaterMsg>, peer_address: String) -> Result<(), IOError> {
+    let FirmwareTransferOptions { compression, use_sha256, streamed, acked, window, target_slot, update_target, chunk_size, chunk_delay, write_retry_limit, paused, keepalive, resumable, framing } = options;
+    let is_firmware = target.map_or(true, |target| target == FirmwareTarget::Firmware);
+    let (_, source, _) = FirmwareSource::open(&path).await?;
+    let (source, digest) = if streamed {
+        let (source, size) = task::spawn_blocking(move || {
+            let size = source.len();
+            (source, size)
+        }).await;
+        (source, FirmwareDigest { size: size?, md5: String::new(), sha256: None, compressed_size: None })
+    } else {
+        let compress = compression != FirmwareCompression::None;
+        if cached_firmware_digest(&path, use_sha256, compress).is_none() {
+            send!(sender, SlaveFirmwareUpdaterMsg::FirmwarePreparing);
+        }
+        let digest_path = path.clone();
+        let (source, digest) = task::spawn_blocking(move || {
+            let digest = digest_firmware_cached(&digest_path, &source, use_sha256, compress);
+            (source, digest)
+        }).await;
+        let digest = digest?;
+        if is_firmware {
+            send!(sender, SlaveFirmwareUpdaterMsg::FirmwareDigestComputed(path.clone(), digest.size, digest.md5.clone()));
+        }
+        (source, digest)
+    };
+    let compression = compression.resolve(digest.size, digest.compressed_size);
+    let transfer_size = if compression == SlaveFirmwareCompression::Gzip {
+        digest.compressed_size.ok_or_else(|| IOError::new(ErrorKind::InvalidData, "选择了 gzip 压缩，但未能计算固件压缩后的大小"))?
+    } else {
+        digest.size
+    };
+    // 断点续传需要预先知道 md5，单遍读取时总是从头开始传输
+    let offset = if streamed || !resumable { None } else { query_firmware_resume_offset(tcp_stream, frame_reader, &digest.md5, compression, transfer_size as usize).await };
+    log::debug!("[{}] 固件大小 {} 字节，传输 {} 字节，md5 {}，续传偏移 {:?}", peer_address, digest.size, transfer_size, digest.md5, offset);
+    let mut local_digest = SlaveFirmwareDigest { md5: digest.md5.clone(), sha256: digest.sha256.clone() }; // 单遍读取时在发送结束后更新
+    let packet = SlaveFirmwareUpdatePacket {
+        firmware_update: SlaveFirmwarePacket {
+            size: digest.size as usize,
+            compression,
+            md5: digest.md5,
+            sha256: digest.sha256,
+            offset,
+            transfer_mode: acked.then(|| "acked".to_string()),
+            target_slot: target_slot.filter(|_| is_firmware).map(str::to_string),
+            target: target.map(|target| target.packet_value().to_string()),
+            update_target,
+            window,
+            digest_mode: streamed.then(|| "trailing".to_string()),
+        }
+    };
+    let json = serde_json::to_string(&packet).unwrap();
+    write_frame(tcp_stream, framing, &json).await.map_err(|err| IOError::new(err.kind(), format!("发送固件信息时出错：{}", err)))?;
+    let offset = offset.unwrap_or(0) as u64;
+    if offset < transfer_size {
+        let mut reporter = FirmwareProgressReporter::new(sender.clone());
+        reporter.report(FirmwareUploadProgress { sent: offset, total: transfer_size, chunk_size: chunk_size as u64, retries: 0, stalled: false });
+        // 在阻塞线程中逐块读取（并压缩）固件，通过有界通道交给发送循环，内存中最多只保留少量数据块；
+        // 单遍读取时由读取线程同时计算校验值，读取结束后通过 digest_task 返回
+        let (chunk_sender, chunk_receiver) = async_std::channel::bounded::<Result<Vec<u8>, IOError>>(16);
+        let digest_task = task::spawn_blocking(move || {
+            let mut md5_context = md5::Context::new();
+            let mut sha256 = (streamed && use_sha256).then(Sha256::new);
+            let result = (|| -> Result<(), IOError> {
+                let mut reader = source.reader()?;
+                if compression == SlaveFirmwareCompression::Gzip {
+                    reader = Box::new(flate2::read::GzEncoder::new(reader, flate2::Compression::best()));
+                }
+                std::io::copy(&mut reader.by_ref().take(offset), &mut std::io::sink())?;
+                loop {
+                    let mut chunk = Vec::with_capacity(chunk_size);
+                    if reader.by_ref().take(chunk_size as u64).read_to_end(&mut chunk)? == 0 {
+                        return Ok(());
+                    }
+                    if streamed {
+                        md5_context.consume(&chunk);
+                        if let Some(sha256) = sha256.as_mut() {
+                            sha256.update(&chunk);
+                        }
+                    }
+                    if task::block_on(chunk_sender.send(Ok(chunk))).is_err() {
+                        return Ok(()); // 发送循环已退出
+                    }
+                }
+            })();
+            if let Err(err) = result {
+                task::block_on(chunk_sender.send(Err(err))).unwrap_or_default();
+            }
+            SlaveFirmwareDigest {
+                md5: format!("{:x}", md5_context.compute()),
+                sha256: sha256.map(|sha256| format!("{:x}", sha256.finalize())),
+            }
+        });
+        let mut sent = offset;
+        let (mut index, mut retries) = (0u32, 0u32);
+        while let Ok(chunk) = chunk_receiver.recv().await {
+            let chunk = chunk?;
+            if is_transfer_paused(&paused) {
+                tcp_stream.flush().await.map_err(|err| chunk_error(index, err))?;
+                log::info!("[{}] 固件传输在第 {} 个数据块前暂停", peer_address, index);
+                wait_while_paused(tcp_stream, framing, &paused, keepalive).await.map_err(|err| chunk_error(index, err))?;
+                log::info!("[{}] 从第 {} 个数据块继续传输固件", peer_address, index);
+            }
+            if acked {
+                retries += send_acked_chunk(tcp_stream, frame_reader, index, &chunk).await.map_err(|err| chunk_error(index, err))?;
+                sent += chunk.len() as u64;
+                reporter.report(FirmwareUploadProgress { sent, total: transfer_size, chunk_size: chunk_size as u64, retries, stalled: false });
+            } else {
+                for part in chunk.chunks(FIRMWARE_PROGRESS_STEP) {
+                    retries += write_all_with_retry(tcp_stream, part, write_retry_limit).await.map_err(|err| chunk_error(index, err))?;
+                    sent += part.len() as u64;
+                    reporter.report(FirmwareUploadProgress { sent, total: transfer_size, chunk_size: chunk_size as u64, retries, stalled: false });
+                }
+                if let Some(window) = window.filter(|window| (index + 1) % window == 0) {
+                    log::trace!("[{}] 已发送 {} 个数据块，等待下位机确认（窗口 {}）", peer_address, index + 1, window);
+                    tcp_stream.flush().await.map_err(|err| chunk_error(index, err))?;
+                    wait_for_window_ack(tcp_stream, frame_reader, index + 1, clone!(@strong sender => move |stalled| {
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(FirmwareUploadProgress { sent, total: transfer_size, chunk_size: chunk_size as u64, retries, stalled }));
+                    })).await.map_err(|err| chunk_error(index, err))?;
+                }
+            }
+            if !chunk_delay.is_zero() {
+                tcp_stream.flush().await.map_err(|err| chunk_error(index, err))?;
+                task::sleep(chunk_delay).await;
+            }
+            index += 1;
+        }
+        tcp_stream.flush().await.map_err(|err| chunk_error(index.saturating_sub(1), err))?;
+        let digest = digest_task.await;
+        if streamed {
+            log::debug!("[{}] 固件发送完成，md5 {}", peer_address, digest.md5);
+            if is_firmware {
+                send!(sender, SlaveFirmwareUpdaterMsg::FirmwareDigestComputed(path.clone(), transfer_size, digest.md5.clone()));
+            }
+            let json = serde_json::to_string(&SlaveFirmwareDigestPacket { firmware_digest: digest.clone() }).unwrap();
+            write_frame(tcp_stream, framing, &json).await.map_err(|err| IOError::new(err.kind(), format!("发送固件校验值时出错：{}", err)))?;
+            local_digest = digest;
+        }
+    } else {
+        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(FirmwareUploadProgress { sent: transfer_size, total: transfer_size, chunk_size: chunk_size as u64, retries: 0, stalled: false }));
+    }
+    receive_firmware_update_result(tcp_stream, frame_reader, &sender).await?;
+    if is_firmware {
+        let check = verify_firmware_digest(tcp_stream, frame_reader, &local_digest).await?;
+        match &check {
+            FirmwareDigestCheck::Matched(algorithm) => log::info!("[{}] 下位机闪存中固件的 {} 校验值与发送的数据一致", peer_address, algorithm),
+            FirmwareDigestCheck::Unavailable => log::warn!("[{}] 下位机未回复固件校验请求，跳过写入后校验", peer_address),
+        }
+        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareDigestChecked(check));
+    }
+    Ok(())
+}
+
+/// 请求下位机计算闪存中固件的校验值并与发送的数据比较，下位机在 `FIRMWARE_VERIFY_TIMEOUT` 内未回复时视为不支持校验
+async fn verify_firmware_digest(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, local: &SlaveFirmwareDigest) -> Result<FirmwareDigestCheck, IOError> {
+    let json = serde_json::to_string(&SlaveFirmwareVerifyRequestPacket { firmware_verify: () }).unwrap();
+    write_frame(tcp_stream, frame_reader.format(), &json).await?;
+    let remote = match receive_packet::<SlaveFirmwareVerifyPacket>(tcp_stream, frame_reader, FIRMWARE_VERIFY_TIMEOUT).await {
+        Ok(packet) => packet.firmware_verify,
+        Err(err) if err.kind() == ErrorKind::TimedOut => return Ok(FirmwareDigestCheck::Unavailable),
+        Err(err) => return Err(err),
+    };
+    if !remote.md5.eq_ignore_ascii_case(&local.md5) {
+        return Err(IOError::new(ErrorKind::InvalidData, format!("下位机闪存中固件的 md5（{}）与发送的数据（{}）不一致，写入的数据可能已损坏", remote.md5, local.md5)));
+    }
+    match (&remote.sha256, &local.sha256) {
+        (Some(remote), Some(local)) if !remote.eq_ignore_ascii_case(local) =>
+            Err(IOError::new(ErrorKind::InvalidData, format!("下位机闪存中固件的 SHA-256（{}）与发送的数据（{}）不一致，写入的数据可能已损坏", remote, local))),
+        (Some(_), Some(_)) => Ok(FirmwareDigestCheck::Matched("SHA-256")),
+        _ => Ok(FirmwareDigestCheck::Matched("md5")),
+    }
+}
+
+/// 等待下位机校验固件后回复的更新结果；下位机擦写闪存期间可先发送写入进度，每收到一个数据包重新计时
+async fn receive_firmware_update_result(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, sender: &Sender<SlaveFirmwareUpdaterMsg>) -> Result<(), IOError> {
+    loop { // 写入进度可能连续到达，一次读取中包含多个数据包
+        let frame = async_std::io::timeout(FIRMWARE_UPDATE_RESULT_TIMEOUT, frame_reader.read_frame(tcp_stream)).await
+            .map_err(|err| if err.kind() == ErrorKind::TimedOut { IOError::new(ErrorKind::TimedOut, "等待下位机确认超时") } else { err })?;
+        if SlavePongPacket::parse(&frame).is_some() {
+            continue;
+        }
+        match serde_json::from_str::<SlaveFirmwareWriteMessage>(&frame) {
+            Ok(SlaveFirmwareWriteMessage::Progress(SlaveFirmwareWriteProgressPacket { firmware_write_progress: progress })) => {
+                if progress.is_finite() {
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareWriteProgressUpdated(progress.clamp(0.0, 1.0)));
+                }
+            },
+            Ok(SlaveFirmwareWriteMessage::Result(SlaveFirmwareUpdateResultPacket { firmware_update_result: SlaveFirmwareUpdateResult { ok: true, .. } })) => return Ok(()),
+            Ok(SlaveFirmwareWriteMessage::Result(SlaveFirmwareUpdateResultPacket { firmware_update_result: SlaveFirmwareUpdateResult { ok: false, message } })) => return Err(IOError::new(ErrorKind::Other, format!("下位机拒绝了固件：{}", message))),
+            Err(err) => return Err(IOError::new(ErrorKind::InvalidData, format!("无法识别来自于下位机的 JSON 数据包（{}）：“{}”", err, frame))),
+        }
+    }
+}
+
+/// 查询下位机当前运行的固件信息，旧版本固件可能不会回复，此时在超时后返回错误
+async fn query_firmware_info(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader) -> Result<SlaveFirmwareInfo, IOError> {
+    let json = serde_json::to_string(&SlaveGetFirmwareInfoPacket { get_firmware_info: () }).unwrap();
+    write_frame(tcp_stream, frame_reader.format(), &json).await?;
+    receive_packet::<SlaveFirmwareInfoPacket>(tcp_stream, frame_reader, FIRMWARE_QUERY_TIMEOUT).await.map(|packet| packet.firmware_info)
+}
+
+/// 请求下位机将推进器上锁，重新查询固件信息确认已上锁
+async fn disarm_vehicle(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader) -> Result<SlaveFirmwareInfo, IOError> {
+    let json = serde_json::to_string(&SlaveSetArmedPacket { set_armed: false }).unwrap();
+    write_frame(tcp_stream, frame_reader.format(), &json).await?;
+    let info = query_firmware_info(tcp_stream, frame_reader).await?;
+    match info.armed {
+        Some(false) => Ok(info),
+        Some(true) => Err(IOError::new(ErrorKind::Other, "下位机仍处于解锁状态")),
+        None => Err(IOError::new(ErrorKind::Unsupported, "下位机未报告推进器的解锁状态")),
+    }
+}
+
+/// 等待下位机断开更新时使用的连接，下位机发送的其他数据被丢弃；超过 `FIRMWARE_DISCONNECT_TIMEOUT` 时返回 `false`
+async fn wait_for_disconnect(tcp_stream: &mut TcpStream) -> bool {
+    let deadline = Instant::now() + FIRMWARE_DISCONNECT_TIMEOUT;
+    let mut buf = [0u8; 1024];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match async_std::io::timeout(remaining, tcp_stream.read(&mut buf)).await {
+            Ok(0) => return true,
+            Ok(_) => continue,
+            Err(err) if err.kind() == ErrorKind::TimedOut => return false,
+            Err(_) => return true,
+        }
+    }
+}
+
+/// 下位机重启后重新建立连接并查询固件信息：先等待更新时使用的连接断开，再在 `FIRMWARE_REBOOT_TIMEOUT` 内每隔一段时间重试一次
+async fn wait_for_reboot(address: SocketAddr, mut tcp_stream: TcpStream, sender: Sender<SlaveFirmwareUpdaterMsg>) -> Result<SlaveFirmwareInfo, IOError> {
+    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareRebootPhaseChanged(FirmwareRebootPhase::Disconnecting));
+    if !wait_for_disconnect(&mut tcp_stream).await {
+        log::warn!("[{}] 下位机在 {} 秒内未断开连接，可能没有重启", address, FIRMWARE_DISCONNECT_TIMEOUT.as_secs());
+    }
+    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareRebootPhaseChanged(FirmwareRebootPhase::Rebooting));
+    let deadline = Instant::now() + FIRMWARE_REBOOT_TIMEOUT;
+    task::sleep(FIRMWARE_REBOOT_RETRY_INTERVAL).await; // 给下位机留出启动时间，避免连接到尚未关闭的旧服务
+    loop {
+        let result = async {
+            let mut tcp_stream = async_std::io::timeout(FIRMWARE_QUERY_TIMEOUT, TcpStream::connect(address)).await?;
+            // 重启后的下位机尚未通过新连接声明能力，使用所有版本都支持的旧格式
+            let info = query_firmware_info(&mut tcp_stream, &mut FrameReader::new(FrameFormat::Legacy)).await;
+            tcp_stream.shutdown(std::net::Shutdown::Both).unwrap_or_default();
+            info
+        }.await;
+        match result {
+            Ok(info) => {
+                send!(sender, SlaveFirmwareUpdaterMsg::FirmwareRebootPhaseChanged(FirmwareRebootPhase::Online));
+                task::sleep(FIRMWARE_ONLINE_NOTICE_DURATION).await;
+                return Ok(info);
+            },
+            Err(err) if Instant::now() >= deadline => return Err(IOError::new(ErrorKind::TimedOut, format!("下位机在 {} 秒内未重新上线（{}）", FIRMWARE_REBOOT_TIMEOUT.as_secs(), err))),
+            Err(_) => task::sleep(FIRMWARE_REBOOT_RETRY_INTERVAL).await,
+        }
+    }
+}
+
+/// 查询下位机已保存的未完成固件，仅在校验值与压缩方式均与本次固件一致时返回可续传的偏移量，否则从头开始传输
+async fn query_firmware_resume_offset(tcp_stream: &mut TcpStream, frame_reader: &mut FrameReader, md5: &str, compression: SlaveFirmwareCompression, len: usize) -> Option<usize> {
+    let json = serde_json::to_string(&SlaveFirmwareUpdateStatusRequestPacket { firmware_update_status: () }).unwrap();
+    let result = match write_frame(tcp_stream, frame_reader.format(), &json).await {
+        Ok(()) => receive_packet::<SlaveFirmwareUpdateStatusPacket>(tcp_stream, frame_reader, FIRMWARE_QUERY_TIMEOUT).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(SlaveFirmwareUpdateStatusPacket { firmware_update_status: status }) if status.md5 == md5 && status.compression == Some(compression) && status.received > 0 && status.received <= len => Some(status.received),
+        Ok(_) => None,
+        Err(err) => {
+            log::warn!("[{}] 无法查询固件传输进度，将从头开始传输（{}）", tcp_stream.peer_addr().map_or_else(|_| "未知".to_string(), |addr| addr.to_string()), err);
+            None
+        },
+    }
+}
+
 impl SlaveFirmwareUpdaterModel {
-    pub fn new(tcp_stream: TcpStream) -> SlaveFirmwareUpdaterModel {
-        SlaveFirmwareUpdaterModel {
-            _tcp_stream: OnceCell::from(tcp_stream),
+    pub fn new(tcp_stream: Option<TcpStream>, dry_run: bool, exclusive_tcp_activity: Rc<Cell<Option<ExclusiveTcpActivity>>>) -> SlaveFirmwareUpdaterModel {
+        let mut model = SlaveFirmwareUpdaterModel {
+            device_address: tcp_stream.as_ref().and_then(|tcp_stream| tcp_stream.peer_addr().ok()),
+            _tcp_stream: tcp_stream.map(OnceCell::from).unwrap_or_default(),
+            dry_run,
+            exclusive_tcp_activity,
             ..Default::default()
-        }
+        };
+        model.set_recent_firmware_files(load_recent_firmware_files());
+        model
     }
     
     pub fn get_tcp_stream(&self) -> &TcpStream {
@@ -88,63 +2278,672 @@ impl MicroModel for SlaveFirmwareUpdaterModel {
     fn update(&mut self, msg: SlaveFirmwareUpdaterMsg, parent_sender: &Sender<SlaveMsg>, sender: Sender<SlaveFirmwareUpdaterMsg>) {
         self.reset();
         match msg {
-            SlaveFirmwareUpdaterMsg::NextStep => self.set_current_page(self.get_current_page().wrapping_add(1)),
-            SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path) => self.set_firmware_file_path(Some(path)),
+            SlaveFirmwareUpdaterMsg::NextStep => self.set_current_page(next_firmware_updater_page(*self.get_current_page())),
+            SlaveFirmwareUpdaterMsg::PreviousStep => {
+                if self.is_uninterruptible() {
+                    return;
+                }
+                if let Some(handle) = self.download_task.take() {
+                    log::info!("已取消固件下载");
+                    task::spawn(handle.cancel());
+                    self.set_firmware_downloading(false);
+                }
+                let page = *self.get_current_page();
+                if page > 1 { // 从进度页或结果页返回时清除上一次的结果，已选择的文件保持不变
+                    self.set_firmware_uploading_progress(0.0);
+                    self.set_firmware_uploading_details(FirmwareUploadProgress::default());
+                    self.set_firmware_download_progress((0, None));
+                    self.set_firmware_update_error(None);
+                    self.set_firmware_verification(None);
+                    self.set_firmware_digest_check(None);
+                    self.set_firmware_part_results(Vec::new());
+                }
+                if previous_firmware_updater_page(page) != page {
+                    self.set_current_page(previous_firmware_updater_page(page));
+                }
+            },
+            SlaveFirmwareUpdaterMsg::RetryUpdate => {
+                if !self.dry_run && self.get_tcp_stream().peer_addr().is_err() {
+                    self.get_mut_toast_messages().borrow_mut().push_back("与下位机的连接已断开，请重新连接下位机后重试".to_string());
+                    return;
+                }
+                self.set_firmware_uploading_progress(0.0);
+                self.set_firmware_uploading_details(FirmwareUploadProgress::default());
+                self.set_firmware_update_error(None);
+                self.set_firmware_verification(None);
+                self.set_firmware_digest_check(None);
+                self.set_firmware_part_results(Vec::new());
+                self.set_current_page(1);
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path) => {
+                self.set_firmware_validation(None);
+                self.set_firmware_manifest(None);
+                self.set_firmware_md5(None);
+                self.set_firmware_image_size(None);
+                self.set_firmware_sidecar_check(None);
+                self.set_sidecar_mismatch_overridden(false);
+                self.set_validation_overridden(false);
+                self.set_board_mismatch_overridden(false);
+                self.set_reinstall_confirmed(false);
+                self.set_firmware_file_path(Some(path.clone()));
+                if let Err(err) = check_firmware_readable(&path) {
+                    log::warn!("无法读取固件文件 {}：{}", path.to_str().unwrap_or_default(), err);
+                    self.get_mut_toast_messages().borrow_mut().push_back(format!("无法读取固件文件：{}", err));
+                    self.set_firmware_validation(Some(FirmwareValidation::Unreadable(err.to_string())));
+                    return;
+                }
+                let use_sha256 = self.get_device_firmware_info().as_ref().map_or(false, SlaveFirmwareInfo::supports_sha256);
+                task::spawn(async move {
+                    let mut manifest = None;
+                    let mut md5 = None;
+                    let mut image_size = None;
+                    let validation = match FirmwareSource::open(&path).await {
+                        Ok((format, source, source_manifest)) => {
+                            manifest = source_manifest;
+                            let digest_path = path.clone();
+                            let result = task::spawn_blocking(move || -> Result<(Vec<u8>, u64, Option<String>, u64), IOError> {
+                                let mut header = Vec::with_capacity(FIRMWARE_HEADER_SIZE);
+                                source.reader()?.take(FIRMWARE_HEADER_SIZE as u64).read_to_end(&mut header)?;
+                                // 读取一遍固件同时得到转换后的准确大小与校验值，结果被缓存，开始更新时无需再次读取；
+                                // 固件包的读取器已跳过文件头，计算的 md5 与下位机上的固件一致
+                                let digest = digest_firmware_cached(&digest_path, &source, use_sha256, false)?;
+                                // 下位机写入的是解压后的镜像，Gzip 文件尾记录的大小在超过 4 GiB 时会回绕，因此完整解压一遍计算
+                                let image_size = if header.starts_with(GZIP_MAGIC) {
+                                    std::io::copy(&mut flate2::read::GzDecoder::new(source.reader()?), &mut std::io::sink())?
+                                } else {
+                                    digest.size
+                                };
+                                Ok((header, digest.size, Some(digest.md5), image_size))
+                            }).await;
+                            match result {
+                                Ok((header, size, digest, size_on_device)) => {
+                                    md5 = digest;
+                                    image_size = Some(size_on_device);
+                                    validate_firmware(&header, size)
+                                        .map_message(|msg| format!("{}，{} 字节，{}", format.to_string(), size, msg))
+                                },
+                                Err(err) => FirmwareValidation::Unreadable(err.to_string()),
+                            }
+                        },
+                        Err(err) => FirmwareValidation::Unreadable(err.to_string()),
+                    };
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareValidated(path.clone(), validation, manifest, md5, image_size));
+                    let sidecar_path = path.clone();
+                    let check = task::spawn_blocking(move || check_firmware_sidecar(&sidecar_path)).await;
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareSidecarChecked(path, check));
+                });
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareValidated(path, validation, manifest, md5, image_size) => {
+                if self.get_firmware_file_path().as_ref() == Some(&path) { // 忽略已被重新选择的文件的校验结果
+                    if !validation.is_invalid() {
+                        save_last_firmware_directory(&path);
+                    }
+                    self.set_firmware_validation(Some(validation));
+                    self.set_firmware_manifest(manifest);
+                    self.set_firmware_md5(md5);
+                    self.set_firmware_image_size(image_size);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::SetReinstallConfirmed(confirmed) => self.set_reinstall_confirmed(confirmed),
+            SlaveFirmwareUpdaterMsg::AddFirmwarePart(path) => {
+                if self.firmware_parts().iter().any(|(part_path, _)| *part_path == path) {
+                    self.get_mut_toast_messages().borrow_mut().push_back("该文件已在更新列表中".to_string());
+                    return;
+                }
+                self.get_mut_extra_firmware_parts().push(FirmwarePartModel { path, target: FirmwareTarget::Filesystem });
+            },
+            SlaveFirmwareUpdaterMsg::RemoveFirmwarePart(index) => {
+                let mut parts: Vec<_> = self.extra_firmware_parts.iter().cloned().collect();
+                if index < parts.len() {
+                    parts.remove(index);
+                    let extra_firmware_parts = self.get_mut_extra_firmware_parts();
+                    extra_firmware_parts.clear();
+                    for part in parts {
+                        extra_firmware_parts.push(part);
+                    }
+                }
+            },
+            SlaveFirmwareUpdaterMsg::SetFirmwarePartTarget(index, target) => {
+                if let Some(part) = self.extra_firmware_parts.get_mut(index) {
+                    part.target = target;
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwarePartStarted(index) => {
+                self.set_current_firmware_part(index);
+                self.set_firmware_write_progress(None);
+                self.set_firmware_upload_timing(None);
+                self.set_firmware_paused_duration(Duration::ZERO);
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareWriteProgressUpdated(progress) => {
+                if self.firmware_uploading {
+                    self.set_firmware_write_progress(Some(progress));
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwarePartFinished(index, result) => {
+                if let Some((_, part_result)) = self.get_mut_firmware_part_results().get_mut(index) {
+                    *part_result = Some(result);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::SetValidationOverridden(overridden) => self.set_validation_overridden(overridden),
+            SlaveFirmwareUpdaterMsg::FirmwareSidecarChecked(path, check) => {
+                if self.get_firmware_file_path().as_ref() == Some(&path) {
+                    match &check {
+                        Some(check @ FirmwareSidecarCheck::Matched { .. }) => log::info!("固件 {} {}", path.to_str().unwrap_or_default(), check.to_string()),
+                        Some(check) => log::warn!("固件 {} {}", path.to_str().unwrap_or_default(), check.to_string()),
+                        None => (),
+                    }
+                    self.set_firmware_sidecar_check(check);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::SetSidecarMismatchOverridden(overridden) => {
+                if overridden {
+                    log::warn!("[{}] 已忽略固件与校验文件不符", self.peer_address());
+                }
+                self.set_sidecar_mismatch_overridden(overridden);
+            },
+            SlaveFirmwareUpdaterMsg::SetBoardMismatchOverridden(overridden) => {
+                if let (true, Some((firmware_board, device_board))) = (overridden, self.firmware_board_mismatch()) {
+                    log::warn!("[{}] 已忽略主板不符：固件适用于 {}，下位机为 {}", self.peer_address(), firmware_board, device_board);
+                }
+                self.set_board_mismatch_overridden(overridden);
+            },
+            SlaveFirmwareUpdaterMsg::SelectRecentFirmware(index) => {
+                if let Some(path) = self.recent_firmwares.get(index).map(|model| model.file.path.clone()) {
+                    if path.is_file() { // 重新校验文件，不使用列表中保存的校验值
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path));
+                    } else {
+                        self.get_mut_toast_messages().borrow_mut().push_back(format!("文件已被移动或删除：{}", path.to_str().unwrap_or_default()));
+                        let files = self.recent_firmware_files();
+                        self.set_recent_firmware_files(files);
+                    }
+                }
+            },
+            SlaveFirmwareUpdaterMsg::RemoveRecentFirmware(index) => {
+                let mut files = self.recent_firmware_files();
+                if index < files.len() {
+                    files.remove(index);
+                    save_recent_firmware_files(&files);
+                    self.set_recent_firmware_files(files);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareDigestComputed(path, size, md5) => {
+                if let Some(attempt) = self.firmware_update_attempt.as_mut().filter(|attempt| attempt.path == path) {
+                    attempt.size = size;
+                    attempt.md5 = md5.clone();
+                }
+                let mut files = self.recent_firmware_files();
+                files.retain(|file| file.path != path);
+                files.insert(0, RecentFirmwareFile {
+                    path,
+                    size,
+                    md5,
+                    last_used: DateTime::now_local().ok().and_then(|time| time.format("%Y-%m-%d %H:%M").ok()).map(|time| time.to_string()).unwrap_or_default(),
+                });
+                files.truncate(RECENT_FIRMWARE_LIMIT);
+                save_recent_firmware_files(&files);
+                self.set_recent_firmware_files(files);
+            },
+            SlaveFirmwareUpdaterMsg::SetFirmwareUrl(url) => self.set_firmware_url(url),
+            SlaveFirmwareUpdaterMsg::StartDownload => {
+                if let Some(url) = self.firmware_download_url() {
+                    self.set_firmware_downloading(true);
+                    self.set_firmware_update_error(None);
+                    self.set_firmware_uploading_progress(0.0);
+                    self.set_firmware_download_progress((0, None));
+                    self.set_current_page(2);
+                    self.download_task = Some(task::spawn(async move {
+                        log::info!("开始下载固件：{}", url);
+                        let result = download_firmware(url.clone(), sender.clone()).await;
+                        if let Err(err) = &result {
+                            log::error!("固件下载失败（{}）：{}", url, err);
+                        }
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareDownloadFinished(result.map_err(|err| err.to_string())));
+                    }));
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareDownloadProgressUpdated(received, total) => {
+                if !self.firmware_downloading {
+                    return;
+                }
+                self.set_firmware_uploading_progress(total.map_or(0.0, |total| if total == 0 { 1.0 } else { (received as f32 / total as f32).min(1.0) }));
+                self.set_firmware_download_progress((received, total));
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareDownloadFinished(result) => {
+                if !self.firmware_downloading { // 下载已被取消
+                    return;
+                }
+                self.download_task = None;
+                self.set_firmware_downloading(false);
+                match result {
+                    Ok(path) => {
+                        self.set_current_page(1);
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path));
+                    },
+                    Err(err) => {
+                        self.set_firmware_uploading_progress(-1.0);
+                        self.set_firmware_update_error(Some(format!("固件下载失败：{}", err)));
+                        self.set_current_page(3);
+                    },
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FilesDropped(paths) => {
+                if *self.get_current_page() > 1 { // 仅在欢迎页与文件选择页接受拖放
+                    return;
+                }
+                let mut paths = paths.into_iter();
+                match paths.next() {
+                    Some(path) if firmware_file_suffix_matches(&path, self.firmware_file_suffixes()) => {
+                        if *self.get_current_page() == 0 {
+                            self.set_current_page(1);
+                        }
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path));
+                    },
+                    Some(path) => self.get_mut_toast_messages().borrow_mut().push_back(format!("不支持的固件文件：{}", path.to_str().unwrap_or_default())),
+                    None => return,
+                }
+                let ignored = paths.count();
+                if ignored > 0 {
+                    self.get_mut_toast_messages().borrow_mut().push_back(format!("仅使用第一个文件，已忽略其余 {} 个文件", ignored));
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwarePreparing => {
+                if self.firmware_uploading {
+                    self.set_firmware_preparing(true);
+                }
+            },
             SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(progress) => {
-                self.set_firmware_uploading_progress(progress);
-                if progress >= 1.0 || progress < 0.0 {
+                if self.firmware_uploading { // 忽略上传结束后迟到的进度
+                    self.set_firmware_preparing(false);
+                    let parts = self.firmware_part_results.len().max(1) as f32;
+                    self.set_firmware_uploading_progress(((self.current_firmware_part as f32 + progress.fraction()) / parts).min(1.0));
+                    if self.firmware_upload_timing.is_none() {
+                        self.set_firmware_upload_timing(Some((Instant::now(), progress.sent)));
+                    }
+                    self.set_firmware_uploading_details(progress);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareUploadFinished(result) => {
+                if !self.firmware_uploading { // 确保只进入一次结果页
+                    return;
+                }
+                self.update_task = None;
+                self.set_firmware_uploading(false);
+                self.set_firmware_preparing(false);
+                self.set_firmware_write_progress(None);
+                self.reset_transfer_pause();
+                match result {
+                    Ok(()) => {
+                        self.set_firmware_uploading_progress(1.0);
+                        if let (true, false, Some(address)) = (self.verify_after_update, self.dry_run, self.device_address) {
+                            self.set_firmware_verifying(true);
+                            self.set_firmware_reboot_phase(None);
+                            let tcp_stream = self.get_tcp_stream().clone();
+                            // 监视连接期间独占连接，避免主连接同时读取下位机断开前发送的数据
+                            self.spawn_update_task(parent_sender, async move {
+                                let result = wait_for_reboot(address, tcp_stream, sender.clone()).await;
+                                match &result {
+                                    Ok(info) => log::info!("[{}] 下位机已重启，固件版本 {}", address, info.version),
+                                    Err(err) => log::error!("[{}] 固件验证失败：{}", address, err),
+                                }
+                                send!(sender, SlaveFirmwareUpdaterMsg::FirmwareVerificationFinished(result.map_err(|err| err.to_string())));
+                            });
+                            return;
+                        }
+                        self.record_firmware_update(None);
+                    },
+                    Err(err) => {
+                        self.record_firmware_update(Some(err.clone()));
+                        self.set_firmware_uploading_progress(-1.0);
+                        self.set_firmware_update_error(Some(err));
+                    },
+                }
+                send!(sender, SlaveFirmwareUpdaterMsg::NextStep);
+            },
+            SlaveFirmwareUpdaterMsg::SetVerifyAfterUpdate(verify) => self.set_verify_after_update(verify),
+            SlaveFirmwareUpdaterMsg::SetAckedTransfer(acked) => self.set_acked_transfer(acked),
+            SlaveFirmwareUpdaterMsg::SetBackupBeforeUpdate(backup) => self.set_backup_before_update(backup),
+            SlaveFirmwareUpdaterMsg::StartBackup => {
+                if self.dry_run || !(self.backup_before_update && self.firmware_readback_available()) {
                     send!(sender, SlaveFirmwareUpdaterMsg::NextStep);
+                    return;
+                }
+                if self.parameter_tuning_active() {
+                    self.get_mut_toast_messages().borrow_mut().push_back("参数调校正在使用下位机连接，请先关闭参数调校窗口".to_string());
+                    return;
+                }
+                let info = self.device_firmware_info.clone().unwrap_or_default();
+                let timestamp = DateTime::now_local().ok().and_then(|time| time.format("%Y%m%d-%H%M%S").ok()).map(|time| time.to_string()).unwrap_or_default();
+                let file_name = [info.board.as_str(), info.version.as_str(), timestamp.as_str()].iter().filter(|part| !part.is_empty())
+                    .map(|part| part.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "_")).collect::<Vec<_>>().join("-");
+                let path = firmware_backup_dir().join(format!("firmware-{}.bin", file_name));
+                self.set_firmware_backing_up(true);
+                self.set_firmware_backup_progress((0, 0));
+                let mut tcp_stream = self.get_tcp_stream().clone();
+                let framing = FrameFormat::from_capabilities(&self.capabilities);
+                let peer_address = self.peer_address();
+                log::info!("[{}] 开始备份当前固件至 {}", peer_address, path.to_str().unwrap_or_default());
+                let handle = task::spawn(async move {
+                    let result = backup_firmware(&mut tcp_stream, framing, path, sender.clone()).await;
+                    match &result {
+                        Ok(path) => log::info!("[{}] 固件已备份至 {}", peer_address, path.to_str().unwrap_or_default()),
+                        Err(err) => log::error!("[{}] 固件备份失败：{}", peer_address, err),
+                    }
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareBackupFinished(result.map_err(|err| err.to_string())));
+                    Ok(())
+                });
+                send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(ExclusiveTcpActivity::FirmwareUpdate, handle)));
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareBackupProgressUpdated(received, total) => self.set_firmware_backup_progress((received, total)),
+            SlaveFirmwareUpdaterMsg::FirmwareBackupFinished(result) => {
+                self.set_firmware_backing_up(false);
+                match result {
+                    Ok(path) => {
+                        self.get_mut_toast_messages().borrow_mut().push_back(format!("当前固件已备份至 {}", path.to_str().unwrap_or_default()));
+                        send!(sender, SlaveFirmwareUpdaterMsg::NextStep);
+                    },
+                    Err(err) => self.get_mut_toast_messages().borrow_mut().push_back(format!("备份失败：{}，可重试或取消备份后继续", err)),
+                }
+            },
+            SlaveFirmwareUpdaterMsg::SetChunkSize(chunk_size) => self.set_chunk_size(chunk_size.clamp(FIRMWARE_CHUNK_SIZE_MIN, FIRMWARE_CHUNK_SIZE_MAX)),
+            SlaveFirmwareUpdaterMsg::SetChunkDelay(chunk_delay) => self.set_chunk_delay(chunk_delay.min(FIRMWARE_CHUNK_DELAY_MAX)),
+            SlaveFirmwareUpdaterMsg::SetWriteRetryLimit(limit) => self.set_write_retry_limit(limit.min(FIRMWARE_WRITE_RETRY_MAX)),
+            SlaveFirmwareUpdaterMsg::SetArmingCheckSkipped(skipped) => {
+                if skipped {
+                    log::warn!("[{}] 已跳过推进器解锁状态检查", self.peer_address());
+                }
+                self.set_arming_check_skipped(skipped);
+            },
+            SlaveFirmwareUpdaterMsg::CancelUpdate => {
+                if let Some(handle) = self.update_task.take() {
+                    log::warn!("[{}] 已中止固件更新", self.peer_address());
+                    task::spawn(handle.cancel());
+                    if self.firmware_verifying {
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareVerificationFinished(Err("已中止等待下位机重启".to_string())));
+                    } else if self.firmware_uploading {
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadFinished(Err("固件更新已中止，下位机上的固件可能不完整，请重新连接下位机后再次更新".to_string())));
+                    }
+                }
+            },
+            SlaveFirmwareUpdaterMsg::SetTransferPaused(paused) => {
+                if !self.firmware_uploading || self.firmware_paused_since.is_some() == paused {
+                    return;
+                }
+                *self.firmware_transfer_paused.lock().unwrap() = paused;
+                match self.firmware_paused_since {
+                    Some(since) => {
+                        log::info!("[{}] 继续传输固件，已暂停 {:?}", self.peer_address(), since.elapsed());
+                        self.set_firmware_paused_duration(self.firmware_paused_duration + since.elapsed());
+                        self.set_firmware_paused_since(None);
+                        self.set_firmware_pause_timed_out(false);
+                    },
+                    None => {
+                        log::info!("[{}] 暂停传输固件", self.peer_address());
+                        let since = Instant::now();
+                        self.set_firmware_paused_since(Some(since));
+                        if let Some(timeout) = self.device_transfer_timeout() {
+                            task::spawn(async move {
+                                task::sleep(timeout).await;
+                                send!(sender, SlaveFirmwareUpdaterMsg::FirmwarePauseTimedOut(since));
+                            });
+                        }
+                    },
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwarePauseTimedOut(since) => {
+                if self.firmware_paused_since == Some(since) { // 期间已恢复或重新暂停时忽略
+                    log::warn!("[{}] 固件传输暂停时间超过下位机的传输超时", self.peer_address());
+                    self.set_firmware_pause_timed_out(true);
+                    self.get_mut_toast_messages().borrow_mut().push_back("暂停时间已超过下位机的传输超时，下位机可能已中止更新".to_string());
+                }
+            },
+            SlaveFirmwareUpdaterMsg::CloseRequested => {
+                if self.update_in_progress() {
+                    self.set_close_prompted(true);
+                } else {
+                    self.set_closing(true);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::AbortAndClose => {
+                send!(sender, SlaveFirmwareUpdaterMsg::CancelUpdate);
+                self.set_closing(true);
+            },
+            SlaveFirmwareUpdaterMsg::ShowUpdateHistory => {
+                let history = load_firmware_update_history(&self.peer_address());
+                self.set_update_history(Some(history));
+            },
+            SlaveFirmwareUpdaterMsg::DisarmVehicle => {
+                if self.disarming || self.parameter_tuning_active() {
+                    return;
+                }
+                self.set_disarming(true);
+                let mut tcp_stream = self.get_tcp_stream().clone();
+                let mut frame_reader = FrameReader::new(FrameFormat::from_capabilities(&self.capabilities));
+                let peer_address = self.peer_address();
+                log::info!("[{}] 请求下位机将推进器上锁", peer_address);
+                let handle = task::spawn(async move {
+                    let result = disarm_vehicle(&mut tcp_stream, &mut frame_reader).await;
+                    match &result {
+                        Ok(_) => log::info!("[{}] 下位机已确认推进器上锁", peer_address),
+                        Err(err) => log::warn!("[{}] 无法确认推进器已上锁：{}", peer_address, err),
+                    }
+                    send!(sender, SlaveFirmwareUpdaterMsg::DisarmFinished(result.map_err(|err| err.to_string())));
+                    Ok(())
+                });
+                send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(ExclusiveTcpActivity::FirmwareUpdate, handle)));
+            },
+            SlaveFirmwareUpdaterMsg::DisarmFinished(result) => {
+                self.set_disarming(false);
+                match result {
+                    Ok(info) => {
+                        self.set_device_firmware_info(Some(info));
+                        self.get_mut_toast_messages().borrow_mut().push_back("推进器已上锁".to_string());
+                        send!(sender, SlaveFirmwareUpdaterMsg::StartUpload);
+                    },
+                    Err(err) => self.get_mut_toast_messages().borrow_mut().push_back(format!("无法确认推进器已上锁：{}", err)),
+                }
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareDigestChecked(check) => self.set_firmware_digest_check(Some(check)),
+            SlaveFirmwareUpdaterMsg::FirmwareRebootPhaseChanged(phase) => {
+                if self.firmware_verifying {
+                    self.set_firmware_reboot_phase(Some(phase));
                 }
             },
+            SlaveFirmwareUpdaterMsg::FirmwareVerificationFinished(result) => {
+                if !self.firmware_verifying { // 中止后任务可能仍发送了结果
+                    return;
+                }
+                self.update_task = None;
+                self.set_firmware_verifying(false);
+                self.set_firmware_reboot_phase(None);
+                self.record_firmware_update(result.as_ref().err().map(|err| format!("固件已写入，但无法确认下位机正常启动：{}", err)));
+                self.set_firmware_verification(Some(result));
+                send!(sender, SlaveFirmwareUpdaterMsg::NextStep);
+            },
             SlaveFirmwareUpdaterMsg::StartUpload => {
-                if let Some(path) = self.get_firmware_file_path() {
+                if self.parameter_tuning_active() {
+                    self.get_mut_toast_messages().borrow_mut().push_back("参数调校正在使用下位机连接，请先关闭参数调校窗口".to_string());
+                    return;
+                }
+                if let Some((firmware_board, device_board)) = self.firmware_board_mismatch().filter(|_| !self.board_mismatch_overridden) {
+                    let message = format!("固件适用于主板 {}，与下位机主板 {} 不符", firmware_board, device_board);
+                    self.get_mut_toast_messages().borrow_mut().push_back(message);
+                    return;
+                }
+                if self.firmware_already_installed().is_some() && !self.reinstall_confirmed {
+                    self.get_mut_toast_messages().borrow_mut().push_back("该固件似乎已安装在下位机上，请确认后再更新".to_string());
+                    return;
+                }
+                if !self.firmware_sidecar_allowed() {
+                    self.get_mut_toast_messages().borrow_mut().push_back("固件与校验文件不符，文件可能已损坏".to_string());
+                    return;
+                }
+                if let Some((size, capacity)) = self.firmware_capacity_exceeded() {
+                    self.get_mut_toast_messages().borrow_mut().push_back(format!("固件大小 {} 超出下位机可写入分区的大小 {}", format_bytes(size), format_bytes(capacity)));
+                    return;
+                }
+                if let Some((missing, _)) = self.firmware_parts().into_iter().find(|(path, _)| !path.is_file()) {
+                    self.get_mut_toast_messages().borrow_mut().push_back(format!("文件已被移动或删除：{}", missing.to_str().unwrap_or_default()));
+                    return;
+                }
+                // 选择文件后权限可能发生变化，开始前再次确认，避免在传输过程中才失败
+                if let Some((path, err)) = self.firmware_parts().into_iter().find_map(|(path, _)| check_firmware_readable(&path).err().map(|err| (path, err))) {
+                    self.get_mut_toast_messages().borrow_mut().push_back(format!("无法读取固件文件 {}：{}", path.to_str().unwrap_or_default(), err));
+                    return;
+                }
+                // 空文件没有可写入的数据，传输会立即“成功”，容易误以为已完成更新
+                if matches!(self.firmware_validation, Some(FirmwareValidation::Empty)) {
+                    self.get_mut_toast_messages().borrow_mut().push_back("固件文件为空，无法更新".to_string());
+                    return;
+                }
+                if let Some((empty, _)) = self.firmware_parts().into_iter().find(|(path, _)| std::fs::metadata(path).map_or(false, |metadata| metadata.len() == 0)) {
+                    self.get_mut_toast_messages().borrow_mut().push_back(format!("文件为空，无法写入：{}", empty.to_str().unwrap_or_default()));
+                    return;
+                }
+                if self.vehicle_possibly_armed() {
+                    self.set_disarm_prompted(true);
+                    return;
+                }
+                if let Some(path) = self.get_firmware_file_path().clone() {
+                    let parts = self.firmware_parts();
+                    self.set_firmware_part_results(parts.iter().map(|(path, target)| (format!("{}（{}）", target.to_string(), path.file_name().and_then(|name| name.to_str()).unwrap_or_default()), None)).collect());
+                    self.set_current_firmware_part(0);
+                    self.set_firmware_uploading(true);
+                    self.set_firmware_update_error(None);
+                    self.set_firmware_verification(None);
+                    self.set_firmware_digest_check(None);
+                    self.set_firmware_uploading_progress(0.0);
+                    self.set_firmware_uploading_details(FirmwareUploadProgress::default());
+                    self.reset_transfer_pause();
                     send!(sender, SlaveFirmwareUpdaterMsg::NextStep);
-                    let mut tcp_stream = self.get_tcp_stream().clone();
-                    let handle = task::spawn(clone!(@strong sender, @strong path => async move {
-                        match async_std::fs::File::open(path).await {
-                            Ok(mut file) => {
-                                let mut bytes = Vec::new();
-                                file.read_to_end(&mut bytes).await?;
-                                let bytes = bytes.as_slice();
-                                let md5_string = format!("{:x}", md5::compute(&bytes));
-                                let packet = SlaveFirmwareUpdatePacket {
-                                    firmware_update: SlaveFirmwarePacket {
-                                        size: bytes.len(),
-                                        compression: String::from("none"),
-                                        md5: md5_string,
-                                    }
-                                };
-                                let json = serde_json::to_string(&packet).unwrap();
-                                let mut json_bytes = json.as_bytes();
-                                async_std::io::copy(&mut json_bytes, &mut tcp_stream).await?;
-                                let chunks = bytes.chunks(1024);
-                                let chunk_num = chunks.len();
-                                if chunk_num > 0 {
-                                    for (chunk_index, chunk) in chunks.enumerate() {
-                                        tcp_stream.write(chunk).await?;
-                                        let progress = (chunk_index + 1) as f32 / chunk_num as f32;
-                                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(progress));
-                                    }
-                                    tcp_stream.flush().await?;
-                                } else {
-                                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(1.0));
-                                }
-                                Ok(())
-                            },
-                            Err(err) => Err(err),
-                        }
+                    if self.dry_run {
+                        log::info!("开始模拟更新固件：{}", path.to_str().unwrap_or_default());
+                        let simulation = simulate_firmware_update(parts, self.chunk_size as u64, self.firmware_transfer_paused.clone(), self.dry_run_duration, move |msg| send!(sender, msg));
+                        self.update_task = Some(task::spawn(simulation));
+                        return;
+                    }
+                    self.set_firmware_update_attempt(Some(FirmwareUpdateAttempt {
+                        started: Instant::now(),
+                        size: std::fs::metadata(&path).map_or(0, |metadata| metadata.len()),
+                        md5: String::new(),
+                        path: path.clone(),
                     }));
-                    let handle = task::spawn(async move {
-                        let result = handle.await;
-                        if result.is_err() {
-                            send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadFailed);
+                    let mut tcp_stream = self.get_tcp_stream().clone();
+                    let compression = if self.gzip_available() { *self.get_compression() } else { FirmwareCompression::None };
+                    let use_sha256 = self.get_device_firmware_info().as_ref().map_or(false, SlaveFirmwareInfo::supports_sha256);
+                    // 不压缩时无需预先计算压缩后的大小，下位机支持时在发送的同时计算校验值，只读取一遍固件
+                    let streamed = compression == FirmwareCompression::None && self.get_device_firmware_info().as_ref().map_or(false, SlaveFirmwareInfo::supports_trailing_digest);
+                    let acked = self.acked_transfer_available() && self.acked_transfer;
+                    // 逐块确认已包含流量控制，仅在连续传输时使用下位机声明的窗口
+                    let window = self.get_device_firmware_info().as_ref().map(|info| info.flow_control_window).filter(|window| !acked && *window > 0 && self.capabilities.flow_control);
+                    let target_slot = self.firmware_dual_bank().then(|| self.firmware_slot.target_slot()).flatten();
+                    let update_target = self.update_target.clone();
+                    self.set_firmware_written_slot(self.firmware_target_slot());
+                    let reboot = self.verify_after_update && self.device_address.is_some();
+                    let chunk_size = self.chunk_size;
+                    let chunk_delay = self.chunk_delay;
+                    let write_retry_limit = self.write_retry_limit;
+                    let paused = self.firmware_transfer_paused.clone();
+                    let keepalive = self.get_device_firmware_info().as_ref().map_or(false, SlaveFirmwareInfo::supports_keepalive);
+                    let resumable = self.get_device_firmware_info().as_ref().map_or(false, SlaveFirmwareInfo::supports_resume);
+                    let framing = FrameFormat::from_capabilities(&self.capabilities);
+                    let options = FirmwareTransferOptions { compression, use_sha256, streamed, acked, window, target_slot, update_target, chunk_size, chunk_delay, write_retry_limit, paused, keepalive, resumable, framing };
+                    let peer_address = self.peer_address();
+                    log::info!("[{}] 开始更新固件：{}（更新对象：{:?}，压缩：{}，数据块 {} 字节，块间延时 {:?}，逐块确认：{}，流量控制窗口：{:?}，单遍读取：{}）", peer_address, path.to_str().unwrap_or_default(), options.update_target, compression.to_string(), chunk_size, chunk_delay, acked, window, streamed);
+                    let multi_part = parts.len() > 1;
+                    self.spawn_update_task(parent_sender, async move {
+                        let mut frame_reader = FrameReader::new(options.framing);
+                        let result = async {
+                            for (index, (path, target)) in parts.into_iter().enumerate() {
+                                send!(sender, SlaveFirmwareUpdaterMsg::FirmwarePartStarted(index));
+                                log::info!("[{}] 开始传输第 {} 部分（{}）：{}", peer_address, index + 1, target.to_string(), path.to_str().unwrap_or_default());
+                                let result = upload_firmware_part(&mut tcp_stream, &mut frame_reader, path, multi_part.then(|| target), options.clone(), sender.clone(), peer_address.clone()).await;
+                                send!(sender, SlaveFirmwareUpdaterMsg::FirmwarePartFinished(index, result.as_ref().map(|_| ()).map_err(ToString::to_string)));
+                                result?;
+                            }
+                            if reboot {
+                                let json = serde_json::to_string(&SlaveRebootPacket { reboot: () }).unwrap();
+                                write_frame(&mut tcp_stream, options.framing, &json).await?;
+                            }
+                            Ok::<(), IOError>(())
+                        }.await;
+                        match &result {
+                            Ok(()) => log::info!("[{}] 固件上传完成，下位机已确认", peer_address),
+                            Err(err) => log::error!("[{}] 固件更新失败：{}", peer_address, err),
                         }
-                        result
+                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadFinished(result.map_err(|err| err.to_string())));
                     });
-                    send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(handle)));
                 }
             },
-            SlaveFirmwareUpdaterMsg::FirmwareUploadFailed => send!(sender, SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(-1.0)),
+            SlaveFirmwareUpdaterMsg::SetCompression(compression) => self.set_compression(compression),
+            SlaveFirmwareUpdaterMsg::SetFirmwareSlot(slot) => self.set_firmware_slot(slot),
+            SlaveFirmwareUpdaterMsg::SetUpdateTarget(index) => {
+                let target = match self.update_targets.get(index) {
+                    Some(target) if Some(target) != self.update_target.as_ref() => target.clone(),
+                    _ => return,
+                };
+                self.set_chunk_size(update_target_chunk_size(Some(&target)));
+                self.set_update_target(Some(target));
+                // 已选择的文件不适用于新的更新对象时清除选择
+                if self.firmware_file_path.as_ref().map_or(false, |path| !firmware_file_suffix_matches(path, self.firmware_file_suffixes())) {
+                    self.set_firmware_file_path(None);
+                    self.set_firmware_validation(None);
+                    self.set_firmware_manifest(None);
+                    self.set_firmware_md5(None);
+                    self.set_firmware_image_size(None);
+                }
+            },
+            SlaveFirmwareUpdaterMsg::BrowseFirmwareFile => self.set_firmware_file_browsing(true),
+            SlaveFirmwareUpdaterMsg::SlaveConnectionChanged(tcp_stream) => {
+                if self.dry_run || self.is_uninterruptible() { // 更新期间由更新流程自行等待下位机重启
+                    return;
+                }
+                match tcp_stream {
+                    Some(tcp_stream) => {
+                        self.device_address = tcp_stream.peer_addr().ok();
+                        self._tcp_stream = OnceCell::from(tcp_stream);
+                        self.get_mut_toast_messages().borrow_mut().push_back("已重新连接下位机".to_string());
+                        send!(sender, SlaveFirmwareUpdaterMsg::QueryFirmwareInfo);
+                    },
+                    None => self.get_mut_toast_messages().borrow_mut().push_back("与下位机的连接已断开，重新连接后即可继续".to_string()),
+                }
+            },
+            SlaveFirmwareUpdaterMsg::CapabilitiesReceived(capabilities) => self.set_capabilities(capabilities),
+            SlaveFirmwareUpdaterMsg::QueryFirmwareInfo => {
+                if self.dry_run {
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareInfoReceived(None));
+                    return;
+                }
+                if self.parameter_tuning_active() { // 查询会独占连接，与 StartUpload 相同，不打断参数调校
+                    log::info!("[{}] 参数调校正在使用下位机连接，跳过查询固件信息", self.peer_address());
+                    self.get_mut_toast_messages().borrow_mut().push_back("参数调校正在使用下位机连接，关闭参数调校窗口后重新打开本窗口即可获取固件信息".to_string());
+                    return;
+                }
+                self.set_firmware_info_querying(true);
+                let mut tcp_stream = self.get_tcp_stream().clone();
+                let mut frame_reader = FrameReader::new(FrameFormat::from_capabilities(&self.capabilities));
+                let peer_address = self.peer_address();
+                let handle = task::spawn(async move {
+                    let info = query_firmware_info(&mut tcp_stream, &mut frame_reader).await;
+                    match &info {
+                        Ok(info) => log::debug!("[{}] 下位机固件信息：{:?}", peer_address, info),
+                        Err(err) => log::warn!("[{}] 无法获取下位机固件信息：{}", peer_address, err),
+                    }
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareInfoReceived(info.ok()));
+                    Ok(())
+                });
+                send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(ExclusiveTcpActivity::FirmwareUpdate, handle)));
+            },
+            SlaveFirmwareUpdaterMsg::FirmwareInfoReceived(info) => {
+                self.set_firmware_info_querying(false);
+                let targets = info.as_ref().map(|info| info.update_targets.clone()).unwrap_or_default();
+                if targets != self.update_targets {
+                    let target = targets.first().cloned();
+                    self.set_chunk_size(update_target_chunk_size(target.as_deref()));
+                    self.set_update_target(target);
+                    self.set_update_targets(targets);
+                }
+                self.set_device_firmware_info(info);
+            },
         }
     }
 }
@@ -153,106 +2952,541 @@ impl MicroModel for SlaveFirmwareUpdaterModel {
 impl MicroWidgets<SlaveFirmwareUpdaterModel> for SlaveFirmwareUpdaterWidgets {
     view! {
         window = Window {
-            set_title: Some("固件更新向导"),
+            set_title: Some(if model.dry_run { "固件更新向导（模拟）" } else { "固件更新向导" }),
             set_width_request: 480,
             set_height_request: 480,
             set_destroy_with_parent: true,
             set_modal: true,
-            set_content = Some(&GtkBox) {
-                set_orientation: Orientation::Vertical,
-                append = &HeaderBar {
-                    set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()), *model.get_firmware_uploading_progress() <= 0.0 || *model.get_firmware_uploading_progress() >= 1.0),
-                },
-                append: carousel = &Carousel {
-                    set_hexpand: true,
-                    set_vexpand: true,
-                    set_interactive: false,
-                    scroll_to_page: track!(model.changed(SlaveFirmwareUpdaterModel::current_page()), model.current_page, true),
-                    append = &StatusPage {
-                        set_icon_name: Some("software-update-available-symbolic"),
-                        set_title: "欢迎使用固件更新向导",
-                        set_hexpand: true,
-                        set_vexpand: true,
-                        set_description: Some("请确保固件更新期间机器人有充足的电量供应。"),
-                        set_child = Some(&Button) {
-                            set_css_classes: &["suggested-action", "pill"],
-                            set_halign: Align::Center,
-                            set_label: "下一步",
+            set_content = Some(&ToastOverlay) {
+                add_toast?: watch!(model.get_toast_messages().borrow_mut().pop_front().map(|x| Toast::new(&x)).as_ref()),
+                set_child = Some(&GtkBox) {
+                    set_orientation: Orientation::Vertical,
+                    append = &HeaderBar {
+                        set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_downloading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()) || model.changed(SlaveFirmwareUpdaterModel::firmware_backing_up()), !model.is_uninterruptible()),
+                        pack_start = &Button {
+                            set_icon_name: "go-previous-symbolic",
+                            set_tooltip_text: Some("上一步"),
+                            set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::current_page()) || model.changed(SlaveFirmwareUpdaterModel::firmware_uploading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_downloading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()) || model.changed(SlaveFirmwareUpdaterModel::firmware_backing_up()), model.current_page > 0 && !model.is_uninterruptible()),
                             connect_clicked(sender) => move |_button| {
-                                send!(sender, SlaveFirmwareUpdaterMsg::NextStep);
+                                send!(sender, SlaveFirmwareUpdaterMsg::PreviousStep);
                             },
                         },
                     },
-                    append = &StatusPage {
-                        set_icon_name: Some("folder-open-symbolic"),
-                        set_title: "请选择固件文件",
+                    append: carousel = &Carousel {
                         set_hexpand: true,
                         set_vexpand: true,
-                        set_description: Some("选择的固件文件必须为下位机的可执行文件。"),
-                        set_child = Some(&GtkBox) {
-                            set_orientation: Orientation::Vertical,
-                            set_spacing: 50,
-                            append = &PreferencesGroup {
-                                add = &ActionRow {
-                                    set_title: "固件文件",
-                                    set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()), &model.firmware_file_path.as_ref().map_or("请选择文件".to_string(), |path| path.to_str().unwrap().to_string())),
-                                    add_suffix: browse_firmware_file_button = &Button {
-                                        set_label: "浏览",
-                                        set_valign: Align::Center,
-                                        connect_clicked(sender, window) => move |_button| {
-                                            let filter = FileFilter::new();
-                                            filter.add_suffix("bin");
-                                            filter.set_name(Some("固件文件"));
-                                            std::mem::forget(select_path(FileChooserAction::Open, &[filter], &window, clone!(@strong sender => move |path| {
-                                                match path {
-                                                    Some(path) => {
-                                                        send!(sender, SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path));
-                                                    },
-                                                    None => (),
-                                                }
-                                            }))); // 内存泄露修复
+                        set_interactive: false,
+                        scroll_to_page: track!(model.changed(SlaveFirmwareUpdaterModel::current_page()), model.current_page, true),
+                        append = &StatusPage {
+                            set_icon_name: Some("software-update-available-symbolic"),
+                            set_title: "欢迎使用固件更新向导",
+                            set_hexpand: true,
+                            set_vexpand: true,
+                            set_description: Some("请确保固件更新期间机器人有充足的电量供应。"),
+                            set_child = Some(&GtkBox) {
+                                set_orientation: Orientation::Vertical,
+                                set_spacing: 50,
+                                append = &PreferencesGroup {
+                                    set_title: "当前固件",
+                                    set_description: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_info_querying()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), match (model.firmware_info_querying, &model.device_firmware_info) {
+                                        (true, _) => Some("正在查询下位机固件信息..."),
+                                        (false, None) => Some("下位机未返回固件信息，仍可继续更新"),
+                                        (false, Some(_)) => None,
+                                    }),
+                                    add = &ActionRow {
+                                        set_title: "版本",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.device_firmware_info.as_ref().map_or("未知", |info| info.version.as_str())),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "构建日期",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.device_firmware_info.as_ref().map_or("未知", |info| info.build_date.as_str())),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "主板",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.device_firmware_info.as_ref().map_or("未知", |info| info.board.as_str())),
+                                    },
+                                    add = &ComboRow {
+                                        set_title: "更新对象",
+                                        set_subtitle: "下位机的单片机与伴随计算机通过同一连接分别更新，切换后将使用对应的文件类型与数据块大小",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::update_targets()), !model.update_targets.is_empty()),
+                                        set_model: track!(model.changed(SlaveFirmwareUpdaterModel::update_targets()), Some(&{
+                                            let list = StringList::new(&[]);
+                                            for target in &model.update_targets {
+                                                list.append(&update_target_name(target));
+                                            }
+                                            list
+                                        })),
+                                        set_selected: track!(model.changed(SlaveFirmwareUpdaterModel::update_targets()) || model.changed(SlaveFirmwareUpdaterModel::update_target()), model.update_target.as_ref().and_then(|target| model.update_targets.iter().position(|x| x == target)).unwrap_or_default() as u32),
+                                        connect_selected_notify(sender) => move |row| {
+                                            send!(sender, SlaveFirmwareUpdaterMsg::SetUpdateTarget(row.selected() as usize));
+                                        }
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "更新前备份当前固件",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), if model.firmware_readback_available() { "读取下位机当前运行的固件并保存至备份目录，便于更新失败后回退" } else { "下位机不支持读取固件，将跳过备份" }),
+                                        set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()) || model.changed(SlaveFirmwareUpdaterModel::firmware_backing_up()), model.firmware_readback_available() && !model.firmware_backing_up),
+                                        add_prefix: backup_before_update_check_button = &CheckButton {
+                                            set_active: track!(model.changed(SlaveFirmwareUpdaterModel::backup_before_update()), model.backup_before_update),
+                                            connect_toggled(sender) => move |button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetBackupBeforeUpdate(button.is_active()));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&backup_before_update_check_button),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "更新记录",
+                                        set_subtitle: "查看该下位机过去的固件更新",
+                                        set_activatable: true,
+                                        add_suffix = &Image {
+                                            set_icon_name: Some("go-next-symbolic"),
+                                        },
+                                        connect_activated(sender) => move |_row| {
+                                            send!(sender, SlaveFirmwareUpdaterMsg::ShowUpdateHistory);
                                         },
                                     },
-                                    set_activatable_widget: Some(&browse_firmware_file_button),
+                                },
+                                append = &GtkBox {
+                                    set_orientation: Orientation::Vertical,
+                                    set_spacing: 6,
+                                    set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_backing_up()), model.firmware_backing_up),
+                                    append = &ProgressBar {
+                                        set_fraction: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_backup_progress()), match model.firmware_backup_progress {
+                                            (_, 0) => 0.0,
+                                            (received, total) => received as f64 / total as f64,
+                                        }),
+                                    },
+                                    append = &Label {
+                                        set_label: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_backup_progress()), &model.firmware_backup_description()),
+                                    },
+                                },
+                                append = &Button {
+                                    set_css_classes: &["suggested-action", "pill"],
+                                    set_halign: Align::Center,
+                                    set_label: track!(model.changed(SlaveFirmwareUpdaterModel::backup_before_update()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), if model.backup_before_update && model.firmware_readback_available() { "备份并继续" } else { "下一步" }),
+                                    set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_backing_up()), !model.firmware_backing_up),
+                                    connect_clicked(sender) => move |_button| {
+                                        send!(sender, SlaveFirmwareUpdaterMsg::StartBackup);
+                                    },
                                 },
                             },
-                            append = &Button {
-                                set_css_classes: &["suggested-action", "pill"],
-                                set_halign: Align::Center,
-                                set_label: "开始更新",
-                                set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()), model.get_firmware_file_path().as_ref().map_or(false, |pathbuf| pathbuf.exists() && pathbuf.is_file())),
-                                connect_clicked(sender) => move |_button| {
-                                    send!(sender, SlaveFirmwareUpdaterMsg::StartUpload);
+                        },
+                        append = &StatusPage {
+                            set_icon_name: Some("folder-open-symbolic"),
+                            set_title: "请选择固件文件",
+                            set_hexpand: true,
+                            set_vexpand: true,
+                            set_description: Some("选择的固件文件必须为下位机的可执行文件。"),
+                            set_child = Some(&GtkBox) {
+                                set_orientation: Orientation::Vertical,
+                                set_spacing: 50,
+                                append = &PreferencesGroup {
+                                    add = &ActionRow {
+                                        set_title: "固件文件",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()) || model.changed(SlaveFirmwareUpdaterModel::firmware_validation()), &match (&model.firmware_file_path, &model.firmware_validation) {
+                                            (None, _) => "请选择文件".to_string(),
+                                            (Some(path), None) => format!("{}\n正在准备固件…", path.to_str().unwrap()),
+                                            (Some(path), Some(validation)) => format!("{}\n{}", path.to_str().unwrap(), validation.to_string()),
+                                        }),
+                                        add_prefix = &Image {
+                                            set_icon_name: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_validation()), model.firmware_validation.as_ref().map(FirmwareValidation::icon_name)),
+                                            set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_validation()), model.firmware_validation.is_some()),
+                                        },
+                                        add_prefix = &Spinner {
+                                            set_spinning: true,
+                                            set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()) || model.changed(SlaveFirmwareUpdaterModel::firmware_validation()), model.firmware_file_path.is_some() && model.firmware_validation.is_none()),
+                                        },
+                                        add_suffix: browse_firmware_file_button = &Button {
+                                            set_label: "浏览",
+                                            set_valign: Align::Center,
+                                            connect_clicked(sender) => move |_button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::BrowseFirmwareFile);
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&browse_firmware_file_button),
+                                    },
+                                    add = &GtkBox {
+                                        set_orientation: Orientation::Vertical,
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::recent_firmwares()), model.recent_firmwares.len() > 0),
+                                        factory!(model.recent_firmwares),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "下载地址",
+                                        set_subtitle: "从 HTTP(S) 服务器下载固件",
+                                        add_suffix = &Entry {
+                                            set_valign: Align::Center,
+                                            set_hexpand: true,
+                                            set_placeholder_text: Some("https://"),
+                                            connect_changed(sender) => move |entry| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetFirmwareUrl(entry.text().to_string()));
+                                            },
+                                            connect_activate(sender) => move |_entry| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::StartDownload);
+                                            },
+                                        },
+                                        add_suffix = &Button {
+                                            set_label: "下载",
+                                            set_valign: Align::Center,
+                                            set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_url()), model.firmware_download_url().is_some()),
+                                            connect_clicked(sender) => move |_button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::StartDownload);
+                                            },
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "忽略校验错误",
+                                        set_subtitle: "仅供专家使用，写入错误的固件可能导致下位机无法启动",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_validation()), model.firmware_validation.as_ref().map_or(false, FirmwareValidation::is_overridable)),
+                                        add_prefix: validation_overridden_check_button = &CheckButton {
+                                            set_active: track!(model.changed(SlaveFirmwareUpdaterModel::validation_overridden()), model.validation_overridden),
+                                            connect_toggled(sender) => move |button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetValidationOverridden(button.is_active()));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&validation_overridden_check_button),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "校验文件",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_sidecar_check()), model.firmware_sidecar_check.is_some()),
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_sidecar_check()), &model.firmware_sidecar_check.as_ref().map(ToString::to_string).unwrap_or_default()),
+                                        add_prefix = &Image {
+                                            set_icon_name: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_sidecar_check()), model.firmware_sidecar_check.as_ref().map(FirmwareSidecarCheck::icon_name)),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "忽略校验文件不符",
+                                        set_subtitle: "仅供专家使用，例如校验文件属于其他版本的固件",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_sidecar_check()), model.firmware_sidecar_check.as_ref().map_or(false, FirmwareSidecarCheck::is_mismatch)),
+                                        add_prefix: sidecar_mismatch_overridden_check_button = &CheckButton {
+                                            set_active: track!(model.changed(SlaveFirmwareUpdaterModel::sidecar_mismatch_overridden()), model.sidecar_mismatch_overridden),
+                                            connect_toggled(sender) => move |button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetSidecarMismatchOverridden(button.is_active()));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&sidecar_mismatch_overridden_check_button),
+                                    },
+                                    add = &ComboRow {
+                                        set_title: "传输压缩",
+                                        set_subtitle: "压缩固件以缩短传输时间，需要下位机支持解压",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::capabilities()), model.gzip_available()),
+                                        set_model: Some(&{
+                                            let model = StringList::new(&[]);
+                                            for value in FirmwareCompression::iter() {
+                                                model.append(&value.to_string());
+                                            }
+                                            model
+                                        }),
+                                        set_selected: track!(model.changed(SlaveFirmwareUpdaterModel::compression()), FirmwareCompression::iter().position(|x| x == model.compression).unwrap() as u32),
+                                        connect_selected_notify(sender) => move |row| {
+                                            send!(sender, SlaveFirmwareUpdaterMsg::SetCompression(FirmwareCompression::iter().nth(row.selected() as usize).unwrap()));
+                                        }
+                                    },
+                                    add = &ComboRow {
+                                        set_title: "目标分区",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), &format!("下位机当前运行 {} 分区，自动模式下写入未运行的分区", model.device_firmware_info.as_ref().and_then(|info| info.active_slot.as_ref()).map_or("未知".to_string(), |slot| slot.to_uppercase()))),
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_dual_bank()),
+                                        set_model: Some(&{
+                                            let model = StringList::new(&[]);
+                                            for value in FirmwareSlot::iter() {
+                                                model.append(&value.to_string());
+                                            }
+                                            model
+                                        }),
+                                        set_selected: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_slot()), FirmwareSlot::iter().position(|x| x == model.firmware_slot).unwrap() as u32),
+                                        connect_selected_notify(sender) => move |row| {
+                                            send!(sender, SlaveFirmwareUpdaterMsg::SetFirmwareSlot(FirmwareSlot::iter().nth(row.selected() as usize).unwrap()));
+                                        }
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "更新后验证",
+                                        set_subtitle: "重启下位机，等待其重新上线后确认新固件的版本",
+                                        add_prefix: verify_after_update_check_button = &CheckButton {
+                                            set_active: track!(model.changed(SlaveFirmwareUpdaterModel::verify_after_update()), model.verify_after_update),
+                                            connect_toggled(sender) => move |button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetVerifyAfterUpdate(button.is_active()));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&verify_after_update_check_button),
+                                    },
+                                    add = &ExpanderRow {
+                                        set_title: "高级选项",
+                                        set_subtitle: "链路不稳定或下位机擦写 Flash 时出现看门狗复位时可调整",
+                                        add_row = &ActionRow {
+                                            set_title: "数据块大小",
+                                            set_subtitle: &format!("每次写入的字节数，范围 {} ~ {}", format_bytes(FIRMWARE_CHUNK_SIZE_MIN as u64), format_bytes(FIRMWARE_CHUNK_SIZE_MAX as u64)),
+                                            add_suffix = &SpinButton::with_range(FIRMWARE_CHUNK_SIZE_MIN as f64, FIRMWARE_CHUNK_SIZE_MAX as f64, FIRMWARE_CHUNK_SIZE_MIN as f64) {
+                                                set_value: track!(model.changed(SlaveFirmwareUpdaterModel::chunk_size()), model.chunk_size as f64),
+                                                connect_input => |button| validate_spin_button_input(button),
+                                                set_digits: 0,
+                                                set_valign: Align::Center,
+                                                connect_value_changed(sender) => move |button| {
+                                                    send!(sender, SlaveFirmwareUpdaterMsg::SetChunkSize(button.value() as usize));
+                                                },
+                                            },
+                                        },
+                                        add_row = &ActionRow {
+                                            set_title: "块间延时",
+                                            set_subtitle: "每个数据块发送后等待的时间（毫秒），为 0 时不等待",
+                                            add_suffix = &SpinButton::with_range(0.0, FIRMWARE_CHUNK_DELAY_MAX.as_millis() as f64, 1.0) {
+                                                set_value: track!(model.changed(SlaveFirmwareUpdaterModel::chunk_delay()), model.chunk_delay.as_millis() as f64),
+                                                connect_input => |button| validate_spin_button_input(button),
+                                                set_digits: 0,
+                                                set_valign: Align::Center,
+                                                connect_value_changed(sender) => move |button| {
+                                                    send!(sender, SlaveFirmwareUpdaterMsg::SetChunkDelay(Duration::from_millis(button.value() as u64)));
+                                                },
+                                            },
+                                        },
+                                        add_row = &ActionRow {
+                                            set_title: "写入重试次数",
+                                            set_subtitle: "写入遇到暂时性错误时等待后重试的最大次数，连接断开时不重试",
+                                            add_suffix = &SpinButton::with_range(0.0, FIRMWARE_WRITE_RETRY_MAX as f64, 1.0) {
+                                                set_value: track!(model.changed(SlaveFirmwareUpdaterModel::write_retry_limit()), model.write_retry_limit as f64),
+                                                connect_input => |button| validate_spin_button_input(button),
+                                                set_digits: 0,
+                                                set_valign: Align::Center,
+                                                connect_value_changed(sender) => move |button| {
+                                                    send!(sender, SlaveFirmwareUpdaterMsg::SetWriteRetryLimit(button.value() as u32));
+                                                },
+                                            },
+                                        },
+                                        add_row = &ActionRow {
+                                            set_title: "跳过解锁状态检查",
+                                            set_subtitle: "危险：仅用于不报告解锁状态的台架环境，推进器未上锁时写入固件可能导致其意外转动",
+                                            add_css_class: "error",
+                                            add_prefix: arming_check_skipped_check_button = &CheckButton {
+                                                set_active: track!(model.changed(SlaveFirmwareUpdaterModel::arming_check_skipped()), model.arming_check_skipped),
+                                                connect_toggled(sender) => move |button| {
+                                                    send!(sender, SlaveFirmwareUpdaterMsg::SetArmingCheckSkipped(button.is_active()));
+                                                },
+                                            },
+                                            set_activatable_widget: Some(&arming_check_skipped_check_button),
+                                        },
+                                        add_row = &ActionRow {
+                                            set_title: "逐块确认",
+                                            set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), if model.acked_transfer_available() { "每个数据块等待下位机确认后再发送下一块，超时自动重传，适用于无线中继等易丢包的链路" } else { "下位机不支持逐块确认" }),
+                                            set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.acked_transfer_available()),
+                                            add_prefix: acked_transfer_check_button = &CheckButton {
+                                                set_active: track!(model.changed(SlaveFirmwareUpdaterModel::acked_transfer()), model.acked_transfer),
+                                                connect_toggled(sender) => move |button| {
+                                                    send!(sender, SlaveFirmwareUpdaterMsg::SetAckedTransfer(button.is_active()));
+                                                },
+                                            },
+                                            set_activatable_widget: Some(&acked_transfer_check_button),
+                                        },
+                                    },
                                 },
-                            }
+                                append = &PreferencesGroup {
+                                    set_title: "固件信息",
+                                    set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()), model.firmware_file_path.is_some()),
+                                    set_description: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::firmware_validation()), match (&model.firmware_validation, &model.firmware_manifest) {
+                                        (Some(_), None) => Some("所选文件不包含固件信息"),
+                                        _ => None,
+                                    }),
+                                    add = &ActionRow {
+                                        set_title: "该固件似乎已安装在下位机上",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_md5()) || model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), &model.firmware_already_installed().unwrap_or_default()),
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_md5()) || model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_already_installed().is_some()),
+                                        add_css_class: "warning",
+                                        add_prefix = &Image {
+                                            set_icon_name: Some("dialog-warning-symbolic"),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "仍要重新写入",
+                                        set_subtitle: "重新写入相同的固件通常没有必要",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_md5()) || model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_already_installed().is_some()),
+                                        add_prefix: reinstall_confirmed_check_button = &CheckButton {
+                                            set_active: track!(model.changed(SlaveFirmwareUpdaterModel::reinstall_confirmed()), model.reinstall_confirmed),
+                                            connect_toggled(sender) => move |button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetReinstallConfirmed(button.is_active()));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&reinstall_confirmed_check_button),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "版本",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()), model.firmware_manifest_field(|manifest| &manifest.version)),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "目标主板",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()), model.firmware_manifest_field(|manifest| &manifest.board)),
+                                        add_prefix = &Image {
+                                            set_icon_name: Some("dialog-error-symbolic"),
+                                            set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_board_mismatch().is_some()),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "下位机主板",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.device_board()),
+                                        add_prefix = &Image {
+                                            set_icon_name: Some("dialog-error-symbolic"),
+                                            set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_board_mismatch().is_some()),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "固件与下位机主板不符",
+                                        set_subtitle: "两种主板的固件互不兼容，写入后下位机将无法启动",
+                                        add_css_class: "error",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_board_mismatch().is_some()),
+                                        add_prefix = &Image {
+                                            set_icon_name: Some("dialog-error-symbolic"),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "忽略主板不符",
+                                        set_subtitle: "仅供专家使用，例如下位机报告的主板标识有误",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_board_mismatch().is_some()),
+                                        add_prefix: board_mismatch_overridden_check_button = &CheckButton {
+                                            set_active: track!(model.changed(SlaveFirmwareUpdaterModel::board_mismatch_overridden()), model.board_mismatch_overridden),
+                                            connect_toggled(sender) => move |button| {
+                                                send!(sender, SlaveFirmwareUpdaterMsg::SetBoardMismatchOverridden(button.is_active()));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&board_mismatch_overridden_check_button),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "未验证目标主板",
+                                        set_subtitle: "固件或下位机未提供主板标识，无法确认固件适用于该下位机，请核对后再更新",
+                                        add_css_class: "warning",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), model.firmware_board_unverified()),
+                                        add_prefix = &Image {
+                                            set_icon_name: Some("dialog-warning-symbolic"),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "分区容量",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_image_size()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), &model.firmware_capacity_description()),
+                                        add_prefix = &Image {
+                                            set_icon_name: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_image_size()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()), Some(match (model.firmware_capacity_exceeded(), model.device_firmware_info.as_ref().map_or(0, |info| info.partition_size)) {
+                                                (Some(_), _) => "dialog-error-symbolic",
+                                                (None, 0) => "dialog-warning-symbolic",
+                                                (None, _) => "emblem-ok-symbolic",
+                                            })),
+                                        },
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "构建日期",
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()), model.firmware_manifest_field(|manifest| &manifest.build_date)),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "更新说明",
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()), model.firmware_manifest.as_ref().map_or(false, |manifest| !manifest.changelog.is_empty())),
+                                        set_subtitle: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()), model.firmware_manifest_field(|manifest| &manifest.changelog)),
+                                    },
+                                },
+                                append = &PreferencesGroup {
+                                    set_title: "附加镜像",
+                                    set_description: Some("在固件之后依次写入，如文件系统镜像；任一部分失败时不再写入其后的部分"),
+                                    set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()), model.firmware_file_path.is_some()),
+                                    add = &GtkBox {
+                                        set_orientation: Orientation::Vertical,
+                                        factory!(model.extra_firmware_parts),
+                                    },
+                                    add = &ActionRow {
+                                        set_title: "添加镜像",
+                                        add_suffix: add_firmware_part_button = &Button {
+                                            set_label: "浏览",
+                                            set_valign: Align::Center,
+                                            connect_clicked(sender, window) => move |_button| {
+                                                select_path(FileChooserAction::Open, &[], load_last_firmware_directory().as_deref(), &window, clone!(@strong sender => move |path| {
+                                                    if let Some(path) = path {
+                                                        send!(sender, SlaveFirmwareUpdaterMsg::AddFirmwarePart(path));
+                                                    }
+                                                }));
+                                            },
+                                        },
+                                        set_activatable_widget: Some(&add_firmware_part_button),
+                                    },
+                                },
+                                append = &Button {
+                                    set_css_classes: &["suggested-action", "pill"],
+                                    set_halign: Align::Center,
+                                    set_label: "开始更新",
+                                    set_sensitive: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_file_path()) || model.changed(SlaveFirmwareUpdaterModel::firmware_validation()) || model.changed(SlaveFirmwareUpdaterModel::validation_overridden()) || model.changed(SlaveFirmwareUpdaterModel::firmware_manifest()) || model.changed(SlaveFirmwareUpdaterModel::device_firmware_info()) || model.changed(SlaveFirmwareUpdaterModel::firmware_md5()) || model.changed(SlaveFirmwareUpdaterModel::reinstall_confirmed()) || model.changed(SlaveFirmwareUpdaterModel::board_mismatch_overridden()) || model.changed(SlaveFirmwareUpdaterModel::firmware_image_size()) || model.changed(SlaveFirmwareUpdaterModel::firmware_sidecar_check()) || model.changed(SlaveFirmwareUpdaterModel::sidecar_mismatch_overridden()), model.get_firmware_file_path().as_ref().map_or(false, |pathbuf| pathbuf.exists() && pathbuf.is_file()) && model.firmware_board_allowed() && model.firmware_capacity_exceeded().is_none() && model.firmware_sidecar_allowed() && (model.firmware_already_installed().is_none() || model.reinstall_confirmed) && match &model.firmware_validation {
+                                        None => false,
+                                        Some(validation) => !validation.is_invalid() || (validation.is_overridable() && model.validation_overridden),
+                                    }),
+                                    connect_clicked(sender) => move |_button| {
+                                        send!(sender, SlaveFirmwareUpdaterMsg::StartUpload);
+                                    },
+                                }
+                            },
                         },
-                    },
-                    append = &StatusPage {
-                        set_icon_name: Some("folder-download-symbolic"),
-                        set_title: "正在更新固件...",
-                        set_hexpand: true,
-                        set_vexpand: true,
-                        set_description: Some("请不要切断连接或电源。"),
-                        set_child = Some(&GtkBox) {
-                            set_orientation: Orientation::Vertical,
-                            set_spacing: 50,
-                            append = &ProgressBar {
-                                set_fraction: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()), *model.get_firmware_uploading_progress() as f64)
+                        append = &StatusPage {
+                            set_icon_name: Some("folder-download-symbolic"),
+                            set_title: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_downloading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()) || model.changed(SlaveFirmwareUpdaterModel::firmware_reboot_phase()), &if model.firmware_downloading { "正在下载固件...".to_string() } else if model.firmware_verifying { model.firmware_reboot_phase.unwrap_or(FirmwareRebootPhase::Disconnecting).to_string() } else { "正在更新固件...".to_string() }),
+                            set_hexpand: true,
+                            set_vexpand: true,
+                            set_description: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_downloading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()), Some(if model.firmware_downloading { "下载完成后将返回文件选择页。" } else if model.firmware_verifying { "固件已写入，下位机重新上线后将查询固件版本。" } else { "请不要切断连接或电源。" })),
+                            set_child = Some(&GtkBox) {
+                                set_orientation: Orientation::Vertical,
+                                set_spacing: 50,
+                                append = &GtkBox {
+                                    set_orientation: Orientation::Vertical,
+                                    set_spacing: 10,
+                                    append: upload_progress_bar = &ProgressBar {
+                                        set_show_text: true,
+                                        set_text: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()), Some(&format!("{:.0}%", model.get_firmware_uploading_progress().max(0.0) * 100.0))),
+                                    },
+                                    append = &ProgressBar {
+                                        set_show_text: true,
+                                        set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_write_progress()), model.firmware_write_progress.is_some()),
+                                        set_fraction: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_write_progress()), model.firmware_write_progress.unwrap_or_default()),
+                                        set_text: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_write_progress()), Some(&format!("正在写入闪存 {:.0}%", model.firmware_write_progress.unwrap_or_default() * 100.0))),
+                                    },
+                                    append = &Label {
+                                        add_css_class: "dim-label",
+                                        set_label: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_details()) || model.changed(SlaveFirmwareUpdaterModel::current_firmware_part()) || model.changed(SlaveFirmwareUpdaterModel::firmware_preparing()) || model.changed(SlaveFirmwareUpdaterModel::firmware_write_progress()) || model.changed(SlaveFirmwareUpdaterModel::firmware_download_progress()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()) || model.changed(SlaveFirmwareUpdaterModel::firmware_paused_since()) || model.changed(SlaveFirmwareUpdaterModel::firmware_pause_timed_out()), &if model.firmware_downloading { model.firmware_download_description() } else if model.firmware_verifying { format!("最长等待 {} 秒", FIRMWARE_REBOOT_TIMEOUT.as_secs()) } else { model.firmware_uploading_description() }),
+                                    },
+                                },
+                                append = &ToggleButton {
+                                    set_css_classes: &["pill"],
+                                    set_halign: Align::Center,
+                                    set_label: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_paused_since()), if model.firmware_paused_since.is_some() { "继续传输" } else { "暂停传输" }),
+                                    set_tooltip_text: Some("暂时停止发送固件数据，为视频等其他数据让出链路带宽，继续后从暂停处的数据块开始传输"),
+                                    set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_write_progress()), model.firmware_uploading && model.firmware_write_progress.is_none()),
+                                    set_active: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_paused_since()), model.firmware_paused_since.is_some()),
+                                    connect_toggled(sender) => move |button| {
+                                        send!(sender, SlaveFirmwareUpdaterMsg::SetTransferPaused(button.is_active()));
+                                    },
+                                },
+                                append = &Button {
+                                    set_css_classes: &["destructive-action", "pill"],
+                                    set_halign: Align::Center,
+                                    set_label: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()), if model.firmware_verifying { "停止等待" } else { "中止更新" }),
+                                    set_tooltip_text: Some("中止更新会使下位机上的固件不完整，需要重新连接后再次更新"),
+                                    set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verifying()), model.update_in_progress()),
+                                    connect_clicked(sender) => move |_button| {
+                                        send!(sender, SlaveFirmwareUpdaterMsg::CancelUpdate);
+                                    },
+                                },
                             },
                         },
-                    },
-                    append = &StatusPage {
-                        set_icon_name: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()), if *model.get_firmware_uploading_progress() >= 0.0 { Some("emblem-ok-symbolic") } else { Some("dialog-warning-symbolic") }),
-                        set_title: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()), if *model.get_firmware_uploading_progress() >= 0.0 { "固件更新成功" } else { "固件更新失败" }),
-                        set_hexpand: true,
-                        set_vexpand: true,
-                        set_description: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()), Some(if *model.get_firmware_uploading_progress() >= 0.0 { "机器人将自动重启，请稍后手动进行连接。" } else { "请检查文件与网络连接是否正常。" })),
-                        set_child = Some(&Button) {
-                            set_css_classes: &["suggested-action", "pill"],
-                            set_halign: Align::Center,
-                            set_label: "完成",
-                            connect_clicked(window) => move |_button| {
-                                window.destroy();
+                        append = &StatusPage {
+                            set_icon_name: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verification()), if model.firmware_update_succeeded() { Some("emblem-ok-symbolic") } else { Some("dialog-warning-symbolic") }),
+                            set_title: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verification()), if *model.get_firmware_uploading_progress() < 0.0 { "固件更新失败" } else if model.firmware_update_succeeded() { "固件更新成功" } else { "固件验证失败" }),
+                            set_hexpand: true,
+                            set_vexpand: true,
+                            set_description: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()) || model.changed(SlaveFirmwareUpdaterModel::firmware_update_error()) || model.changed(SlaveFirmwareUpdaterModel::firmware_part_results()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verification()) || model.changed(SlaveFirmwareUpdaterModel::firmware_digest_check()), Some(&model.firmware_result_description())),
+                            set_child = Some(&GtkBox) {
+                                set_orientation: Orientation::Horizontal,
+                                set_halign: Align::Center,
+                                set_spacing: 12,
+                                append = &Button {
+                                    set_css_classes: &["pill"],
+                                    set_label: "重试",
+                                    set_visible: track!(model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()) || model.changed(SlaveFirmwareUpdaterModel::firmware_verification()), !model.firmware_update_succeeded()),
+                                    connect_clicked(sender) => move |_button| {
+                                        send!(sender, SlaveFirmwareUpdaterMsg::RetryUpdate);
+                                    },
+                                },
+                                append = &Button {
+                                    set_css_classes: &["suggested-action", "pill"],
+                                    set_label: "完成",
+                                    connect_clicked(window) => move |_button| {
+                                        window.destroy();
+                                    },
+                                },
                             },
                         },
                     },
@@ -260,6 +3494,108 @@ impl MicroWidgets<SlaveFirmwareUpdaterModel> for SlaveFirmwareUpdaterWidgets {
             },
         }
     }
+
+    fn post_init() {
+        let drop_target = DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+        drop_target.connect_drop(clone!(@strong sender => move |_target, value, _x, _y| {
+            match value.get::<gdk::FileList>() {
+                Ok(file_list) => {
+                    send!(sender, SlaveFirmwareUpdaterMsg::FilesDropped(file_list.files().iter().filter_map(|file| file.path()).collect()));
+                    true
+                },
+                Err(_) => false,
+            }
+        }));
+        window.add_controller(&drop_target);
+        let close_inhibited = Rc::new(Cell::new(false));
+        window.connect_close_request(clone!(@strong close_inhibited, @strong sender => move |_window| {
+            if close_inhibited.get() {
+                send!(sender, SlaveFirmwareUpdaterMsg::CloseRequested);
+                Inhibit(true)
+            } else {
+                Inhibit(false)
+            }
+        }));
+        let progress_target = Rc::new(Cell::new(0.0));
+        upload_progress_bar.add_tick_callback(clone!(@strong progress_target => move |bar, _clock| {
+            let (current, target) = (bar.fraction(), progress_target.get());
+            if current != target {
+                // 进度减小（重新开始或失败）时直接跳转，增大时每帧移动剩余距离的一部分，使进度条在两次更新之间平滑前进
+                bar.set_fraction(if target < current || target - current < 0.001 { target } else { current + (target - current) * FIRMWARE_PROGRESS_SMOOTHING });
+            }
+            glib::Continue(true)
+        }));
+    }
+
+    additional_fields! {
+        progress_target: Rc<Cell<f64>>,
+        close_inhibited: Rc<Cell<bool>>,
+    }
+
+    fn post_view() {
+        self.close_inhibited.set(model.update_in_progress());
+        if model.changed(SlaveFirmwareUpdaterModel::closing()) && model.closing {
+            self.window.close();
+        }
+        if model.changed(SlaveFirmwareUpdaterModel::close_prompted()) && model.close_prompted {
+            let dialog = MessageDialog::builder()
+                .message_type(MessageType::Warning)
+                .text(if model.firmware_verifying { "正在等待下位机重启" } else { "正在更新固件" })
+                .secondary_text(if model.firmware_verifying { "固件已写入，关闭窗口将不再确认下位机是否正常启动。" } else { "中止更新会使下位机上的固件不完整，下位机可能无法正常启动，需要重新连接后再次更新。" })
+                .modal(true)
+                .transient_for(&self.window)
+                .build();
+            dialog.add_button("继续更新", ResponseType::Cancel);
+            dialog.add_button("中止并关闭", ResponseType::Accept);
+            dialog.set_default_response(ResponseType::Cancel);
+            dialog.connect_response(clone!(@strong sender => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    send!(sender, SlaveFirmwareUpdaterMsg::AbortAndClose);
+                }
+                dialog.destroy();
+            }));
+            dialog.show();
+        }
+        if model.changed(SlaveFirmwareUpdaterModel::firmware_uploading_progress()) {
+            self.progress_target.set(model.get_firmware_uploading_progress().max(0.0) as f64);
+        }
+        if model.changed(SlaveFirmwareUpdaterModel::disarm_prompted()) && model.disarm_prompted {
+            let armed = model.device_firmware_info.as_ref().and_then(|info| info.armed);
+            let dialog = MessageDialog::builder()
+                .message_type(MessageType::Warning)
+                .text(if armed == Some(true) { "推进器已解锁" } else { "无法确认推进器已上锁" })
+                .secondary_text("在推进器解锁时写入固件可能导致推进器意外转动。请先将推进器上锁，下位机确认上锁后才会开始更新。")
+                .modal(true)
+                .transient_for(&self.window)
+                .build();
+            dialog.add_button("取消", ResponseType::Cancel);
+            dialog.add_button("上锁并继续", ResponseType::Accept);
+            dialog.connect_response(clone!(@strong sender => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    send!(sender, SlaveFirmwareUpdaterMsg::DisarmVehicle);
+                }
+                dialog.destroy();
+            }));
+            dialog.show();
+        }
+        if model.changed(SlaveFirmwareUpdaterModel::firmware_file_browsing()) && model.firmware_file_browsing {
+            let filter = FileFilter::new();
+            for suffix in model.firmware_file_suffixes() {
+                filter.add_suffix(suffix);
+            }
+            filter.set_name(Some(if model.update_target.as_deref() == Some("sbc") { "系统镜像" } else { "固件文件" }));
+            select_path(FileChooserAction::Open, &[filter], load_last_firmware_directory().as_deref(), &self.window, clone!(@strong sender => move |path| {
+                if let Some(path) = path {
+                    send!(sender, SlaveFirmwareUpdaterMsg::FirmwareFileSelected(path));
+                }
+            }));
+        }
+        if model.changed(SlaveFirmwareUpdaterModel::update_history()) {
+            if let Some(records) = &model.update_history {
+                firmware_update_history_dialog(&model.peer_address(), records, &self.window);
+            }
+        }
+    }
 }
 
 impl Debug for SlaveFirmwareUpdaterWidgets {
@@ -267,3 +3603,314 @@ impl Debug for SlaveFirmwareUpdaterWidgets {
         self.root_widget().fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每次至多接受 `max_write` 字节的写入端，按 `script` 依次在部分写入之间插入错误
+    struct PartialWriter {
+        written: Vec<u8>,
+        max_write: usize,
+        script: VecDeque<Option<ErrorKind>>, // 为 `None` 或用尽后正常写入
+    }
+
+    impl async_std::io::Write for PartialWriter {
+        fn poll_write(mut self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>, buf: &[u8]) -> std::task::Poll<Result<usize, IOError>> {
+            if let Some(Some(kind)) = self.script.pop_front() {
+                return std::task::Poll::Ready(Err(IOError::new(kind, "模拟的写入错误")));
+            }
+            let len = buf.len().min(self.max_write);
+            self.written.extend_from_slice(&buf[..len]);
+            std::task::Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), IOError>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), IOError>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    fn test_chunk() -> Vec<u8> {
+        (0..4096u32).map(|index| (index % 251) as u8).collect()
+    }
+
+    #[test]
+    fn write_all_with_retry_survives_partial_writes() {
+        let chunk = test_chunk();
+        let mut writer = PartialWriter { written: Vec::new(), max_write: 7, script: VecDeque::new() };
+        let retries = task::block_on(write_all_with_retry(&mut writer, &chunk, 3)).unwrap();
+        assert_eq!(retries, 0);
+        assert_eq!(writer.written, chunk);
+    }
+
+    #[test]
+    fn write_all_with_retry_resumes_after_transient_errors() {
+        let chunk = test_chunk();
+        let script = [None, None, Some(ErrorKind::Interrupted), None, Some(ErrorKind::WouldBlock)].into_iter().collect();
+        let mut writer = PartialWriter { written: Vec::new(), max_write: 1000, script };
+        let retries = task::block_on(write_all_with_retry(&mut writer, &chunk, 3)).unwrap();
+        assert_eq!(retries, 2);
+        assert_eq!(writer.written, chunk); // 重试从中断处继续，不重复也不遗漏数据
+    }
+
+    #[test]
+    fn write_all_with_retry_gives_up_after_limit() {
+        let script = [Some(ErrorKind::TimedOut), Some(ErrorKind::TimedOut)].into_iter().collect();
+        let mut writer = PartialWriter { written: Vec::new(), max_write: 16, script };
+        let err = task::block_on(write_all_with_retry(&mut writer, &test_chunk(), 1)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn write_all_with_retry_does_not_retry_fatal_errors() {
+        let script = [Some(ErrorKind::ConnectionReset)].into_iter().collect();
+        let mut writer = PartialWriter { written: Vec::new(), max_write: 16, script };
+        let err = task::block_on(write_all_with_retry(&mut writer, &test_chunk(), 3)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert!(writer.written.is_empty());
+    }
+
+    #[test]
+    fn empty_firmware_cannot_be_overridden() {
+        assert_eq!(validate_firmware(&[], 0), FirmwareValidation::Empty);
+        assert!(!validate_firmware(&[], 0).is_overridable());
+        assert!(!FirmwareValidation::Unreadable(String::new()).is_overridable());
+        assert!(FirmwareValidation::Invalid(String::new()).is_overridable());
+    }
+
+    const TEST_SHA256: &str = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+
+    #[test]
+    fn parse_checksum_file_reads_digest_and_file_name() {
+        let content = format!("{}  other.bin\n{} *firmware.bin\n", "0".repeat(64), TEST_SHA256);
+        assert_eq!(parse_checksum_file(&content, "firmware.bin", 64), Some(TEST_SHA256.to_ascii_lowercase()));
+        assert_eq!(parse_checksum_file(&content, "missing.bin", 64), None);
+        // 只有一行记录时不要求文件名一致
+        let content = format!("{}  renamed.bin\n", TEST_SHA256);
+        assert_eq!(parse_checksum_file(&content, "firmware.bin", 64), Some(TEST_SHA256.to_ascii_lowercase()));
+    }
+
+    #[test]
+    fn parse_checksum_file_reads_bare_digest() {
+        let content = format!("# sha256\n{}\n", TEST_SHA256);
+        assert_eq!(parse_checksum_file(&content, "firmware.bin", 64), Some(TEST_SHA256.to_ascii_lowercase()));
+        assert_eq!(parse_checksum_file(TEST_SHA256, "firmware.bin", 32), None); // 长度与算法不符
+        assert_eq!(parse_checksum_file("not a digest", "firmware.bin", 64), None);
+    }
+
+    #[test]
+    fn parse_checksum_file_reads_bsd_format() {
+        let content = format!("SHA256 (firmware.bin) = {}\n", TEST_SHA256);
+        assert_eq!(parse_checksum_file(&content, "firmware.bin", 64), Some(TEST_SHA256.to_ascii_lowercase()));
+    }
+
+    #[test]
+    fn format_bytes_switches_units_at_boundaries() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(format_bytes(1024 * 1024), "1.0 MiB");
+    }
+
+    #[test]
+    fn parse_intel_hex_concatenates_records() {
+        let text = ":0400000001020304F2\n:020004000506EF\n:00000001FF\n";
+        assert_eq!(parse_intel_hex(text), Ok(vec![1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn parse_intel_hex_rejects_bad_checksum() {
+        assert!(parse_intel_hex(":0400000001020304F3\n:00000001FF\n").unwrap_err().contains("校验和"));
+    }
+
+    #[test]
+    fn parse_intel_hex_applies_extended_linear_address() {
+        // 扩展线性地址 0x0800 后的记录位于 0x08000010，镜像从最低地址开始
+        let text = ":020000040800F2\n:01001200CC21\n:02001000AABB89\n:00000001FF\n";
+        assert_eq!(parse_intel_hex(text), Ok(vec![0xAA, 0xBB, 0xCC]));
+    }
+
+    #[test]
+    fn parse_intel_hex_rejects_gap() {
+        let text = ":0400000001020304F2\n:0100080007F0\n:00000001FF\n";
+        assert!(parse_intel_hex(text).unwrap_err().contains("0x00000004"));
+    }
+
+    #[test]
+    fn parse_intel_hex_rejects_address_overflow() {
+        let text = ":02000004FFFFFC\n:02FFFE000102FE\n:00000001FF\n";
+        assert!(parse_intel_hex(text).is_err());
+    }
+
+    /// 建立一对本地 TCP 连接，返回（下位机一侧，上位机一侧）
+    async fn tcp_pair() -> (TcpStream, TcpStream) {
+        let listener = async_std::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let tcp_stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (slave, _) = listener.accept().await.unwrap();
+        (slave, tcp_stream)
+    }
+
+    fn window_ack_frame(framing: FrameFormat, chunks: u32) -> Vec<u8> {
+        framing.encode(&format!(r#"{{"firmware_window_ack":{{"chunks":{}}}}}"#, chunks)).unwrap()
+    }
+
+    #[test]
+    fn receive_packet_keeps_buffered_frames_across_calls() {
+        for framing in [FrameFormat::Legacy, FrameFormat::LengthPrefixed] {
+            task::block_on(async {
+                let (mut slave, mut tcp_stream) = tcp_pair().await;
+                let mut frame_reader = FrameReader::new(framing);
+                let first = window_ack_frame(framing, 1);
+                let (head, tail) = first.split_at(first.len() / 2);
+                slave.write_all(head).await.unwrap();
+                let err = receive_packet::<SlaveFirmwareWindowAckPacket>(&mut tcp_stream, &mut frame_reader, Duration::from_millis(100)).await.unwrap_err();
+                assert_eq!(err.kind(), ErrorKind::TimedOut);
+                // 超时前收到的半个数据包、其余部分与之后的两个数据包一同到达，依次读取时均不丢失
+                let mut rest = tail.to_vec();
+                rest.extend(window_ack_frame(framing, 2));
+                rest.extend(window_ack_frame(framing, 3));
+                slave.write_all(&rest).await.unwrap();
+                for chunks in 1..=3 {
+                    let packet = receive_packet::<SlaveFirmwareWindowAckPacket>(&mut tcp_stream, &mut frame_reader, Duration::from_secs(1)).await.unwrap();
+                    assert_eq!(packet.firmware_window_ack.chunks, chunks);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn window_ack_split_across_stall_threshold_is_received() {
+        task::block_on(async {
+            let (mut slave, mut tcp_stream) = tcp_pair().await;
+            let mut frame_reader = FrameReader::new(FrameFormat::LengthPrefixed);
+            let frame = window_ack_frame(FrameFormat::LengthPrefixed, 8);
+            let slave_task = task::spawn(async move {
+                let (head, tail) = frame.split_at(3);
+                slave.write_all(head).await.unwrap();
+                task::sleep(FIRMWARE_WINDOW_STALL_THRESHOLD + Duration::from_millis(200)).await;
+                slave.write_all(tail).await.unwrap();
+                slave
+            });
+            let stalls = Mutex::new(Vec::new());
+            wait_for_window_ack(&mut tcp_stream, &mut frame_reader, 8, |stalled| stalls.lock().unwrap().push(stalled)).await.unwrap();
+            assert_eq!(*stalls.lock().unwrap(), vec![true, false]);
+            slave_task.await;
+        });
+    }
+
+    #[test]
+    fn backup_header_is_split_by_length_prefix() {
+        let data = b"{\x00\x00\x00\x10}";
+        let mut bytes = FrameFormat::LengthPrefixed.encode(r#"{"firmware_read":{"size":6,"md5":"abc"}}"#).unwrap();
+        bytes.extend_from_slice(data);
+        let (header, consumed) = parse_backup_header(&bytes, FrameFormat::LengthPrefixed).unwrap().unwrap();
+        assert_eq!((header.size, header.md5.as_str(), &bytes[consumed..]), (6, "abc", &data[..]));
+        for len in 0..consumed {
+            assert!(parse_backup_header(&bytes[..len], FrameFormat::LengthPrefixed).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn backup_header_rejects_oversized_prefix() {
+        let bytes = b"{\"firmware_read\":{\"size\":6,\"md5\":\"abc\"}}";
+        assert_eq!(parse_backup_header(bytes, FrameFormat::LengthPrefixed).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn legacy_backup_header_skips_leading_bytes() {
+        let bytes = b"\0\n{\"firmware_read\":{\"size\":2,\"md5\":\"abc\"}}{}";
+        let (header, consumed) = parse_backup_header(bytes, FrameFormat::Legacy).unwrap().unwrap();
+        assert_eq!((header.size, &bytes[consumed..]), (2, &b"{}"[..]));
+        assert!(parse_backup_header(&bytes[..20], FrameFormat::Legacy).unwrap().is_none());
+    }
+
+    #[test]
+    fn firmware_updater_pages_step_forward_and_back() {
+        assert_eq!((0..=FIRMWARE_UPDATER_LAST_PAGE).map(next_firmware_updater_page).collect::<Vec<_>>(), vec![1, 2, 3, 3]);
+        assert_eq!((0..=FIRMWARE_UPDATER_LAST_PAGE).map(previous_firmware_updater_page).collect::<Vec<_>>(), vec![0, 0, 1, 1]);
+    }
+
+    /// 写入 `len` 字节的临时固件文件，文件名包含 `name` 以免并行的测试相互覆盖
+    fn temp_firmware(name: &str, len: usize) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rov-host-test-{}-{}.bin", std::process::id(), name));
+        std::fs::write(&path, vec![0x5a; len]).unwrap();
+        path
+    }
+
+    #[test]
+    fn dry_run_simulation_reports_every_part() {
+        let parts = vec![(temp_firmware("simulation-0", 1000), FirmwareTarget::Firmware), (temp_firmware("simulation-1", 300), FirmwareTarget::Firmware)];
+        let messages = Mutex::new(Vec::new());
+        task::block_on(simulate_firmware_update(parts.clone(), 256, Arc::new(Mutex::new(false)), Duration::ZERO, |msg| messages.lock().unwrap().push(msg)));
+        let messages = messages.into_inner().unwrap();
+        assert_eq!(messages.len(), 2 * (FIRMWARE_DRY_RUN_STEPS as usize + 2) + 1);
+        for (index, (path, _)) in parts.iter().enumerate() {
+            let start = index * (FIRMWARE_DRY_RUN_STEPS as usize + 2);
+            assert!(matches!(messages[start], SlaveFirmwareUpdaterMsg::FirmwarePartStarted(part) if part == index));
+            let total = std::fs::metadata(path).unwrap().len();
+            assert!(matches!(&messages[start + FIRMWARE_DRY_RUN_STEPS as usize], SlaveFirmwareUpdaterMsg::FirmwareUploadProgressUpdated(progress) if progress.sent == total && progress.total == total));
+            assert!(matches!(messages[start + FIRMWARE_DRY_RUN_STEPS as usize + 1], SlaveFirmwareUpdaterMsg::FirmwarePartFinished(part, Ok(())) if part == index));
+            std::fs::remove_file(path).unwrap_or_default();
+        }
+        assert!(matches!(messages.last(), Some(SlaveFirmwareUpdaterMsg::FirmwareUploadFinished(Ok(())))));
+    }
+
+    /// 依次处理 `messages` 及处理过程中模型发给自己的消息，直到更新任务结束且没有待处理的消息
+    fn run_updater(model: &mut SlaveFirmwareUpdaterModel, messages: Vec<SlaveFirmwareUpdaterMsg>) {
+        let context = glib::MainContext::new();
+        let (parent_sender, _parent_receiver) = glib::MainContext::channel::<SlaveMsg>(glib::PRIORITY_DEFAULT);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let queue = Rc::new(RefCell::new(VecDeque::from(messages)));
+        receiver.attach(Some(&context), clone!(@strong queue => move |msg| {
+            queue.borrow_mut().push_back(msg);
+            glib::Continue(true)
+        }));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let msg = queue.borrow_mut().pop_front();
+            match msg {
+                Some(msg) => model.update(msg, &parent_sender, sender.clone()),
+                None if context.iteration(false) => (),
+                None if model.update_task.is_none() => break,
+                None => {
+                    assert!(Instant::now() < deadline, "模拟更新未在预期时间内结束");
+                    std::thread::sleep(Duration::from_millis(1));
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn dry_run_upload_reaches_success_page_and_steps_back() {
+        let path = temp_firmware("dry-run", 2048);
+        let mut model = SlaveFirmwareUpdaterModel::new(None, true, Rc::new(Cell::new(None)));
+        model.dry_run_duration = Duration::ZERO;
+        run_updater(&mut model, vec![SlaveFirmwareUpdaterMsg::NextStep]);
+        assert_eq!(model.current_page, 1);
+        model.firmware_file_path = Some(path.clone());
+        run_updater(&mut model, vec![SlaveFirmwareUpdaterMsg::StartUpload]);
+        assert_eq!(model.current_page, FIRMWARE_UPDATER_LAST_PAGE);
+        assert!(!model.firmware_uploading);
+        assert_eq!(model.firmware_update_error, None);
+        assert_eq!(model.firmware_uploading_progress, 1.0);
+        assert!(matches!(model.firmware_part_results.as_slice(), [(_, Some(Ok(())))]));
+        // 结果页之后不再前进
+        run_updater(&mut model, vec![SlaveFirmwareUpdaterMsg::NextStep]);
+        assert_eq!(model.current_page, FIRMWARE_UPDATER_LAST_PAGE);
+        // 返回文件选择页时清除结果，保留已选择的文件
+        run_updater(&mut model, vec![SlaveFirmwareUpdaterMsg::PreviousStep]);
+        assert_eq!(model.current_page, 1);
+        assert!(model.firmware_part_results.is_empty());
+        assert_eq!(model.firmware_uploading_progress, 0.0);
+        assert_eq!(model.firmware_file_path.as_ref(), Some(&path));
+        run_updater(&mut model, vec![SlaveFirmwareUpdaterMsg::PreviousStep, SlaveFirmwareUpdaterMsg::PreviousStep]);
+        assert_eq!(model.current_page, 0);
+        std::fs::remove_file(&path).unwrap_or_default();
+    }
+}
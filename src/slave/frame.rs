@@ -0,0 +1,391 @@
+/* frame.rs
+ *
+ * Copyright 2021-2022 Bohong Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::{collections::VecDeque, io::{Error as IOError, ErrorKind}};
+
+use async_std::{io::{Read, Write}, prelude::*};
+
+use super::capabilities::Capabilities;
+
+/// 单个 JSON 数据包允许的最大字节数，超过该值视为下位机数据异常
+pub const MAX_FRAME_BYTES: usize = 64 * 1024;
+/// 长度前缀的字节数，前缀为大端序的数据包长度
+pub const FRAME_LENGTH_PREFIX_BYTES: usize = 4;
+
+/// 数据包在 TCP 字节流中的分帧方式。
+///
+/// 由于 `MAX_FRAME_BYTES` 远小于 16 MiB，带长度前缀的数据包的第一个字节总是 `\0`，而旧格式的数据包总是以 `{` 开头，
+/// 下位机据此区分两种格式，并以收到的请求所用的格式回复；因此旧格式总是可用，声明支持长度前缀的下位机同样接受旧格式的请求。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// 直接发送 JSON 文本，接收时按花括号的配对关系切分，兼容旧版固件
+    Legacy,
+    /// 每个数据包前附带 4 字节的长度
+    LengthPrefixed,
+}
+
+impl Default for FrameFormat {
+    fn default() -> Self {
+        FrameFormat::Legacy
+    }
+}
+
+impl FrameFormat {
+    /// 下位机声明支持时使用长度前缀，否则使用旧格式
+    pub fn from_capabilities(capabilities: &Capabilities) -> FrameFormat {
+        if capabilities.length_prefixed_frames { FrameFormat::LengthPrefixed } else { FrameFormat::Legacy }
+    }
+
+    /// 将数据包编码为写入字节流的字节，数据包超过 `MAX_FRAME_BYTES` 时返回错误
+    pub fn encode(&self, payload: &str) -> Result<Vec<u8>, IOError> {
+        if payload.len() > MAX_FRAME_BYTES {
+            return Err(IOError::new(ErrorKind::InvalidInput, format!("数据包长度 {} 字节超过上限 {} 字节", payload.len(), MAX_FRAME_BYTES)));
+        }
+        Ok(match self {
+            FrameFormat::Legacy => payload.as_bytes().to_vec(),
+            FrameFormat::LengthPrefixed => {
+                let mut frame = Vec::with_capacity(FRAME_LENGTH_PREFIX_BYTES + payload.len());
+                frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+                frame.extend_from_slice(payload.as_bytes());
+                frame
+            },
+        })
+    }
+}
+
+/// 以 `format` 写入一个完整的数据包并刷新；整个数据包通过一次 `write_all` 写入，
+/// 但多个任务共用同一连接时仍需由调用者保证写入的先后（如统一经由主循环写入），否则数据包可能相互穿插
+pub async fn write_frame<W: Write + Unpin>(writer: &mut W, format: FrameFormat, payload: &str) -> Result<(), IOError> {
+    writer.write_all(&format.encode(payload)?).await?;
+    writer.flush().await
+}
+
+/// 从字节流中逐个读取数据包，未读完的数据保留在内部，可跨多次调用读取同一数据包。
+///
+/// 每次读取只在 `read` 返回后才保存数据，因此在超时等原因取消 `read_frame` 后可以继续使用，已接收的数据不会丢失。
+#[derive(Debug, Default)]
+pub struct FrameReader {
+    format: FrameFormat,
+    buffer: Vec<u8>, // 长度前缀格式下尚未组成完整数据包的字节
+    decoder: JsonFrameDecoder,
+    frames: VecDeque<String>, // 已切分但尚未返回的数据包
+}
+
+impl FrameReader {
+    pub fn new(format: FrameFormat) -> FrameReader {
+        FrameReader { format, ..Default::default() }
+    }
+
+    /// 读取时使用的分帧方式，在同一连接上发送请求时也应使用该格式
+    pub fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    /// 丢弃尚未返回的数据，在其他模块独占连接后恢复读取前调用
+    pub fn clear(&mut self) {
+        *self = FrameReader::new(self.format);
+    }
+
+    /// 输入新读取的字节，切分出的完整数据包在之后的 `read_frame` 中依次返回
+    pub fn push(&mut self, bytes: &[u8]) -> Result<(), IOError> {
+        match self.format {
+            FrameFormat::Legacy => self.frames.extend(self.decoder.push(bytes)?),
+            FrameFormat::LengthPrefixed => {
+                self.buffer.extend_from_slice(bytes);
+                loop {
+                    match self.buffer.first() {
+                        None => break,
+                        Some(0) if !self.decoder.is_partial() => (),
+                        Some(byte) if !self.decoder.is_partial() && byte.is_ascii_whitespace() => { // 旧格式数据包之后的换行等
+                            self.buffer.remove(0);
+                            continue;
+                        },
+                        Some(_) => { // 旧格式的数据包，如独占连接前以旧格式发出的请求的回复，长度前缀的第一个字节不可能为非零值
+                            let consumed = match self.buffer.iter().position(|&byte| byte == b'}') {
+                                Some(position) => position + 1,
+                                None => self.buffer.len(),
+                            };
+                            let bytes: Vec<u8> = self.buffer.drain(..consumed).collect();
+                            self.frames.extend(self.decoder.push(&bytes)?);
+                            continue;
+                        },
+                    }
+                    if self.buffer.len() < FRAME_LENGTH_PREFIX_BYTES {
+                        break;
+                    }
+                    let mut prefix = [0u8; FRAME_LENGTH_PREFIX_BYTES];
+                    prefix.copy_from_slice(&self.buffer[..FRAME_LENGTH_PREFIX_BYTES]);
+                    let len = u32::from_be_bytes(prefix) as usize;
+                    if len > MAX_FRAME_BYTES {
+                        self.buffer.clear();
+                        return Err(IOError::new(ErrorKind::InvalidData, format!("下位机数据包声明的长度 {} 字节超过上限 {} 字节，可能是数据异常", len, MAX_FRAME_BYTES)));
+                    }
+                    if self.buffer.len() < FRAME_LENGTH_PREFIX_BYTES + len {
+                        break;
+                    }
+                    let payload: Vec<u8> = self.buffer.drain(..FRAME_LENGTH_PREFIX_BYTES + len).skip(FRAME_LENGTH_PREFIX_BYTES).collect();
+                    match String::from_utf8(payload) {
+                        Ok(frame) => self.frames.push_back(frame),
+                        Err(err) => log::warn!("来自于下位机的数据包不是有效的 UTF-8 文本（{}）", err),
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// 读取下一个完整的数据包，连接关闭时返回 `ErrorKind::ConnectionAborted`
+    pub async fn read_frame<R: Read + Unpin>(&mut self, reader: &mut R) -> Result<String, IOError> {
+        let mut buf = [0u8; 1024];
+        loop {
+            if let Some(frame) = self.frames.pop_front() {
+                return Ok(frame);
+            }
+            let len = reader.read(&mut buf).await?;
+            if len == 0 {
+                return Err(IOError::new(ErrorKind::ConnectionAborted, "下位机主动断开连接（EOF）"));
+            }
+            self.push(&buf[..len])?;
+        }
+    }
+}
+
+/// 从 TCP 字节流中切分出完整的 JSON 对象。
+///
+/// 下位机发送的数据包之间没有分隔符，一次读取可能包含多个数据包，也可能只包含半个，
+/// 因此按照顶层花括号的配对关系（忽略字符串中的括号）确定每个数据包的边界，数据包之外的空白与 `\0` 会被丢弃。
+#[derive(Debug, Default)]
+pub struct JsonFrameDecoder {
+    buffer: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonFrameDecoder {
+    pub fn new() -> JsonFrameDecoder {
+        Default::default()
+    }
+
+    /// 丢弃尚未完整的数据包，在其他模块独占连接后恢复读取前调用
+    pub fn clear(&mut self) {
+        *self = Default::default();
+    }
+
+    /// 是否正处于某个数据包的中间
+    pub fn is_partial(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// 输入新读取的字节，返回其中所有已完整的数据包；未完整的数据包超过 `MAX_FRAME_BYTES` 时返回错误，此后不应继续使用该连接
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<String>, IOError> {
+        let mut frames = Vec::new();
+        for &byte in bytes {
+            if self.depth == 0 {
+                if byte == b'{' {
+                    self.depth = 1;
+                    self.buffer.push(byte);
+                }
+                continue;
+            }
+            self.buffer.push(byte);
+            if self.buffer.len() > MAX_FRAME_BYTES {
+                let len = self.buffer.len();
+                self.clear();
+                return Err(IOError::new(ErrorKind::InvalidData, format!("下位机数据包超过 {} 字节仍未结束（已接收 {} 字节），可能是数据异常", MAX_FRAME_BYTES, len)));
+            }
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => (),
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' => self.depth += 1,
+                b'}' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        let frame = std::mem::take(&mut self.buffer);
+                        match String::from_utf8(frame) {
+                            Ok(frame) => frames.push(frame),
+                            Err(err) => log::warn!("来自于下位机的数据包不是有效的 UTF-8 文本（{}）", err),
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{pin::Pin, task::{Context, Poll}};
+
+    use async_std::{sync::{Arc, Mutex}, task};
+
+    use super::*;
+
+    /// 每次读取至多返回 `chunk` 字节的内存字节流，模拟 TCP 将数据包拆分到多次读取中
+    struct ChunkedReader {
+        data: Vec<u8>,
+        position: usize,
+        chunk: usize,
+    }
+
+    impl ChunkedReader {
+        fn new(data: Vec<u8>, chunk: usize) -> ChunkedReader {
+            ChunkedReader { data, position: 0, chunk }
+        }
+    }
+
+    impl Read for ChunkedReader {
+        fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, IOError>> {
+            let len = self.chunk.min(buf.len()).min(self.data.len() - self.position);
+            buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+            self.position += len;
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    /// 依次读出全部数据包直至字节流结束
+    fn read_all(format: FrameFormat, data: Vec<u8>, chunk: usize) -> Vec<String> {
+        task::block_on(async {
+            let mut reader = ChunkedReader::new(data, chunk);
+            let mut frame_reader = FrameReader::new(format);
+            let mut frames = Vec::new();
+            loop {
+                match frame_reader.read_frame(&mut reader).await {
+                    Ok(frame) => frames.push(frame),
+                    Err(err) => {
+                        assert_eq!(err.kind(), ErrorKind::ConnectionAborted);
+                        return frames;
+                    },
+                }
+            }
+        })
+    }
+
+    fn encode_all(format: FrameFormat, payloads: &[&str]) -> Vec<u8> {
+        payloads.iter().flat_map(|payload| format.encode(payload).unwrap()).collect()
+    }
+
+    const PAYLOADS: [&str; 3] = [r#"{"a":1}"#, r#"{"nested":{"b":[1,2,{"c":"}{"}]}}"#, r#"{"escaped":"\"}"}"#];
+
+    #[test]
+    fn length_prefixed_frames_survive_partial_reads() {
+        let data = encode_all(FrameFormat::LengthPrefixed, &PAYLOADS);
+        for chunk in [1, 2, 3, 5, 1024] {
+            assert_eq!(read_all(FrameFormat::LengthPrefixed, data.clone(), chunk), PAYLOADS, "chunk: {}", chunk);
+        }
+    }
+
+    #[test]
+    fn legacy_frames_survive_partial_reads() {
+        let data = encode_all(FrameFormat::Legacy, &PAYLOADS);
+        for chunk in [1, 2, 3, 5, 1024] {
+            assert_eq!(read_all(FrameFormat::Legacy, data.clone(), chunk), PAYLOADS, "chunk: {}", chunk);
+        }
+    }
+
+    #[test]
+    fn oversized_length_prefix_is_rejected() {
+        task::block_on(async {
+            let mut data = ((MAX_FRAME_BYTES + 1) as u32).to_be_bytes().to_vec();
+            data.extend_from_slice(b"{}");
+            let mut reader = ChunkedReader::new(data, 1);
+            let mut frame_reader = FrameReader::new(FrameFormat::LengthPrefixed);
+            let err = frame_reader.read_frame(&mut reader).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert!(frame_reader.buffer.is_empty());
+            assert!(frame_reader.frames.is_empty());
+        });
+    }
+
+    #[test]
+    fn oversized_payload_is_not_encoded() {
+        let payload = "a".repeat(MAX_FRAME_BYTES + 1);
+        for format in [FrameFormat::Legacy, FrameFormat::LengthPrefixed] {
+            assert_eq!(format.encode(&payload).unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn stray_legacy_frames_interleave_with_prefixed_frames() {
+        // 独占连接前以旧格式发出的请求的回复可能夹在长度前缀的数据包之间，其后可能带有换行
+        let legacy = r#"{"reply":{"inner":{"text":"}}"},"list":[{}]}}"#;
+        let mut data = FrameFormat::LengthPrefixed.encode(PAYLOADS[0]).unwrap();
+        data.extend_from_slice(legacy.as_bytes());
+        data.extend_from_slice(b"\r\n");
+        data.extend(FrameFormat::LengthPrefixed.encode(PAYLOADS[1]).unwrap());
+        data.extend_from_slice(legacy.as_bytes());
+        data.extend(FrameFormat::LengthPrefixed.encode(PAYLOADS[2]).unwrap());
+        for chunk in [1, 2, 3, 7, 1024] {
+            assert_eq!(read_all(FrameFormat::LengthPrefixed, data.clone(), chunk), [PAYLOADS[0], legacy, PAYLOADS[1], legacy, PAYLOADS[2]], "chunk: {}", chunk);
+        }
+    }
+
+    #[test]
+    fn frames_from_two_writers_stay_intact() {
+        const FRAMES_PER_WRITER: usize = 50;
+        let sink = Arc::new(Mutex::new(Vec::new()));
+        task::block_on(async {
+            // 两个任务共用同一连接，每次写入一个完整的数据包，由锁保证数据包之间不会穿插
+            let writers: Vec<_> = [("preview", FrameFormat::LengthPrefixed), ("legacy", FrameFormat::Legacy)].into_iter().map(|(name, format)| {
+                let sink = sink.clone();
+                task::spawn(async move {
+                    for index in 0..FRAMES_PER_WRITER {
+                        let payload = format!(r#"{{"{}":{},"padding":"{}"}}"#, name, index, "}".repeat(index));
+                        write_frame(&mut *sink.lock().await, format, &payload).await.unwrap();
+                        task::yield_now().await;
+                    }
+                })
+            }).collect();
+            for writer in writers {
+                writer.await;
+            }
+        });
+        let data = task::block_on(sink.lock()).clone();
+        let frames = read_all(FrameFormat::LengthPrefixed, data, 3);
+        assert_eq!(frames.len(), FRAMES_PER_WRITER * 2);
+        for name in ["preview", "legacy"] {
+            let indices: Vec<u64> = frames.iter()
+                .map(|frame| serde_json::from_str::<serde_json::Value>(frame).unwrap())
+                .filter_map(|value| value.get(name).and_then(serde_json::Value::as_u64))
+                .collect();
+            assert_eq!(indices, (0..FRAMES_PER_WRITER as u64).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn json_decoder_rejects_oversized_frame() {
+        let mut decoder = JsonFrameDecoder::new();
+        let mut oversized = b"{\"data\":\"".to_vec();
+        oversized.resize(MAX_FRAME_BYTES + 1, b'a');
+        let err = decoder.push(&oversized).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(!decoder.is_partial());
+        // 出错后解码器已重置，之后的数据包可以正常切分
+        assert_eq!(decoder.push(b"{\"a\":1}").unwrap(), vec!["{\"a\":1}".to_string()]);
+    }
+}
@@ -0,0 +1,121 @@
+/* latency.rs
+ *
+ * Copyright 2021-2022 Bohong Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+/// 发送延迟测量数据包的间隔
+pub const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// 超过该时间仍未收到回复的数据包视为丢失，不再参与计算
+pub const PING_TIMEOUT: Duration = Duration::from_secs(5);
+const RTT_SMOOTHING_FACTOR: f64 = 0.125; // 与 TCP 的 SRTT 计算（RFC 6298）相同
+const JITTER_SMOOTHING_FACTOR: f64 = 0.25;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlavePingValuePacket {
+    seq: u32,
+    timestamp: u128, // 发送时的 UNIX 时间戳，单位：毫秒，仅供下位机记录
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlavePingPacket {
+    ping: SlavePingValuePacket,
+}
+
+/// 下位机收到 `ping` 后原样返回其中的内容
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlavePongPacket {
+    pong: SlavePingValuePacket,
+}
+
+impl SlavePongPacket {
+    /// 判断数据包是否为延迟测量的回复，其他模块收到回复时应直接丢弃
+    pub fn parse(json_string: &str) -> Option<SlavePongPacket> {
+        serde_json::from_str(json_string).ok()
+    }
+}
+
+/// 平滑后的往返延迟与抖动，单位：毫秒
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Latency {
+    pub rtt: f64,
+    pub jitter: f64,
+}
+
+impl ToString for Latency {
+    fn to_string(&self) -> String {
+        format!("{:.0} ms ± {:.0}", self.rtt, self.jitter)
+    }
+}
+
+/// 根据 `ping`/`pong` 数据包估计连接的往返延迟。
+///
+/// 同一时间只保留一个未回复的数据包，避免在连接被其他模块独占前留下过多尚未到达的回复。
+#[derive(Debug, Default)]
+pub struct LatencyEstimator {
+    next_seq: u32,
+    pending: Option<(u32, Instant)>,
+    latency: Option<Latency>,
+}
+
+impl LatencyEstimator {
+    pub fn new() -> LatencyEstimator {
+        LatencyEstimator {
+            next_seq: rand::random(), // 各连接的序号互不相同，独占前主连接残留的回复不会被误认
+            ..Default::default()
+        }
+    }
+
+    /// 生成下一个待发送的数据包，上一个数据包尚未回复且未超时时返回 `None`
+    pub fn next_ping(&mut self) -> Option<String> {
+        if self.pending.map_or(false, |(_, instant)| instant.elapsed() < PING_TIMEOUT) {
+            return None;
+        }
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.pending = Some((seq, Instant::now()));
+        let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis();
+        Some(serde_json::to_string(&SlavePingPacket { ping: SlavePingValuePacket { seq, timestamp } }).unwrap())
+    }
+
+    /// 处理收到的回复，返回更新后的延迟；序号不匹配的回复被忽略
+    pub fn receive_pong(&mut self, pong: &SlavePongPacket) -> Option<Latency> {
+        match self.pending {
+            Some((seq, instant)) if seq == pong.pong.seq => {
+                self.pending = None;
+                let sample = instant.elapsed().as_secs_f64() * 1000.0;
+                let latency = match self.latency {
+                    Some(Latency { rtt, jitter }) => Latency {
+                        rtt: rtt + RTT_SMOOTHING_FACTOR * (sample - rtt),
+                        jitter: jitter + JITTER_SMOOTHING_FACTOR * ((sample - rtt).abs() - jitter),
+                    },
+                    None => Latency { rtt: sample, jitter: sample / 2.0 },
+                };
+                self.latency = Some(latency);
+                Some(latency)
+            },
+            _ => None,
+        }
+    }
+
+    /// 放弃尚未回复的数据包，在连接被其他模块独占时调用
+    pub fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+}
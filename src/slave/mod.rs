@@ -21,8 +21,12 @@ pub mod param_tuner;
 pub mod slave_config;
 pub mod slave_video;
 pub mod firmware_update;
+pub mod frame;
+pub mod latency;
+pub mod capabilities;
+pub mod discovery;
 
-use std::{cell::RefCell, collections::{HashMap, VecDeque, HashSet}, rc::Rc, sync::{Arc, Mutex}, fmt::Debug, time::{Duration, SystemTime}, ops::Deref, io::Error as IOError};
+use std::{cell::{RefCell, Cell}, collections::{HashMap, VecDeque, HashSet}, rc::Rc, sync::{Arc, Mutex}, fmt::Debug, time::{Duration, SystemTime}, ops::Deref, io::Error as IOError};
 use async_std::{net::TcpStream, prelude::*, task::{JoinHandle, self}};
 
 use glib::{PRIORITY_DEFAULT, Sender, WeakRef, DateTime, MainContext};
@@ -38,8 +42,12 @@ use derivative::*;
 use crate::{input::{InputSource, InputSourceEvent, InputSystem, Button, Axis}, slave::param_tuner::SlaveParameterTunerMsg};
 use crate::preferences::PreferencesModel;
 use crate::ui::generic::error_message;
+
+use self::frame::JsonFrameDecoder;
+use self::latency::{Latency, LatencyEstimator, SlavePongPacket, PING_INTERVAL};
+use self::capabilities::{Capabilities, SlaveCapabilitiesPacket, SlaveHelloPacket, HOST_PROTOCOL_VERSION};
 use crate::AppMsg;
-use self::{param_tuner::SlaveParameterTunerModel, slave_config::{SlaveConfigModel, SlaveConfigMsg}, slave_video::{SlaveVideoModel, SlaveVideoMsg}, firmware_update::SlaveFirmwareUpdaterModel};
+use self::{param_tuner::{SlaveParameterTunerModel, ControlLoopModel}, slave_config::{SlaveConfigModel, SlaveConfigMsg}, slave_video::{SlaveVideoModel, SlaveVideoMsg}, firmware_update::{SlaveFirmwareUpdaterModel, SlaveFirmwareUpdaterMsg}};
 
 #[tracker::track(pub)]
 #[derive(Debug, Derivative)]
@@ -80,6 +88,33 @@ pub struct SlaveModel {
     #[derivative(Default(value="FactoryVec::new()"))]
     pub infos: FactoryVec<SlaveInfoModel>,
     pub config_presented: bool,
+    pub tuner_feedbacks: Option<HashMap<String, f32>>, // 参数调校窗口运行时各控制环最新的反馈值
+    pub latency: Option<Latency>, // 连接的往返延迟，尚未测得或未连接时为 `None`
+    pub reconnect_attempt: Option<u32>, // 连接意外断开后正在进行的自动重连次数（从 1 开始）
+    #[no_eq]
+    pub firmware_updater_sender: Option<Sender<SlaveFirmwareUpdaterMsg>>, // 最近打开的固件更新窗口，连接变化时通知其更新连接
+    #[no_eq]
+    pub parameter_tuner_sender: Option<Sender<SlaveParameterTunerMsg>>, // 最近打开的参数调校窗口，重连后由其恢复调试
+    pub capabilities: Capabilities, // 下位机连接时声明的功能，未声明时为最小功能集
+    pub exclusive_tcp_activity: Option<ExclusiveTcpActivity>,
+    #[no_eq]
+    pub shared_exclusive_tcp_activity: Rc<Cell<Option<ExclusiveTcpActivity>>>, // 与固件更新、参数调校窗口共享，供其在占用连接前检查
+}
+
+/// 需要独占下位机连接的操作，同一时间只能进行其中一项，否则发送的数据会相互穿插
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExclusiveTcpActivity {
+    FirmwareUpdate,
+    ParameterTuning,
+}
+
+impl ToString for ExclusiveTcpActivity {
+    fn to_string(&self) -> String {
+        match self {
+            ExclusiveTcpActivity::FirmwareUpdate => "固件更新",
+            ExclusiveTcpActivity::ParameterTuning => "参数调校",
+        }.to_string()
+    }
 }
 
 #[tracker::track(pub)]
@@ -146,6 +181,8 @@ impl SlaveStatusClass {
 }
 
 const JOYSTICK_DISPLAY_THRESHOLD: i16 = 500;
+const RECONNECT_BASE_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_INTERVAL: Duration = Duration::from_secs(30);
 
 impl SlaveModel {
     pub fn new(config: SlaveConfigModel, preferences: Rc<RefCell<PreferencesModel>>, component_sender: &Sender<SlaveMsg>, input_event_sender: Sender<InputSourceEvent>) -> Self {
@@ -172,6 +209,76 @@ impl SlaveModel {
         let mut status = self.get_mut_status().lock().unwrap();
         *status.entry(status_class.clone()).or_insert(0) = new_status;
     }
+
+    /// 同时更新界面使用的状态与子窗口共享的状态
+    fn update_exclusive_tcp_activity(&mut self, activity: Option<ExclusiveTcpActivity>) {
+        self.shared_exclusive_tcp_activity.set(activity);
+        self.set_exclusive_tcp_activity(activity);
+    }
+
+    /// 按照连接设置中的 URL 建立连接，URL 有误时返回 `false`
+    fn connect(&mut self, sender: &Sender<SlaveMsg>) -> bool {
+        let url = self.config.model().get_slave_url().clone();
+        if let ("tcp", Some(host), Some(port)) = (url.scheme(), url.host_str().map(ToString::to_string), url.port()) {
+            let (tcp_sender, tcp_receiver) = async_std::channel::bounded::<SlaveTcpMsg>(128);
+            self.set_tcp_msg_sender(Some(tcp_sender.clone()));
+            let sender = sender.clone();
+            let control_sending_rate = *self.preferences.borrow().get_default_input_sending_rate();
+            self.set_connected(None);
+            self.config.send(SlaveConfigMsg::SetConnected(None)).unwrap();
+            async_std::task::spawn(async move {
+                match TcpStream::connect(format!("{}:{}", host, port)).await.map(|x| async_std::sync::Arc::new(x)) {
+                    Ok(tcp_stream) => {
+                        tcp_main_handler(control_sending_rate, tcp_stream.clone(), tcp_sender, tcp_receiver, sender.clone()).await.unwrap_or_default();
+                    },
+                    Err(err) => send!(sender, SlaveMsg::TcpError(err.to_string())),
+                }
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 安排下一次自动重连，间隔按指数增长；超过设置的次数后放弃
+    fn schedule_reconnect(&mut self, sender: &Sender<SlaveMsg>) {
+        let limit = *self.preferences.borrow().get_slave_reconnect_attempts();
+        let attempt = self.reconnect_attempt.map_or(1, |attempt| attempt + 1);
+        if attempt > limit {
+            if limit > 0 {
+                self.get_mut_toast_messages().borrow_mut().push_back(format!("已尝试重新连接 {} 次，停止自动重连", limit));
+            }
+            self.stop_reconnecting();
+            return;
+        }
+        let delay = RECONNECT_BASE_INTERVAL.saturating_mul(1 << (attempt - 1).min(16)).min(RECONNECT_MAX_INTERVAL);
+        self.set_reconnect_attempt(Some(attempt));
+        task::spawn(clone!(@strong sender => async move {
+            task::sleep(delay).await;
+            send!(sender, SlaveMsg::Reconnect(attempt));
+        }));
+    }
+
+    /// 放弃自动重连，并通知等待重连的参数调校窗口
+    fn stop_reconnecting(&mut self) {
+        self.set_reconnect_attempt(None);
+        if let Some(tuner_sender) = self.get_parameter_tuner_sender() {
+            send!(tuner_sender, SlaveParameterTunerMsg::SlaveConnectionChanged(None));
+        }
+    }
+
+    pub fn reconnect_description(&self) -> String {
+        match self.reconnect_attempt {
+            Some(attempt) => format!("正在重新连接（{}/{}）", attempt, self.preferences.borrow().get_slave_reconnect_attempts()),
+            None => String::new(),
+        }
+    }
+
+    pub fn tuner_feedbacks_summary(&self) -> String {
+        let mut feedbacks = self.tuner_feedbacks.iter().flatten().collect::<Vec<_>>();
+        feedbacks.sort_by(|(a, _), (b, _)| a.cmp(b));
+        feedbacks.into_iter().map(|(key, value)| format!("{} {:.2}", ControlLoopModel::key_to_string(key), value)).collect::<Vec<_>>().join("\n")
+    }
 }
 
 pub fn input_sources_list_box(input_sources: &HashSet<InputSource>, input_system: &InputSystem, sender: &Sender<SlaveMsg>) -> Widget {
@@ -269,6 +376,25 @@ impl MicroWidgets<SlaveModel> for SlaveWidgets {
                         append = &Label {
                             set_text: track!(model.changed(SlaveModel::config()), model.config.model().get_slave_url().to_string().as_str()),
                         },
+                        append = &Label {
+                            set_css_classes: &["dim-label"],
+                            set_tooltip_text: Some("与下位机之间的往返延迟（平滑值 ± 抖动）"),
+                            set_visible: track!(model.changed(SlaveModel::latency()), model.get_latency().is_some()),
+                            set_label: track!(model.changed(SlaveModel::latency()), &model.get_latency().map(|latency| latency.to_string()).unwrap_or_default()),
+                        },
+                        append = &Label {
+                            set_visible: track!(model.changed(SlaveModel::reconnect_attempt()), model.get_reconnect_attempt().is_some()),
+                            set_label: track!(model.changed(SlaveModel::reconnect_attempt()), &model.reconnect_description()),
+                        },
+                        append = &GtkButton {
+                            set_icon_name: "process-stop-symbolic",
+                            set_css_classes: &["circular"],
+                            set_tooltip_text: Some("停止自动重连"),
+                            set_visible: track!(model.changed(SlaveModel::reconnect_attempt()), model.get_reconnect_attempt().is_some()),
+                            connect_clicked(sender) => move |_button| {
+                                send!(sender, SlaveMsg::StopReconnecting);
+                            },
+                        },
                         append = &MenuButton {
                             set_icon_name: "input-gaming-symbolic",
                             set_css_classes: &["circular"],
@@ -308,7 +434,8 @@ impl MicroWidgets<SlaveModel> for SlaveWidgets {
                         append = &GtkButton {
                             set_icon_name: "software-update-available-symbolic",
                             set_css_classes: &["circular"],
-                            set_tooltip_text: Some("固件更新"),
+                            set_sensitive: track!(model.changed(SlaveModel::exclusive_tcp_activity()), model.exclusive_tcp_activity != Some(ExclusiveTcpActivity::ParameterTuning)),
+                            set_tooltip_text: track!(model.changed(SlaveModel::exclusive_tcp_activity()), Some(if model.exclusive_tcp_activity == Some(ExclusiveTcpActivity::ParameterTuning) { "参数调校进行中，请先关闭参数调校窗口" } else { "固件更新" })),
                             connect_clicked(sender) => move |_button| {
                                 send!(sender, SlaveMsg::OpenFirmwareUpater);
                             },
@@ -316,7 +443,8 @@ impl MicroWidgets<SlaveModel> for SlaveWidgets {
                         append = &GtkButton {
                             set_icon_name: "preferences-other-symbolic",
                             set_css_classes: &["circular"],
-                            set_tooltip_text: Some("参数调校"),
+                            set_sensitive: track!(model.changed(SlaveModel::exclusive_tcp_activity()), model.exclusive_tcp_activity != Some(ExclusiveTcpActivity::FirmwareUpdate)),
+                            set_tooltip_text: track!(model.changed(SlaveModel::exclusive_tcp_activity()), Some(if model.exclusive_tcp_activity == Some(ExclusiveTcpActivity::FirmwareUpdate) { "固件更新进行中，请等待更新完成" } else { "参数调校" })),
                             connect_clicked(sender) => move |_button| {
                                 send!(sender, SlaveMsg::OpenParameterTuner);
                             },
@@ -467,6 +595,20 @@ impl MicroWidgets<SlaveModel> for SlaveWidgets {
                                                 set_hexpand: true,
                                                 factory!(model.infos),
                                             },
+                                            append = &CenterBox {
+                                                set_hexpand: true,
+                                                set_visible: track!(model.changed(SlaveModel::tuner_feedbacks()), model.get_tuner_feedbacks().is_some()),
+                                                set_tooltip_text: Some("参数调校窗口正在运行，显示各控制环最新的反馈值"),
+                                                set_start_widget = Some(&Label) {
+                                                    set_valign: Align::Start,
+                                                    set_markup: "<b>参数调校</b>",
+                                                },
+                                                set_end_widget = Some(&Label) {
+                                                    set_valign: Align::Start,
+                                                    set_justify: gtk::Justification::Right,
+                                                    set_label: track!(model.changed(SlaveModel::tuner_feedbacks()), &model.tuner_feedbacks_summary()),
+                                                },
+                                            },
                                             append = &CenterBox {
                                                 set_hexpand: true,
                                                 set_start_widget = Some(&Label) {
@@ -530,6 +672,8 @@ pub enum SlaveMsg {
     InputReceived(InputSourceEvent),
     OpenFirmwareUpater,
     OpenParameterTuner,
+    FirmwareUpdaterClosed,
+    ParameterTunerClosed,
     DestroySlave,
     ErrorMessage(String),
     TcpError(String),
@@ -538,6 +682,12 @@ pub enum SlaveMsg {
     TcpMessage(SlaveTcpMsg),
     InformationsReceived(HashMap<String, String>),
     SetConfigPresented(bool),
+    ParameterTunerFeedbacksUpdated(Option<HashMap<String, f32>>),
+    ExclusiveTcpActivityFinished,
+    LatencyUpdated(Option<Latency>),
+    CapabilitiesReceived(Capabilities),
+    Reconnect(u32),
+    StopReconnecting,
 }
 
 pub enum SlaveTcpMsg {
@@ -545,7 +695,7 @@ pub enum SlaveTcpMsg {
     Disconnect,
     SendString(String),
     ControlUpdated(ControlPacket),
-    Block(JoinHandle<Result<(), IOError>>),
+    Block(ExclusiveTcpActivity, JoinHandle<Result<(), IOError>>),
 }
 
 async fn tcp_main_handler(input_rate: u16,
@@ -562,6 +712,8 @@ async fn tcp_main_handler(input_rate: u16,
     let idle = async_std::sync::Arc::new(async_std::sync::Mutex::new(true));
     let last_action_timestamp = async_std::sync::Arc::new(async_std::sync::Mutex::new(current_millis()));
     let control_packet = async_std::sync::Arc::new(async_std::sync::Mutex::new(None as Option<ControlPacket>));
+    let latency_estimator = async_std::sync::Arc::new(async_std::sync::Mutex::new(LatencyEstimator::new()));
+    let ping_supported = async_std::sync::Arc::new(async_std::sync::Mutex::new(false)); // 下位机声明支持前不发送延迟测量数据包
     
     const IDLE_TIME_MILLIS: u128 = 5000;
 
@@ -584,26 +736,70 @@ async fn tcp_main_handler(input_rate: u16,
         }
     }));
 
-    let receive_task = task::spawn(clone!(@strong tcp_sender, @strong idle, @strong slave_sender, @strong tcp_stream => async move {
+    // 仅在连接未被独占时发送，独占期间由参数调校等模块自行测量
+    let ping_task = task::spawn(clone!(@strong idle, @strong tcp_sender, @strong tcp_stream, @strong latency_estimator, @strong ping_supported => async move {
+        let mut tcp_stream = &tcp_stream;
+        loop {
+            if tcp_sender.is_closed() {
+                return;
+            }
+            let idle = idle.lock().await; // 写入完成前保持锁定，防止写入期间连接被其他模块独占
+            if *idle && *ping_supported.lock().await {
+                if let Some(ping) = latency_estimator.lock().await.next_ping() {
+                    if let Err(err) = tcp_stream.write_all(ping.as_bytes()).await {
+                        tcp_sender.send(SlaveTcpMsg::ConnectionLost(err)).await.unwrap_or_default();
+                        break;
+                    }
+                }
+            }
+            drop(idle);
+            task::sleep(PING_INTERVAL).await;
+        }
+    }));
+
+    let receive_task = task::spawn(clone!(@strong tcp_sender, @strong idle, @strong slave_sender, @strong tcp_stream, @strong latency_estimator, @strong ping_supported => async move {
         let mut tcp_stream = tcp_stream.clone();
         let mut buf = [0u8; 1024];
+        let mut decoder = JsonFrameDecoder::new();
+        let mut hello_sent = false;
         loop {
             if *idle.lock().await {
-                buf.fill(0);
-                if let Err(err) = tcp_stream.read(&mut buf).await {
-                    tcp_sender.send(SlaveTcpMsg::ConnectionLost(err)).await.unwrap_or_default();
+                let len = match tcp_stream.read(&mut buf).await {
+                    Ok(len) => len,
+                    Err(err) => {
+                        tcp_sender.send(SlaveTcpMsg::ConnectionLost(err)).await.unwrap_or_default();
+                        break;
+                    },
+                };
+                if len == 0 {
+                    tcp_sender.send(SlaveTcpMsg::ConnectionLost(IOError::new(std::io::ErrorKind::ConnectionAborted, "下位机主动断开连接（EOF）"))).await.unwrap_or_default();
                     break;
-                } else {
-                    let json_string = match std::str::from_utf8(buf.split(|x| x.eq(&0)).next().unwrap()) {
-                        Ok(string) => string,
-                        Err(_) => continue,
-                    };
-                    if json_string.is_empty() {
-                        tcp_sender.send(SlaveTcpMsg::ConnectionLost(IOError::new(std::io::ErrorKind::ConnectionAborted, "下位机主动断开连接（EOF）"))).await.unwrap_or_default();
+                }
+                let json_strings = match decoder.push(&buf[..len]) {
+                    Ok(json_strings) => json_strings,
+                    Err(err) => {
+                        tcp_sender.send(SlaveTcpMsg::ConnectionLost(err)).await.unwrap_or_default();
                         break;
+                    },
+                };
+                for json_string in json_strings {
+                    if let Some(pong) = SlavePongPacket::parse(&json_string) {
+                        if let Some(latency) = latency_estimator.lock().await.receive_pong(&pong) {
+                            send!(slave_sender, SlaveMsg::LatencyUpdated(Some(latency)));
+                        }
+                        continue;
                     }
-                    let msg = serde_json::from_str::<SlaveInfoPacket>(&json_string);
-                    match msg {
+                    if let Ok(SlaveCapabilitiesPacket { capabilities }) = serde_json::from_str::<SlaveCapabilitiesPacket>(&json_string) {
+                        *ping_supported.lock().await = capabilities.ping;
+                        // 旧版下位机不一定能忽略未知的数据包，只向声明了协议版本的下位机发送握手数据包，每个连接只发送一次
+                        if capabilities.protocol_version.is_some() && !hello_sent {
+                            hello_sent = true;
+                            tcp_sender.send(SlaveTcpMsg::SendString(serde_json::to_string(&SlaveHelloPacket::default()).unwrap())).await.unwrap_or_default();
+                        }
+                        send!(slave_sender, SlaveMsg::CapabilitiesReceived(capabilities));
+                        continue;
+                    }
+                    match serde_json::from_str::<SlaveInfoPacket>(&json_string) {
                         Ok(packet) => {
                             send!(slave_sender, SlaveMsg::InformationsReceived(packet.info));
                         },
@@ -611,6 +807,7 @@ async fn tcp_main_handler(input_rate: u16,
                     }
                 }
             } else {
+                decoder.clear(); // 连接被其他模块独占期间的数据不属于本模块
                 task::sleep(Duration::from_millis(100)).await;
             }
         }
@@ -646,6 +843,7 @@ async fn tcp_main_handler(input_rate: u16,
                         if tcp_stream.shutdown(std::net::Shutdown::Both).is_ok() {
                             connection_test_task.cancel().await;
                             control_send_task.cancel().await;
+                            ping_task.cancel().await;
                             receive_task.cancel().await;
                             send!(slave_sender, SlaveMsg::TcpConnectionChanged(None));
                         }
@@ -656,6 +854,7 @@ async fn tcp_main_handler(input_rate: u16,
                         tcp_stream.shutdown(std::net::Shutdown::Both).unwrap_or_default();
                         connection_test_task.cancel().await;
                         control_send_task.cancel().await;
+                        ping_task.cancel().await;
                         receive_task.cancel().await;
                         send!(slave_sender, SlaveMsg::TcpError(err.to_string()));
                         tcp_receiver.close();
@@ -663,19 +862,22 @@ async fn tcp_main_handler(input_rate: u16,
                     }
                     SlaveTcpMsg::SendString(string) => {
                         tcp_stream.write_all(string.as_bytes()).await?;
+                        tcp_stream.flush().await?;
                         *last_action_timestamp.lock().await = current_millis();
                     },
                     SlaveTcpMsg::ControlUpdated(control) => {
                         *control_packet.lock().await = Some(control);
                         *last_action_timestamp.lock().await = current_millis();
                     },
-                    SlaveTcpMsg::Block(blocker) => {
+                    SlaveTcpMsg::Block(_, blocker) => {
                         *idle.lock().await = false;
-                        task::spawn(clone!(@strong idle => async move {
+                        latency_estimator.lock().await.cancel_pending(); // 独占期间收到的回复由独占的模块丢弃
+                        task::spawn(clone!(@strong idle, @strong slave_sender => async move {
                             if let Err(err) = blocker.await {
                                 eprintln!("模块异常退出：{}", err);
                             }
                             *idle.lock().await = true;
+                            send!(slave_sender, SlaveMsg::ExclusiveTcpActivityFinished);
                         }));
                     },
                 }
@@ -708,23 +910,8 @@ impl MicroModel for SlaveModel {
                         });
                     },
                     Some(false) => { // 连接
-                        let url = self.config.model().get_slave_url().clone();
-                        if let ("tcp", Some(host), Some(port)) = (url.scheme(), url.host_str().map(ToString::to_string), url.port()) {
-                            let (tcp_sender, tcp_receiver) = async_std::channel::bounded::<SlaveTcpMsg>(128);
-                            self.set_tcp_msg_sender(Some(tcp_sender.clone()));
-                            let sender = sender.clone();
-                            let control_sending_rate = *self.preferences.borrow().get_default_input_sending_rate();
-                            self.set_connected(None);
-                            self.config.send(SlaveConfigMsg::SetConnected(None)).unwrap();
-                            async_std::task::spawn(async move {
-                                match TcpStream::connect(format!("{}:{}", host, port)).await.map(|x| async_std::sync::Arc::new(x)) {
-                                    Ok(tcp_stream) => {
-                                        tcp_main_handler(control_sending_rate, tcp_stream.clone(), tcp_sender, tcp_receiver, sender.clone()).await.unwrap_or_default();
-                                    },
-                                    Err(err) => send!(sender, SlaveMsg::TcpError(err.to_string())),
-                                }
-                            });
-                        } else {
+                        self.set_reconnect_attempt(None); // 手动连接失败时不再自动重连
+                        if !self.connect(&sender) {
                             error_message("错误", "连接 URL 有误，请检查并修改后重试 。", app_window.upgrade().as_ref());
                         }
                     },
@@ -800,33 +987,52 @@ impl MicroModel for SlaveModel {
                 }
             },
             SlaveMsg::OpenFirmwareUpater => {
+                let dry_run = *self.preferences.borrow().get_firmware_update_dry_run();
                 match self.get_tcp_stream() {
-                    Some(tcp_stream) => {
-                        let component = MicroComponent::new(SlaveFirmwareUpdaterModel::new(Deref::deref(tcp_stream).clone()), sender.clone());
+                    None if !dry_run => {
+                        error_message("错误", "请确保下位机处于连接状态。", app_window.upgrade().as_ref());
+                    },
+                    _ if !dry_run && self.exclusive_tcp_activity == Some(ExclusiveTcpActivity::ParameterTuning) => {
+                        error_message("错误", "参数调校正在使用下位机连接，请先关闭参数调校窗口。", app_window.upgrade().as_ref());
+                    },
+                    tcp_stream => {
+                        let tcp_stream = tcp_stream.as_ref().filter(|_| !dry_run).map(|tcp_stream| Deref::deref(tcp_stream).clone()); // 模拟更新时不使用连接
+                        let component = MicroComponent::new(SlaveFirmwareUpdaterModel::new(tcp_stream, dry_run, self.shared_exclusive_tcp_activity.clone()), sender.clone());
                         let window = component.root_widget();
                         window.set_transient_for(app_window.upgrade().as_ref());
+                        window.connect_destroy(clone!(@strong sender => move |_window| send!(sender, SlaveMsg::FirmwareUpdaterClosed)));
                         window.set_visible(true);
-                    },
-                    None => {
-                        error_message("错误", "请确保下位机处于连接状态。", app_window.upgrade().as_ref());
+                        send!(component.sender(), SlaveFirmwareUpdaterMsg::CapabilitiesReceived(self.capabilities.clone()));
+                        send!(component.sender(), SlaveFirmwareUpdaterMsg::QueryFirmwareInfo);
+                        self.set_firmware_updater_sender(Some(component.sender()));
                     },
                 }
             },
             SlaveMsg::OpenParameterTuner => {
                 match self.get_tcp_stream() {
+                    Some(_) if self.exclusive_tcp_activity == Some(ExclusiveTcpActivity::FirmwareUpdate) => {
+                        error_message("错误", "固件更新正在使用下位机连接，请等待更新完成后再进行参数调校。", app_window.upgrade().as_ref());
+                    },
                     Some(tcp_stream) => {
-                        let component = MicroComponent::new(SlaveParameterTunerModel::new(*self.preferences.borrow().get_default_param_tuner_graph_view_point_num_limit()), sender.clone());
+                        let component = MicroComponent::new(SlaveParameterTunerModel::new(&self.preferences.borrow(), self.shared_exclusive_tcp_activity.clone()), sender.clone());
                         let window = component.root_widget();
                         window.set_transient_for(app_window.upgrade().as_ref());
+                        window.connect_destroy(clone!(@strong sender => move |_window| send!(sender, SlaveMsg::ParameterTunerClosed)));
                         window.set_visible(true);
+                        send!(component.sender(), SlaveParameterTunerMsg::CapabilitiesReceived(self.capabilities.clone()));
                         send!(component.sender(), SlaveParameterTunerMsg::StartDebug(Deref::deref(tcp_stream).clone()));
+                        self.set_parameter_tuner_sender(Some(component.sender()));
                     },
                     None => {
                         error_message("错误", "请确保下位机处于连接状态。", app_window.upgrade().as_ref());
                     },
                 }
             },
+            // 窗口均为模态窗口，同一时间至多打开一个，关闭后不再向其转发连接变化，避免已关闭的窗口再次独占连接
+            SlaveMsg::FirmwareUpdaterClosed => self.set_firmware_updater_sender(None),
+            SlaveMsg::ParameterTunerClosed => self.set_parameter_tuner_sender(None),
             SlaveMsg::DestroySlave => {
+                self.set_reconnect_attempt(None);
                 if let Some(polling) = self.get_polling() {
                     if *polling {
                         send!(self.video.sender(), SlaveVideoMsg::StopPipeline);
@@ -843,14 +1049,43 @@ impl MicroModel for SlaveModel {
                 error_message("错误", &msg, app_window.upgrade().as_ref());
             },
             SlaveMsg::TcpError(msg) => {
+                let connection_lost = self.get_tcp_stream().is_some(); // 已建立的连接意外断开，而不是连接失败
                 send!(sender, SlaveMsg::ShowToastMessage(format!("下位机通讯错误：{}", msg)));
                 send!(sender, SlaveMsg::TcpConnectionChanged(None));
+                if connection_lost || self.reconnect_attempt.is_some() {
+                    self.schedule_reconnect(&sender);
+                } else {
+                    self.stop_reconnecting(); // 手动连接失败
+                }
+            },
+            SlaveMsg::Reconnect(attempt) => {
+                if self.reconnect_attempt == Some(attempt) && *self.get_connected() == Some(false) {
+                    self.connect(&sender);
+                }
+            },
+            SlaveMsg::StopReconnecting => {
+                if self.reconnect_attempt.is_some() {
+                    self.stop_reconnecting();
+                    self.get_mut_toast_messages().borrow_mut().push_back("已停止自动重连".to_string());
+                }
             },
             SlaveMsg::TcpConnectionChanged(tcp_stream) => {
                 self.set_connected(Some(tcp_stream.is_some()));
                 self.config.send(SlaveConfigMsg::SetConnected(Some(tcp_stream.is_some()))).unwrap();
                 if tcp_stream.is_none() {
                     self.set_tcp_msg_sender(None);
+                    self.update_exclusive_tcp_activity(None); // 连接断开后占用连接的操作均已结束
+                    self.set_latency(None);
+                    self.set_capabilities(Capabilities::default()); // 重新连接后由下位机重新声明
+                } else if self.reconnect_attempt.is_some() {
+                    self.set_reconnect_attempt(None);
+                    self.get_mut_toast_messages().borrow_mut().push_back("已重新连接下位机".to_string());
+                }
+                if let Some(updater_sender) = self.get_firmware_updater_sender() {
+                    send!(updater_sender, SlaveFirmwareUpdaterMsg::SlaveConnectionChanged(tcp_stream.as_ref().map(|tcp_stream| Deref::deref(tcp_stream).clone())));
+                }
+                if let (Some(tuner_sender), Some(tcp_stream)) = (self.get_parameter_tuner_sender(), tcp_stream.as_ref()) {
+                    send!(tuner_sender, SlaveParameterTunerMsg::SlaveConnectionChanged(Some(Deref::deref(tcp_stream).clone())));
                 }
                 self.set_tcp_stream(tcp_stream);
             },
@@ -890,10 +1125,14 @@ impl MicroModel for SlaveModel {
                 send!(self.video.sender(), SlaveVideoMsg::SaveScreenshot(pathbuf));
             },
             SlaveMsg::TcpMessage(msg) => {
+                if let SlaveTcpMsg::Block(activity, _) = &msg {
+                    self.update_exclusive_tcp_activity(Some(*activity));
+                }
                 if let Some(sender) = self.get_tcp_msg_sender().as_ref() {
                     sender.try_send(msg).unwrap_or_default();
                 }
             },
+            SlaveMsg::ExclusiveTcpActivityFinished => self.update_exclusive_tcp_activity(None),
             SlaveMsg::InformationsReceived(info_map) => {
                 let infos = self.get_mut_infos();
                 let mut sorted_infos = info_map.into_iter().collect::<Vec<_>>();
@@ -904,6 +1143,30 @@ impl MicroModel for SlaveModel {
                 }
             },
             SlaveMsg::SetConfigPresented(presented) => self.set_config_presented(presented),
+            SlaveMsg::ParameterTunerFeedbacksUpdated(feedbacks) => self.set_tuner_feedbacks(feedbacks),
+            SlaveMsg::CapabilitiesReceived(capabilities) => {
+                if let Some(version) = capabilities.incompatible_protocol_version() {
+                    log::error!("下位机的通讯协议版本 {} 与上位机的 {} 不兼容", version.to_string(), HOST_PROTOCOL_VERSION.to_string());
+                    self.stop_reconnecting();
+                    if *self.get_connected() == Some(true) {
+                        send!(sender, SlaveMsg::ToggleConnect);
+                    }
+                    error_message("协议版本不兼容",
+                                  &format!("下位机使用的通讯协议版本为 {}，而上位机的协议版本为 {}，主版本号不同的两端无法正常通讯，已断开连接。请更新上位机或下位机固件后重试。", version.to_string(), HOST_PROTOCOL_VERSION.to_string()),
+                                  app_window.upgrade().as_ref());
+                    return;
+                }
+                if let Some(updater_sender) = self.get_firmware_updater_sender() {
+                    send!(updater_sender, SlaveFirmwareUpdaterMsg::CapabilitiesReceived(capabilities.clone()));
+                }
+                if let Some(tuner_sender) = self.get_parameter_tuner_sender() {
+                    send!(tuner_sender, SlaveParameterTunerMsg::CapabilitiesReceived(capabilities.clone()));
+                }
+                self.set_capabilities(capabilities);
+            },
+            SlaveMsg::LatencyUpdated(latency) => if *self.get_connected() == Some(true) { // 忽略断开连接后才到达的结果
+                self.set_latency(latency);
+            },
             SlaveMsg::SetSlaveStatus(which, value) => {
                 self.set_target_status(&which, value);
                 if let Some(sender) = self.get_tcp_msg_sender() {
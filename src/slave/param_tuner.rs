@@ -16,41 +16,159 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::{fmt::Debug, cmp::{max, min}, collections::{HashMap, VecDeque}, ops::Deref, time::{SystemTime, Duration}, io::Error as IOError};
-use async_std::{net::TcpStream, task, prelude::*};
+use std::{fmt::Debug, cmp::{max, min}, collections::{HashMap, VecDeque}, ops::Deref, time::{SystemTime, Duration, Instant}, io::Error as IOError, path::{Path, PathBuf}, cell::{RefCell, Cell}, rc::Rc, fs};
+use async_std::{net::TcpStream, task};
 
-use glib::{Sender, clone};
-use gtk::{Align, Box as GtkBox, Button, Image, Inhibit, Label, Orientation, SpinButton, Switch, prelude::*, FlowBox, Scale, SelectionMode};
-use adw::{HeaderBar, PreferencesGroup, PreferencesPage, PreferencesWindow, prelude::*, Clamp, Leaflet, ToastOverlay, ExpanderRow, ActionRow};
-use relm4::{factory::{FactoryPrototype, FactoryVec}, send, MicroWidgets, MicroModel};
+use glib::{Sender, clone, DateTime};
+use gtk::{Align, Box as GtkBox, Button, Image, Inhibit, Label, LevelBar, Orientation, SpinButton, Spinner, Switch, prelude::*, FlowBox, Scale, SelectionMode, MenuButton, FileFilter, FileChooserAction, TextBuffer, TextView, ScrolledWindow, Dialog, ResponseType, DropDown, StringList, WrapMode, ShortcutController, Shortcut, ShortcutTrigger, CallbackAction, PropagationPhase, ToggleButton};
+use adw::{HeaderBar, PreferencesGroup, PreferencesPage, PreferencesWindow, prelude::*, Clamp, Leaflet, ToastOverlay, ExpanderRow, ActionRow, Toast};
+use relm4::{factory::{FactoryPrototype, FactoryVec}, send, MicroWidgets, MicroModel, actions::{RelmAction, RelmActionGroup}, new_action_group, new_stateless_action};
 use relm4_macros::micro_widget;
 
 use serde::{Serialize, Deserialize};
+use serde_json::Value as JsonValue;
 use derivative::*;
 
-use crate::ui::graph_view::{GraphView, Point as GraphPoint};
+use crate::ui::graph_view::{GraphView, Point as GraphPoint, decimate_indices};
+use crate::ui::generic::{select_path, info_message, validate_spin_button_input, ScaleDebounceExt};
 use crate::slave::SlaveTcpMsg;
 use crate::function::*;
+use crate::preferences::{PreferencesModel, get_data_path};
 
-use super::SlaveMsg;
+use super::{SlaveMsg, ExclusiveTcpActivity, frame::{FrameFormat, FrameReader, write_frame}, latency::{Latency, LatencyEstimator, SlavePongPacket, PING_INTERVAL}, capabilities::Capabilities};
 
 pub enum SlaveParameterTunerMsg {
-    SetPropellerLowerDeadzone(usize, i8),
-    SetPropellerUpperDeadzone(usize, i8),
+    SetPropellerLowerDeadzone(usize, f64),
+    SetPropellerUpperDeadzone(usize, f64),
     SetPropellerPowerPositive(usize, f64),
     SetPropellerPowerNegative(usize, f64),
+    SetPropellerPowerLinked(usize, bool),
     SetPropellerReversed(usize, bool),
     SetPropellerEnabled(usize, bool),
+    SetPropellerPreviewHistoryDisplayed(usize, bool),
     SetP(usize, f64),
     SetI(usize, f64),
     SetD(usize, f64),
+    SetControlLoopEnabled(usize, bool),
+    SetControlLoopBias(usize, f64),
+    SetControlLoopBiasOnDevice(usize, bool),
+    ZeroControlLoopFeedback(usize),
+    CaptureControlLoopSnapshot(usize),
+    RecallControlLoopSnapshot(usize, Option<usize>),
+    RemoveControlLoopSnapshot(usize),
+    MoveControlLoopBackward(usize),
+    MoveControlLoopForward(usize),
     SetPropellerPwmFreqCalibration(f64),
     ResetParameters,
     ApplyParameters,
     StartDebug(TcpStream),
     StopDebug,
     FeedbacksReceived(SlaveParameterTunerFeedbackPacket),
+    TelemetryReceived(SlaveParameterTunerTelemetryPacket),
     ParametersReceived(SlaveParameterTunerPacket),
+    PreviewSent(u128, HashMap<String, i8>),
+    ExportParameters(PathBuf),
+    GenerateReport(PathBuf),
+    ImportParameters(PathBuf),
+    ImportPropellerLayout(PathBuf),
+    LoadPreset(usize),
+    ConnectionStateChanged(ConnectionState),
+    ResolveParameterConflict(Vec<String>),
+    StopAllPropellers,
+    RefreshFeedbackRate,
+    LinkCongestionChanged(bool),
+    ApplyResult(Result<(), String>),
+    SetReadOnly(bool),
+    SetPreviewLatched(bool),
+    ExportChangeLog(PathBuf),
+    LatencyUpdated(Latency),
+    ConnectionLost(String),
+    SlaveConnectionChanged(Option<TcpStream>),
+    CapabilitiesReceived(Capabilities),
+    ProtocolError(String),
+    SetWindowActive(bool),
+}
+
+impl SlaveParameterTunerMsg {
+    /// 会修改参数或向下位机发送预览、写入参数的消息，只读模式下被忽略
+    fn modifies_parameters(&self) -> bool {
+        use SlaveParameterTunerMsg::*;
+        matches!(self, SetPropellerLowerDeadzone(..) | SetPropellerUpperDeadzone(..) | SetPropellerPowerPositive(..) | SetPropellerPowerNegative(..) | SetPropellerPowerLinked(..)
+                 | SetPropellerReversed(..) | SetPropellerEnabled(..) | SetP(..) | SetI(..) | SetD(..) | SetControlLoopEnabled(..) | SetControlLoopBias(..)
+                 | SetControlLoopBiasOnDevice(..) | ZeroControlLoopFeedback(..) | SetPropellerPwmFreqCalibration(..) | ApplyParameters
+                 | ImportParameters(..) | LoadPreset(..) | ResolveParameterConflict(..))
+    }
+
+    /// 可能改变参数的消息的来源，用于记录参数修改
+    fn parameter_change_source(&self) -> Option<ParameterChangeSource> {
+        use SlaveParameterTunerMsg::*;
+        match self {
+            ParametersReceived(..) | ResolveParameterConflict(..) => Some(ParameterChangeSource::Device),
+            ImportParameters(..) => Some(ParameterChangeSource::Import),
+            msg if msg.modifies_parameters() => Some(ParameterChangeSource::User),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterChangeSource {
+    User, Device, Import,
+}
+
+impl ToString for ParameterChangeSource {
+    fn to_string(&self) -> String {
+        match self {
+            ParameterChangeSource::User => "用户",
+            ParameterChangeSource::Device => "下位机",
+            ParameterChangeSource::Import => "导入",
+        }.to_string()
+    }
+}
+
+/// 本次会话中的一次参数修改，仅保存在内存中，可导出为 CSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterChange {
+    instant: Instant,
+    time: String, // 本地时间，格式为“年-月-日 时:分:秒”
+    source: ParameterChangeSource,
+    field: String,
+    old: JsonValue,
+    new: JsonValue,
+}
+
+impl ToString for ParameterChange {
+    fn to_string(&self) -> String {
+        let time = self.time.split(' ').last().unwrap_or_default();
+        format!("{}  [{}]  {}：{} → {}", time, self.source.to_string(), self.field, self.old, self.new)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected,
+    Reconnecting,
+    Error(String),
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self::Disconnected
+    }
+}
+
+impl ToString for ConnectionState {
+    fn to_string(&self) -> String {
+        match self {
+            ConnectionState::Disconnected => "未连接".to_string(),
+            ConnectionState::Connecting => "正在连接".to_string(),
+            ConnectionState::Connected => "已连接".to_string(),
+            ConnectionState::Reconnecting => "正在重新连接".to_string(),
+            ConnectionState::Error(err) => format!("连接错误：{}", err),
+        }
+    }
 }
 
 #[tracker::track(pub)]
@@ -58,6 +176,8 @@ pub enum SlaveParameterTunerMsg {
 #[derivative(Default)]
 pub struct PropellerModel {
     key: String,
+    name: String,
+    mirror_name: Option<String>, // 与之对称的推进器的显示名称
     deadzone_lower: i8,
     deadzone_upper: i8,
     #[derivative(Default(value="0.75"))]
@@ -65,8 +185,14 @@ pub struct PropellerModel {
     #[derivative(Default(value="0.75"))]
     power_negative: f64,
     #[derivative(Default(value="true"))]
+    power_linked: bool, // 正反向动力联动时只显示一组控件，同时调整两者
+    #[derivative(Default(value="true"))]
     enabled: bool,
     reversed: bool,
+    preview_history: VecDeque<(u128, i8)>, // (发送时间戳, 预览值)
+    preview_history_displayed: bool,
+    previewing_until: Option<u128>, // 预览值使推进器保持转动的截止时间（毫秒时间戳），推进器归零后为 `None`
+    telemetry: Option<PropellerTelemetry>, // 下位机未发送遥测数据时为 `None`，不显示遥测信息
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -79,9 +205,39 @@ struct Propeller {
     pub enabled: bool,
 }
 
-const DEFAULT_PROPELLERS: [&'static str; 6] = ["front_left", "front_right", "back_left", "back_right", "center_left", "center_right"];
+const PROPELLER_CURRENT_MAX: f64 = 20.0; // 电流指示条满量程（A）
+const PROPELLER_TEMPERATURE_MAX: f64 = 100.0; // 温度指示条满量程（°C）
+const PROPELLER_TEMPERATURE_WARNING: f32 = 80.0; // 电调温度高于该值（°C）时提示过热
+
+const POWER_RANGE: (f64, f64) = (0.01, 1.0);
+const PID_RANGE: (f64, f64) = (0.0, 100.0);
+const PWM_FREQ_CALIBRATION_RANGE: (f64, f64) = (-0.1, 0.1);
+const FEEDBACK_BIAS_RANGE: (f64, f64) = (-1.0, 1.0); // 反馈零点偏置的范围，与反馈值同为换算前的原始值
+
+const DEFAULT_PROPELLERS: [(&'static str, &'static str); 6] = [("front_left", "左前"), ("front_right", "右前"), ("back_left", "左后"), ("back_right", "右后"), ("center_left", "左中"), ("center_right", "右中")];
+const DEFAULT_PROPELLER_MIRROR_PAIRS: [(&'static str, &'static str); 3] = [("front_left", "front_right"), ("back_left", "back_right"), ("center_left", "center_right")];
 const DEFAULT_CONTROL_LOOPS: [&'static str; 2] = ["depth_lock", "direction_lock"];
 const CARD_MIN_WIDTH: i32 = 300;
+const PREVIEW_TIME_MILLIS: u128 = 1000; // 最后一次预览后经过该时间自动将推进器归零
+const PREVIEW_LATCHED_UNTIL: u128 = u128::MAX; // 保持模式下预览指示的截止时间，只有停止推进器后才会清除
+pub const DEFAULT_PREVIEW_INTERVAL: Duration = Duration::from_millis(100);
+pub const MIN_PREVIEW_INTERVAL: Duration = Duration::from_millis(20); // 避免预览数据包占满链路
+const FEEDBACK_RATE_WARNING_THRESHOLD: usize = 5; // 反馈频率低于该值（Hz）时提示检查连接
+const UPLOAD_DIFF_DEPTH: usize = 2; // 仅比较到单个推进器与控制环，下位机按完整的 `Propeller` 与 `ControlLoop` 解析参数
+const PARAMETER_CHANGE_LOG_LIMIT: usize = 1000; // 超过该数量时丢弃最早的修改记录
+const PARAMETER_CHANGE_LOG_DISPLAY_LIMIT: usize = 200; // 面板中显示的最近修改记录数量，导出时包括全部记录
+const PARAMETER_CHANGE_MERGE_INTERVAL: Duration = Duration::from_secs(2); // 用户在该时间内连续修改同一字段（如拖动滑块）时合并为一条记录
+const FEEDBACKS_FORWARD_INTERVAL: Duration = Duration::from_millis(250); // 向机位主界面转发反馈值的最短间隔
+const LINK_CONGESTION_WINDOW: Duration = Duration::from_secs(2); // 统计预览值丢失比例的时间窗口
+const LINK_CONGESTION_MIN_SAMPLES: usize = 10; // 窗口内预览值少于该数量时不判定为拥塞
+const LINK_CONGESTION_ENTER_RATE: f32 = 0.5; // 丢失比例高于该值时提示链路拥塞
+const LINK_CONGESTION_EXIT_RATE: f32 = 0.2; // 丢失比例低于该值时取消提示，与进入阈值不同以免提示反复闪烁
+/// 随程序附带的参数预设（名称、参数数据包），仅设置推进器功率、启用状态与控制环参数
+const PARAMETER_PRESETS: [(&'static str, &'static str); 3] = [
+    ("激进", include_str!("presets/aggressive.json")),
+    ("稳定", include_str!("presets/stable.json")),
+    ("台架安全", include_str!("presets/bench_safe.json")),
+];
 
 trait SlaveParameterTunerWindowExt {
     fn set_destroy(&self, destroy: bool);
@@ -95,12 +251,135 @@ impl SlaveParameterTunerWindowExt for PreferencesWindow {
     }
 }
 
+/// 推进器布局，列出机架上推进器的键、显示名称与左右对称的推进器，不同机架可通过导入布局文件替换默认的六推进器布局
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropellerLayout {
+    propellers: Vec<PropellerLayoutEntry>,
+    #[serde(default)]
+    mirror_pairs: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PropellerLayoutEntry {
+    key: String,
+    #[serde(default)]
+    name: String, // 为空时显示键
+}
+
+impl Default for PropellerLayout {
+    fn default() -> Self {
+        PropellerLayout {
+            propellers: DEFAULT_PROPELLERS.iter().map(|(key, name)| PropellerLayoutEntry { key: key.to_string(), name: name.to_string() }).collect(),
+            mirror_pairs: DEFAULT_PROPELLER_MIRROR_PAIRS.iter().map(|(left, right)| (left.to_string(), right.to_string())).collect(),
+        }
+    }
+}
+
+/// 控制环卡片的显示顺序，仅影响显示，不改变控制环的键与数据包；保存在数据目录中，重新打开窗口后保持
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct ControlLoopOrder {
+    keys: Vec<String>, // 未列出的控制环按下位机声明或默认的顺序排在其后
+}
+
+impl ControlLoopOrder {
+    fn path() -> PathBuf {
+        get_data_path().join("control_loop_order.json")
+    }
+
+    pub fn load() -> ControlLoopOrder {
+        fs::read_to_string(Self::path()).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), IOError> {
+        fs::write(Self::path(), serde_json::to_string_pretty(self).unwrap())
+    }
+
+    /// 按保存的顺序排列 `keys`
+    fn sort<'a>(&self, keys: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut keys: Vec<&str> = keys.into_iter().collect();
+        keys.sort_by_key(|key| self.keys.iter().position(|ordered| ordered.as_str() == *key).unwrap_or(usize::MAX)); // 稳定排序，未列出的控制环保持原有顺序
+        keys.into_iter().map(ToString::to_string).collect()
+    }
+
+    /// 以 `keys` 为新的顺序，保留当前未显示的控制环原有的顺序
+    fn update(&mut self, keys: Vec<String>) {
+        let rest: Vec<String> = self.keys.drain(..).filter(|key| !keys.contains(key)).collect();
+        self.keys = keys;
+        self.keys.extend(rest);
+    }
+}
+
+impl PropellerLayout {
+    fn path() -> PathBuf {
+        get_data_path().join("propeller_layout.json")
+    }
+
+    /// 读取上次导入的布局，不存在或无效时使用默认布局
+    pub fn load() -> PropellerLayout {
+        match fs::read_to_string(Self::path()) {
+            Ok(json) => match Self::from_json(&json) {
+                Ok(layout) => layout,
+                Err(err) => {
+                    log::warn!("推进器布局文件无效，使用默认布局：{}", err);
+                    Default::default()
+                },
+            },
+            Err(_) => Default::default(),
+        }
+    }
+
+    fn save(&self) -> Result<(), IOError> {
+        fs::write(Self::path(), serde_json::to_string_pretty(self).unwrap())
+    }
+
+    fn from_json(json: &str) -> Result<PropellerLayout, String> {
+        let layout: PropellerLayout = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        if layout.propellers.is_empty() {
+            return Err("布局中没有推进器".to_string());
+        }
+        let mut keys = std::collections::HashSet::new();
+        for entry in &layout.propellers {
+            if entry.key.is_empty() || !keys.insert(entry.key.as_str()) {
+                return Err(format!("推进器的键“{}”为空或重复", entry.key));
+            }
+        }
+        for (left, right) in &layout.mirror_pairs {
+            if !keys.contains(left.as_str()) || !keys.contains(right.as_str()) || left == right {
+                return Err(format!("对称推进器 {} 与 {} 无效", left, right));
+            }
+        }
+        Ok(layout)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.propellers.iter().map(|entry| entry.key.clone()).collect()
+    }
+
+    fn key_to_string<'a>(&'a self, key: &'a str) -> &'a str {
+        self.propellers.iter().find(|entry| entry.key == key && !entry.name.is_empty()).map_or(key, |entry| entry.name.as_str())
+    }
+
+    fn mirror_of(&self, key: &str) -> Option<&str> {
+        self.mirror_pairs.iter().find_map(|(left, right)| match key {
+            _ if key == left => Some(right.as_str()),
+            _ if key == right => Some(left.as_str()),
+            _ => None,
+        })
+    }
+}
+
+fn current_millis() -> u128 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis()
+}
+
 impl PropellerModel {
-    pub fn new(key: &str) -> PropellerModel {
+    pub fn new(key: &str, layout: &PropellerLayout) -> PropellerModel {
         let a = PreferencesWindow::new();
         a.set_destroy(false);
         PropellerModel {
             key: key.to_string(),
+            name: Self::key_to_string(key, layout).to_string(),
+            mirror_name: layout.mirror_of(key).map(|mirror| Self::key_to_string(mirror, layout).to_string()),
             ..Default::default()
         }
     }
@@ -112,16 +391,30 @@ impl PropellerModel {
         }).collect()
     }
 
-    fn key_to_string<'a, 'b : 'a>(key: &'b str) -> &'a str {
-        match key {
-            "front_left"   => "左前",
-            "front_right"  => "右前",
-            "back_left"    => "左后",
-            "back_right"   => "右后",
-            "center_left"  => "左中",
-            "center_right" => "右中",
-            key => key,
+    /// 将控件数值四舍五入并限制在 `i8` 范围内，返回值的第二项表示是否发生了截断。
+    fn deadzone_from_value(value: f64) -> (i8, bool) {
+        let rounded = value.round();
+        if rounded.is_nan() {
+            return (0, true);
         }
+        let clamped = rounded.clamp(i8::MIN as f64, i8::MAX as f64);
+        (clamped as i8, clamped != rounded)
+    }
+
+    /// 设置下死区，上死区随之提高以保持下死区不大于上死区
+    fn update_deadzone_lower(&mut self, value: i8) {
+        self.set_deadzone_lower(value);
+        self.set_deadzone_upper(max(*self.get_deadzone_upper(), value));
+    }
+
+    /// 设置上死区，下死区随之降低以保持下死区不大于上死区
+    fn update_deadzone_upper(&mut self, value: i8) {
+        self.set_deadzone_upper(value);
+        self.set_deadzone_lower(min(*self.get_deadzone_lower(), value));
+    }
+
+    fn key_to_string<'a>(key: &'a str, layout: &'a PropellerLayout) -> &'a str {
+        layout.key_to_string(key)
     }
 }
 
@@ -136,7 +429,24 @@ pub struct ControlLoopModel {
     i: f64,
     #[derivative(Default(value="1.0"))]
     d: f64,
+    #[derivative(Default(value="true"))]
+    enabled: bool,
     feedbacks: VecDeque<f32>,
+    raw_feedbacks: VecDeque<f32>, // 包括被丢弃的异常值，仅在启用异常值过滤时记录，用于导出调参报告
+    feedback_count: u64, // 累计加入 `feedbacks` 的反馈数，用于换算中断位置在图表中的序号
+    feedback_gaps: VecDeque<u64>, // 连接中断后收到的第一个反馈的累计序号
+    feedback_display: ControlLoopFeedbackDisplay,
+    bias: f64, // 反馈零点偏置，如深度传感器在水面处的读数
+    bias_on_device: bool, // 偏置随参数写入下位机，由下位机修正反馈；否则仅修正图表中的显示
+    snapshots: Vec<ControlLoopSnapshot>, // 仅保存在内存中，关闭窗口后丢弃，可随调参报告导出
+    recalled_snapshot: Option<usize>, // 图表正在显示的快照，为 `None` 时显示实时反馈
+}
+
+/// 某一时刻图表中的反馈曲线及当时的参数，用于对比不同参数的效果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlLoopSnapshot {
+    label: String,
+    values: Vec<f32>, // 已按当时的偏置修正
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -144,12 +454,36 @@ struct ControlLoop {
     pub p: f64,
     pub i: f64,
     pub d: f64,
+    #[serde(default = "control_loop_enabled_default")]
+    pub enabled: bool, // 旧版下位机不包含该字段，视为启用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bias: Option<f64>, // 仅在选择由下位机修正反馈时发送
+}
+
+fn control_loop_enabled_default() -> bool {
+    true
+}
+
+/// 反馈值在图表中的显示方式，仅影响显示，原始反馈值不做换算；下位机可在参数数据包中为每个控制环声明
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ControlLoopFeedbackDisplay {
+    pub scale: f32,
+    pub unit: String,
+    pub upper: f32,
+    pub lower: f32,
+}
+
+impl ControlLoopFeedbackDisplay {
+    fn is_valid(&self) -> bool {
+        self.scale.is_finite() && self.scale != 0.0 && self.upper.is_finite() && self.lower.is_finite() && self.upper > self.lower
+    }
 }
 
 impl ControlLoopModel {
     fn new(key: &str) -> ControlLoopModel {
         ControlLoopModel {
             key: key.to_string(),
+            feedback_display: Self::default_feedback_display(key),
             ..Default::default()
         }
     }
@@ -158,7 +492,7 @@ impl ControlLoopModel {
         v.iter().map(Deref::deref).map(Self::to_control_loop).collect()
     }
 
-    fn key_to_string<'a, 'b : 'a>(key: &'b str) -> &'a str {
+    pub fn key_to_string<'a, 'b : 'a>(key: &'b str) -> &'a str {
         match key {
             "depth_lock"     => "深度锁定", 
             "direction_lock" => "方向锁定",
@@ -166,9 +500,72 @@ impl ControlLoopModel {
         }
     }
 
+    /// 下位机未声明显示方式时使用的默认值
+    fn default_feedback_display(key: &str) -> ControlLoopFeedbackDisplay {
+        let (scale, unit, range) = match key {
+            "depth_lock"     => (1.0, "m", 1.0), // 以米为单位显示原始反馈值，不做换算；下位机可通过 `control_loop_feedback_display` 声明实际的比例
+            "direction_lock" => (180.0, "°", 180.0),
+            _                => (100.0, "%", 100.0),
+        };
+        ControlLoopFeedbackDisplay { scale, unit: unit.to_string(), upper: range, lower: -range }
+    }
+
     fn to_control_loop(&self) -> (String, ControlLoop) {
-        let Self { key, p, i, d, .. } = self.clone();
-        (key, ControlLoop { p, i, d })
+        let Self { key, p, i, d, enabled, bias, bias_on_device, .. } = self.clone();
+        (key, ControlLoop { p, i, d, enabled, bias: bias_on_device.then(|| bias) })
+    }
+
+    /// 图表中显示的反馈值；由下位机修正时收到的反馈已扣除偏置
+    fn displayed_feedback(&self, value: f32) -> f32 {
+        if self.bias_on_device { value } else { value - self.bias as f32 }
+    }
+
+    /// 使最近一次反馈显示为零的偏置，尚未收到反馈时返回 `None`
+    fn zeroing_bias(&self) -> Option<f64> {
+        self.feedbacks.back().map(|&value| self.displayed_feedback(value) as f64 + self.bias)
+    }
+
+    /// 图表中显示的曲线，正在查看快照时为快照的曲线
+    fn graph_points(&self) -> Vec<GraphPoint> {
+        match self.recalled_snapshot.and_then(|index| self.snapshots.get(index)) {
+            Some(snapshot) => snapshot.values.iter().map(|&value| GraphPoint { value, timestamp: None }).collect(),
+            None => self.feedbacks.iter().map(|&x| GraphPoint { value: self.displayed_feedback(x), timestamp: None }).collect(),
+        }
+    }
+
+    /// 图表中需要标记连接中断的数据点序号
+    fn graph_gaps(&self) -> Vec<usize> {
+        if self.recalled_snapshot.is_some() {
+            return Vec::new();
+        }
+        let start = self.feedback_count - self.feedbacks.len() as u64;
+        self.feedback_gaps.iter().filter(|&&gap| gap > start).map(|&gap| (gap - start) as usize).collect()
+    }
+
+    /// 加入一个反馈值，超过数量上限时丢弃最早的值
+    fn push_feedback(&mut self, value: f32, limit: usize) {
+        let feedbacks = self.get_mut_feedbacks();
+        if feedbacks.len() == limit {
+            feedbacks.pop_front();
+        }
+        feedbacks.push_back(value);
+        self.feedback_count += 1;
+        let start = self.feedback_count - self.feedbacks.len() as u64;
+        while self.feedback_gaps.front().map_or(false, |&gap| gap <= start) {
+            self.feedback_gaps.pop_front();
+        }
+    }
+
+    /// 快照选择框中的选项，第一项为实时反馈
+    fn snapshot_labels(&self) -> Vec<&str> {
+        std::iter::once("实时").chain(self.snapshots.iter().map(|snapshot| snapshot.label.as_str())).collect()
+    }
+
+    /// 偏置在图表单位下的取值范围
+    fn displayed_bias_range(&self) -> (f64, f64) {
+        let scale = self.feedback_display.scale as f64;
+        let (lower, upper) = (FEEDBACK_BIAS_RANGE.0 * scale, FEEDBACK_BIAS_RANGE.1 * scale);
+        (lower.min(upper), lower.max(upper))
     }
 }
 
@@ -188,6 +585,55 @@ pub struct SlaveParameterTunerModel {
     tcp_msg_sender: Option<async_std::channel::Sender<SlaveParameterTunerTcpMsg>>,
     graph_view_point_num_limit: u16,
     stopped: bool,
+    connection_state: ConnectionState,
+    #[no_eq]
+    #[derivative(Default(value="TextBuffer::new(None)"))]
+    notes: TextBuffer,
+    slave_address: Option<String>,
+    #[no_eq]
+    imported_metadata: Option<SlaveParameterTunerExportMetadata>,
+    #[no_eq]
+    toast_messages: Rc<RefCell<VecDeque<String>>>,
+    device_parameters: Option<SlaveParameterTunerPacket>, // 最近一次从下位机接收或成功写入下位机的参数
+    force_full_upload: bool,
+    preserve_local_edits: bool,
+    #[derivative(Default(value="DEFAULT_PREVIEW_INTERVAL"))]
+    preview_interval: Duration,
+    pretty_json: bool, // 以格式化的 JSON 发送数据包，仅用于调试
+    #[derivative(Default(value="50.0"))]
+    pwm_base_frequency: f64, // 首选项中的 PWM 基准频率（Hz）
+    device_pwm_base_frequency: Option<f64>, // 下位机报告的 PWM 基准频率（Hz），优先于首选项
+    feedback_outlier_limit: Option<f32>, // 原始反馈值的绝对值上限，超过时不绘制，为 `None` 时不过滤
+    export_point_limit: Option<usize>, // 调参报告中每个控制环最多导出的反馈采样点数，为 `None` 时导出全部采样点
+    #[no_eq]
+    exclusive_tcp_activity: Rc<Cell<Option<ExclusiveTcpActivity>>>,
+    #[no_eq]
+    pending_device_parameters: Option<SlaveParameterTunerPacket>, // 与本地修改冲突、等待用户选择的下位机参数
+    #[no_eq]
+    last_feedbacks_forwarded: Option<Instant>,
+    #[no_eq]
+    feedback_timestamps: VecDeque<Instant>, // 最近一秒内收到反馈的时间
+    feedback_rate: usize,
+    uploading: bool, // 参数正在写入下位机，期间禁止编辑以免与写入的数据包冲突
+    #[no_eq]
+    uploading_parameters: Option<SlaveParameterTunerPacket>, // 正在写入的参数，写入成功后才视为下位机当前的参数
+    #[no_eq]
+    propeller_layout: PropellerLayout,
+    control_loop_order: ControlLoopOrder,
+    link_congested: bool, // 预览值因链路拥塞被大量丢弃或合并
+    loaded_preset: Option<&'static str>, // 已载入但尚未写入下位机的预设名称
+    read_only: bool, // 只读模式：仅显示下位机的参数与反馈，不允许修改，也不发送预览或写入参数
+    preview_latched: bool, // 保持模式：预览值不自动归零，推进器持续转动直至手动停止，用于台架长时间运行
+    #[no_eq]
+    parameter_change_log: VecDeque<ParameterChange>,
+    latency: Option<Latency>, // 调试期间测得的往返延迟
+    auto_reconnect: bool, // 机位启用了自动重连，连接中断后保留窗口等待重连
+    keep_history_on_reconnect: bool,
+    awaiting_reconnect: bool,
+    #[derivative(Default(value="true"))]
+    window_active: bool, // 窗口处于前台时关键事件只在本窗口提示
+    #[no_eq]
+    capabilities: Capabilities, // 下位机连接时声明的功能，未声明时为最小功能集
 }
 
 #[relm4::factory_prototype(pub)]
@@ -199,21 +645,29 @@ impl FactoryPrototype for PropellerModel {
 
     view! {
         group = &PreferencesGroup {
-            set_title: PropellerModel::key_to_string(&self.key),
+            set_title: &self.name,
+            set_description: self.mirror_name.as_ref().map(|mirror| format!("与{}对称", mirror)).as_deref(),
             add = &GtkBox {
                 set_orientation: Orientation::Vertical,
                 set_spacing: 12,
                 append = &PreferencesGroup {
                     add = &ExpanderRow {
                         set_title: "启用",
+                        set_tooltip_text: Some("关闭后下位机将不再驱动该推进器"),
                         set_show_enable_switch: true,
                         set_expanded: *self.get_enabled(),
+                        add_action = &Spinner {
+                            set_tooltip_text: Some("正在预览，推进器可能在转动"),
+                            set_visible: track!(self.changed(PropellerModel::previewing_until()), self.previewing_until.is_some()),
+                            set_spinning: track!(self.changed(PropellerModel::previewing_until()), self.previewing_until.is_some()),
+                        },
                         set_enable_expansion: track!(self.changed(PropellerModel::enabled()), *self.get_enabled()),
                         connect_enable_expansion_notify(sender, key) => move |expander| {
                             send!(sender, SlaveParameterTunerMsg::SetPropellerEnabled(key, expander.enables_expansion()));
                         },
                         add_row = &ActionRow {
                             set_title: "反转",
+                            set_tooltip_text: Some("反转推进器的转动方向，用于修正安装方向或桨叶正反导致的推力方向错误"),
                             add_suffix: reversed_switch = &Switch {
                                 set_valign: Align::Center,
                                 set_active: track!(self.changed(PropellerModel::reversed()), *self.get_reversed()),
@@ -225,9 +679,24 @@ impl FactoryPrototype for PropellerModel {
                             set_activatable_widget: Some(&reversed_switch),
                         },
                         add_row = &ActionRow {
-                            set_title: "正向动力",
+                            set_title: "联动正反向动力",
+                            set_tooltip_text: Some("开启后正转与反转使用相同的输出功率比例，由同一组控件调整"),
+                            add_suffix: power_linked_switch = &Switch {
+                                set_valign: Align::Center,
+                                set_active: track!(self.changed(PropellerModel::power_linked()), *self.get_power_linked()),
+                                connect_state_set(sender, key) => move |_switch, state| {
+                                    send!(sender, SlaveParameterTunerMsg::SetPropellerPowerLinked(key, state));
+                                    Inhibit(false)
+                                }
+                            },
+                            set_activatable_widget: Some(&power_linked_switch),
+                        },
+                        add_row = &ActionRow {
+                            set_title: track!(self.changed(PropellerModel::power_linked()), if *self.get_power_linked() { "动力" } else { "正向动力" }),
+                            set_tooltip_text: track!(self.changed(PropellerModel::power_linked()), Some(if *self.get_power_linked() { "正转与反转时的输出功率比例，范围 0.01 ~ 1.00，1.00 为满功率，台架调试时建议不超过 0.50" } else { "正转时的输出功率比例，范围 0.01 ~ 1.00，1.00 为满功率，台架调试时建议不超过 0.50" })),
                             add_suffix = &SpinButton::with_range(0.01, 1.0, 0.01) {
                                 set_value: track!(self.changed(PropellerModel::power_positive()), *self.get_power_positive()),
+                                connect_input => |button| validate_spin_button_input(button),
                                 set_digits: 2,
                                 set_valign: Align::Center,
                                 connect_value_changed(key, sender) => move |button| {
@@ -240,15 +709,18 @@ impl FactoryPrototype for PropellerModel {
                                 set_width_request: CARD_MIN_WIDTH,
                                 set_round_digits: 2,
                                 set_value: track!(self.changed(PropellerModel::power_positive()), *self.get_power_positive() as f64),
-                                connect_value_changed(key, sender) => move |scale| {
+                                connect_value_changed_debounced(key, sender) => move |scale| {
                                     send!(sender, SlaveParameterTunerMsg::SetPropellerPowerPositive(key, scale.value()));
                                 }
                             }
                         },
                         add_row = &ActionRow {
+                            set_visible: track!(self.changed(PropellerModel::power_linked()), !*self.get_power_linked()),
                             set_title: "反向动力",
+                            set_tooltip_text: Some("反转时的输出功率比例，范围 0.01 ~ 1.00，1.00 为满功率，台架调试时建议不超过 0.50"),
                             add_suffix = &SpinButton::with_range(0.01, 1.0, 0.01) {
                                 set_value: track!(self.changed(PropellerModel::power_negative()), *self.get_power_negative()),
+                                connect_input => |button| validate_spin_button_input(button),
                                 set_digits: 2,
                                 set_valign: Align::Center,
                                 connect_value_changed(key, sender) => move |button| {
@@ -257,23 +729,26 @@ impl FactoryPrototype for PropellerModel {
                             },
                         },
                         add_row = &ActionRow {
+                            set_visible: track!(self.changed(PropellerModel::power_linked()), !*self.get_power_linked()),
                             set_child = Some(&Scale::with_range(Orientation::Horizontal, 0.01, 1.0, 0.01)) {
                                 set_width_request: CARD_MIN_WIDTH,
                                 set_round_digits: 2,
                                 set_value: track!(self.changed(PropellerModel::power_negative()), *self.get_power_negative() as f64),
-                                connect_value_changed(key, sender) => move |scale| {
+                                connect_value_changed_debounced(key, sender) => move |scale| {
                                     send!(sender, SlaveParameterTunerMsg::SetPropellerPowerNegative(key, scale.value()));
                                 }
                             }
                         },
                         add_row = &ActionRow {
                             set_title: "死区上限",
+                            set_tooltip_text: Some("电调输入死区的上边界（原始值，范围 -128 ~ 127），输入值落在上下限之间时推进器不转动，调整时推进器会短暂以该值转动以便观察"),
                             add_suffix = &SpinButton::with_range(-128.0, 127.0, 1.0) {
                                 set_value: track!(self.changed(PropellerModel::deadzone_upper()), *self.get_deadzone_upper() as f64),
+                                connect_input => |button| validate_spin_button_input(button),
                                 set_digits: 0,
                                 set_valign: Align::Center,
                                 connect_value_changed(key, sender) => move |button| {
-                                    send!(sender, SlaveParameterTunerMsg::SetPropellerUpperDeadzone(key, button.value() as i8));
+                                    send!(sender, SlaveParameterTunerMsg::SetPropellerUpperDeadzone(key, button.value()));
                                 }
                             },
                         },
@@ -282,19 +757,21 @@ impl FactoryPrototype for PropellerModel {
                                 set_width_request: CARD_MIN_WIDTH,
                                 set_round_digits: 0,
                                 set_value: track!(self.changed(PropellerModel::deadzone_upper()), *self.get_deadzone_upper() as f64),
-                                connect_value_changed(key, sender) => move |scale| {
-                                    send!(sender, SlaveParameterTunerMsg::SetPropellerUpperDeadzone(key, scale.value() as i8));
+                                connect_value_changed_debounced(key, sender) => move |scale| {
+                                    send!(sender, SlaveParameterTunerMsg::SetPropellerUpperDeadzone(key, scale.value()));
                                 }
                             }
                         },
                         add_row = &ActionRow {
                             set_title: "死区下限",
+                            set_tooltip_text: Some("电调输入死区的下边界（原始值，范围 -128 ~ 127），不能大于死区上限，调整时推进器会短暂以该值转动以便观察"),
                             add_suffix = &SpinButton::with_range(-128.0, 127.0, 1.0) {
                                 set_value: track!(self.changed(PropellerModel::deadzone_lower()), *self.get_deadzone_lower() as f64),
+                                connect_input => |button| validate_spin_button_input(button),
                                 set_digits: 0,
                                 set_valign: Align::Center,
                                 connect_value_changed(key, sender) => move |button| {
-                                    send!(sender, SlaveParameterTunerMsg::SetPropellerLowerDeadzone(key, button.value() as i8));
+                                    send!(sender, SlaveParameterTunerMsg::SetPropellerLowerDeadzone(key, button.value()));
                                 }
                             },
                         },
@@ -303,13 +780,68 @@ impl FactoryPrototype for PropellerModel {
                                 set_width_request: CARD_MIN_WIDTH,
                                 set_round_digits: 0,
                                 set_value: track!(self.changed(PropellerModel::deadzone_lower()), *self.get_deadzone_lower() as f64),
-                                connect_value_changed(key, sender) => move |scale| {
-                                    send!(sender, SlaveParameterTunerMsg::SetPropellerLowerDeadzone(key, scale.value() as i8));
+                                connect_value_changed_debounced(key, sender) => move |scale| {
+                                    send!(sender, SlaveParameterTunerMsg::SetPropellerLowerDeadzone(key, scale.value()));
                                 }
                             }
                         },
                     },
                 },
+                append = &PreferencesGroup {
+                    set_title: "遥测",
+                    set_visible: track!(self.changed(PropellerModel::telemetry()), self.telemetry.is_some()),
+                    add = &ActionRow {
+                        set_title: "电流",
+                        set_visible: track!(self.changed(PropellerModel::telemetry()), self.telemetry.as_ref().and_then(|telemetry| telemetry.current).is_some()),
+                        set_subtitle: track!(self.changed(PropellerModel::telemetry()), &self.telemetry.as_ref().and_then(|telemetry| telemetry.current).map_or(String::new(), |current| format!("{:.2} A", current))),
+                        add_suffix = &LevelBar {
+                            set_valign: Align::Center,
+                            set_width_request: CARD_MIN_WIDTH / 3,
+                            set_max_value: PROPELLER_CURRENT_MAX,
+                            set_value: track!(self.changed(PropellerModel::telemetry()), self.telemetry.as_ref().and_then(|telemetry| telemetry.current).map_or(0.0, |current| (current as f64).clamp(0.0, PROPELLER_CURRENT_MAX))),
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "电调温度",
+                        set_visible: track!(self.changed(PropellerModel::telemetry()), self.telemetry.as_ref().and_then(|telemetry| telemetry.temperature).is_some()),
+                        set_subtitle: track!(self.changed(PropellerModel::telemetry()), &self.telemetry.as_ref().and_then(|telemetry| telemetry.temperature).map_or(String::new(), |temperature| if temperature >= PROPELLER_TEMPERATURE_WARNING {
+                            format!("{:.1} °C（过热）", temperature)
+                        } else {
+                            format!("{:.1} °C", temperature)
+                        })),
+                        add_suffix = &LevelBar {
+                            set_valign: Align::Center,
+                            set_width_request: CARD_MIN_WIDTH / 3,
+                            set_max_value: PROPELLER_TEMPERATURE_MAX,
+                            set_value: track!(self.changed(PropellerModel::telemetry()), self.telemetry.as_ref().and_then(|telemetry| telemetry.temperature).map_or(0.0, |temperature| (temperature as f64).clamp(0.0, PROPELLER_TEMPERATURE_MAX))),
+                        },
+                    },
+                },
+                append = &PreferencesGroup {
+                    add = &ActionRow {
+                        set_title: "发送记录",
+                        set_tooltip_text: Some("显示最近实际发送至该推进器的预览值"),
+                        add_suffix: preview_history_switch = &Switch {
+                            set_valign: Align::Center,
+                            set_active: track!(self.changed(PropellerModel::preview_history_displayed()), *self.get_preview_history_displayed()),
+                            connect_state_set(sender, key) => move |_switch, state| {
+                                send!(sender, SlaveParameterTunerMsg::SetPropellerPreviewHistoryDisplayed(key, state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&preview_history_switch),
+                    },
+                    add = &ActionRow {
+                        set_visible: track!(self.changed(PropellerModel::preview_history_displayed()), *self.get_preview_history_displayed()),
+                        set_child = Some(&GraphView::new()) {
+                            set_width_request: CARD_MIN_WIDTH,
+                            set_height_request: CARD_MIN_WIDTH / 3,
+                            set_points: track!(self.changed(PropellerModel::preview_history()), self.preview_history.iter().map(|&(timestamp, x)| GraphPoint { value: x as f32, timestamp: DateTime::from_unix_local((timestamp / 1000) as i64).ok() }).collect()),
+                            set_upper_value: 128.0,
+                            set_lower_value: -128.0,
+                        },
+                    },
+                },
             }
         }
     }
@@ -337,17 +869,126 @@ impl FactoryPrototype for ControlLoopModel {
                         set_child = Some(&GraphView::new()) {
                             set_width_request: CARD_MIN_WIDTH,
                             set_height_request: CARD_MIN_WIDTH / 2,
-                            set_points: track!(self.changed(ControlLoopModel::feedbacks()), self.feedbacks.iter().map(|&x|  GraphPoint { value: x * 100.0 }).collect()),
-                            set_upper_value: 100.0,
-                            set_lower_value: -100.0,
+                            set_points: track!(self.changed(ControlLoopModel::feedbacks()) || self.changed(ControlLoopModel::bias()) || self.changed(ControlLoopModel::bias_on_device()) || self.changed(ControlLoopModel::recalled_snapshot()) || self.changed(ControlLoopModel::snapshots()), self.graph_points()),
+                            set_gaps: track!(self.changed(ControlLoopModel::feedbacks()) || self.changed(ControlLoopModel::feedback_gaps()) || self.changed(ControlLoopModel::recalled_snapshot()), self.graph_gaps()),
+                            set_value_scale: track!(self.changed(ControlLoopModel::feedback_display()), self.feedback_display.scale),
+                            set_unit_label: track!(self.changed(ControlLoopModel::feedback_display()), &self.feedback_display.unit),
+                            set_upper_value: track!(self.changed(ControlLoopModel::feedback_display()), self.feedback_display.upper),
+                            set_lower_value: track!(self.changed(ControlLoopModel::feedback_display()), self.feedback_display.lower),
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "快照",
+                        set_tooltip_text: Some("保存当前图表中的曲线及参数，用于对比不同参数的效果；快照在关闭窗口后丢弃，可通过生成调参报告导出"),
+                        add_suffix = &DropDown {
+                            set_valign: Align::Center,
+                            set_model: track!(self.changed(ControlLoopModel::snapshots()), Some(&StringList::new(&self.snapshot_labels()))),
+                            set_selected: track!(self.changed(ControlLoopModel::snapshots()) || self.changed(ControlLoopModel::recalled_snapshot()), self.recalled_snapshot.map_or(0, |index| index + 1) as u32),
+                            connect_selected_notify(sender, key) => move |drop_down| {
+                                send!(sender, SlaveParameterTunerMsg::RecallControlLoopSnapshot(key, (drop_down.selected() as usize).checked_sub(1)));
+                            },
+                        },
+                        add_suffix = &Button {
+                            set_icon_name: "camera-photo-symbolic",
+                            set_css_classes: &["flat"],
+                            set_valign: Align::Center,
+                            set_tooltip_text: Some("拍摄快照"),
+                            connect_clicked(key, sender) => move |_button| {
+                                send!(sender, SlaveParameterTunerMsg::CaptureControlLoopSnapshot(key));
+                            }
+                        },
+                        add_suffix = &Button {
+                            set_icon_name: "user-trash-symbolic",
+                            set_css_classes: &["flat"],
+                            set_valign: Align::Center,
+                            set_tooltip_text: Some("删除正在查看的快照"),
+                            set_sensitive: track!(self.changed(ControlLoopModel::recalled_snapshot()), self.recalled_snapshot.is_some()),
+                            connect_clicked(key, sender) => move |_button| {
+                                send!(sender, SlaveParameterTunerMsg::RemoveControlLoopSnapshot(key));
+                            }
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "启用",
+                        set_tooltip_text: Some("关闭后下位机将不再执行该控制环，参数仍可调整"),
+                        add_suffix: enabled_switch = &Switch {
+                            set_active: track!(self.changed(ControlLoopModel::enabled()), *self.get_enabled()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender, key) => move |_switch, state| {
+                                send!(sender, SlaveParameterTunerMsg::SetControlLoopEnabled(key, state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&enabled_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "零点偏置",
+                        set_subtitle: track!(self.changed(ControlLoopModel::feedback_display()), &format!("单位：{}", self.feedback_display.unit)),
+                        set_tooltip_text: Some("从反馈值中扣除的固定偏差，用于修正传感器的零点漂移"),
+                        add_suffix = &SpinButton::with_range(FEEDBACK_BIAS_RANGE.0, FEEDBACK_BIAS_RANGE.1, 0.01) {
+                            set_range: track!(self.changed(ControlLoopModel::feedback_display()), self.displayed_bias_range().0, self.displayed_bias_range().1),
+                            set_value: track!(self.changed(ControlLoopModel::bias()) || self.changed(ControlLoopModel::feedback_display()), *self.get_bias() * self.feedback_display.scale as f64),
+                            connect_input => |button| validate_spin_button_input(button),
+                            set_digits: 2,
+                            set_valign: Align::Center,
+                            connect_value_changed(key, sender) => move |button| {
+                                send!(sender, SlaveParameterTunerMsg::SetControlLoopBias(key, button.value()));
+                            }
+                        },
+                        add_suffix = &Button {
+                            set_label: "设为零点",
+                            set_valign: Align::Center,
+                            set_tooltip_text: Some("将当前反馈值设为零点"),
+                            connect_clicked(key, sender) => move |_button| {
+                                send!(sender, SlaveParameterTunerMsg::ZeroControlLoopFeedback(key));
+                            }
+                        },
+                    },
+                    add = &ActionRow {
+                        set_title: "由下位机修正",
+                        set_tooltip_text: Some("开启后偏置随参数写入下位机，由下位机修正反馈；关闭时仅修正图表中的显示"),
+                        add_suffix: bias_on_device_switch = &Switch {
+                            set_active: track!(self.changed(ControlLoopModel::bias_on_device()), *self.get_bias_on_device()),
+                            set_valign: Align::Center,
+                            connect_state_set(sender, key) => move |_switch, state| {
+                                send!(sender, SlaveParameterTunerMsg::SetControlLoopBiasOnDevice(key, state));
+                                Inhibit(false)
+                            }
+                        },
+                        set_activatable_widget: Some(&bias_on_device_switch),
+                    },
+                    add = &ActionRow {
+                        set_title: "卡片位置",
+                        set_tooltip_text: Some("调整该控制环在窗口中的显示顺序，仅影响显示，重新打开窗口后保持"),
+                        add_suffix = &Button {
+                            set_icon_name: "go-previous-symbolic",
+                            set_css_classes: &["flat"],
+                            set_valign: Align::Center,
+                            set_tooltip_text: Some("前移"),
+                            set_sensitive: *key > 0,
+                            connect_clicked(key, sender) => move |_button| {
+                                send!(sender, SlaveParameterTunerMsg::MoveControlLoopBackward(key));
+                            }
+                        },
+                        add_suffix = &Button {
+                            set_icon_name: "go-next-symbolic",
+                            set_css_classes: &["flat"],
+                            set_valign: Align::Center,
+                            set_tooltip_text: Some("后移"),
+                            connect_clicked(key, sender) => move |_button| {
+                                send!(sender, SlaveParameterTunerMsg::MoveControlLoopForward(key));
+                            }
                         },
                     },
                 },
                 append = &PreferencesGroup {
+                    set_opacity: track!(self.changed(ControlLoopModel::enabled()), if *self.get_enabled() { 1.0 } else { 0.5 }),
                     add = &ActionRow {
                         set_title: "P",
+                        set_tooltip_text: Some("比例系数，范围 0 ~ 100，增大可加快响应，过大会引起振荡"),
                         add_suffix = &SpinButton::with_range(0.0, 100.0, 0.01) {
                             set_value: track!(self.changed(ControlLoopModel::p()), *self.get_p()),
+                            connect_input => |button| validate_spin_button_input(button),
                             set_digits: 2,
                             set_valign: Align::Center,
                             connect_value_changed(key, sender) => move |button| {
@@ -360,17 +1001,20 @@ impl FactoryPrototype for ControlLoopModel {
                             set_width_request: CARD_MIN_WIDTH,
                             set_round_digits: 2,
                             set_value: track!(self.changed(ControlLoopModel::p()), *self.get_p()),
-                            connect_value_changed(key, sender) => move |scale| {
+                            connect_value_changed_debounced(key, sender) => move |scale| {
                                 send!(sender, SlaveParameterTunerMsg::SetP(key, scale.value()));
                             }
                         }
                     },
                 },
                 append = &PreferencesGroup {
+                    set_opacity: track!(self.changed(ControlLoopModel::enabled()), if *self.get_enabled() { 1.0 } else { 0.5 }),
                     add = &ActionRow {
                         set_title: "I",
+                        set_tooltip_text: Some("积分系数，范围 0 ~ 100，用于消除稳态误差，过大会引起超调"),
                         add_suffix = &SpinButton::with_range(0.0, 100.0, 0.01) {
                             set_value: track!(self.changed(ControlLoopModel::i()), *self.get_i()),
+                            connect_input => |button| validate_spin_button_input(button),
                             set_digits: 2,
                             set_valign: Align::Center,
                             connect_value_changed(key, sender) => move |button| {
@@ -383,17 +1027,20 @@ impl FactoryPrototype for ControlLoopModel {
                             set_width_request: CARD_MIN_WIDTH,
                             set_round_digits: 2,
                             set_value: track!(self.changed(ControlLoopModel::i()), *self.get_i()),
-                            connect_value_changed(key, sender) => move |scale| {
+                            connect_value_changed_debounced(key, sender) => move |scale| {
                                 send!(sender, SlaveParameterTunerMsg::SetI(key, scale.value()));
                             }
                         }
                     },
                 },
                 append = &PreferencesGroup {
+                    set_opacity: track!(self.changed(ControlLoopModel::enabled()), if *self.get_enabled() { 1.0 } else { 0.5 }),
                     add = &ActionRow {
                         set_title: "D",
+                        set_tooltip_text: Some("微分系数，范围 0 ~ 100，用于抑制超调与振荡，过大会放大传感器噪声"),
                         add_suffix = &SpinButton::with_range(0.0, 100.0, 0.01) {
                             set_value: track!(self.changed(ControlLoopModel::d()), *self.get_d()),
+                            connect_input => |button| validate_spin_button_input(button),
                             set_digits: 2,
                             set_valign: Align::Center,
                             connect_value_changed(key, sender) => move |button| {
@@ -406,7 +1053,7 @@ impl FactoryPrototype for ControlLoopModel {
                             set_width_request: CARD_MIN_WIDTH,
                             set_round_digits: 2,
                             set_value: track!(self.changed(ControlLoopModel::d()), *self.get_d()),
-                            connect_value_changed(key, sender) => move |scale| {
+                            connect_value_changed_debounced(key, sender) => move |scale| {
                                 send!(sender, SlaveParameterTunerMsg::SetD(key, scale.value()));
                             }
                         }
@@ -422,73 +1069,514 @@ impl FactoryPrototype for ControlLoopModel {
 }
 
 impl SlaveParameterTunerModel {
-    pub fn new(graph_view_point_num_limit: u16) -> Self {
+    pub fn new(preferences: &PreferencesModel, exclusive_tcp_activity: Rc<Cell<Option<ExclusiveTcpActivity>>>) -> Self {
+        let preview_interval = preferences.param_tuner_preview_interval;
+        if preview_interval < MIN_PREVIEW_INTERVAL {
+            log::warn!("预览发送间隔 {:?} 过短，已限制为 {:?}", preview_interval, MIN_PREVIEW_INTERVAL);
+        }
+        let propeller_layout = PropellerLayout::load();
+        let control_loop_order = ControlLoopOrder::load();
         SlaveParameterTunerModel {
-            propellers: FactoryVec::from_vec(DEFAULT_PROPELLERS.iter().map(|key| PropellerModel::new(key)).collect()),
-            control_loops: FactoryVec::from_vec(DEFAULT_CONTROL_LOOPS.iter().map(|key| ControlLoopModel::new(key)).collect()),
-            graph_view_point_num_limit,
+            propellers: FactoryVec::from_vec(propeller_layout.keys().iter().map(|key| PropellerModel::new(key, &propeller_layout)).collect()),
+            propeller_layout,
+            control_loops: FactoryVec::from_vec(control_loop_order.sort(DEFAULT_CONTROL_LOOPS).iter().map(|key| ControlLoopModel::new(key)).collect()),
+            control_loop_order,
+            graph_view_point_num_limit: preferences.default_param_tuner_graph_view_point_num_limit,
+            force_full_upload: preferences.param_tuner_force_full_upload,
+            preserve_local_edits: preferences.param_tuner_preserve_local_edits,
+            preview_interval: preview_interval.max(MIN_PREVIEW_INTERVAL),
+            pretty_json: preferences.param_tuner_pretty_json,
+            pwm_base_frequency: preferences.param_tuner_pwm_base_frequency,
+            feedback_outlier_limit: preferences.param_tuner_feedback_outlier_rejection.then(|| preferences.param_tuner_feedback_outlier_limit as f32),
+            export_point_limit: preferences.param_tuner_export_downsampling.then(|| preferences.param_tuner_export_point_num as usize),
+            auto_reconnect: preferences.slave_reconnect_attempts > 0,
+            keep_history_on_reconnect: preferences.param_tuner_keep_history_on_reconnect,
+            exclusive_tcp_activity,
             ..Default::default()
         }
     }
-}
 
-#[micro_widget(pub)]
-impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
-    view! {
-        window = PreferencesWindow {
-            set_destroy_with_parent: true,
-            set_modal: true,
-            set_search_enabled: false,
-            add = &PreferencesPage {
-                set_title: "推进器",
-                set_icon_name: Some("weather-windy-symbolic"),
-                set_hexpand: true,
-                set_vexpand: true,
-                set_can_focus: false,
-                add: group_pwm = &PreferencesGroup {
-                    set_title: "PWM 控制器",
-                    add = &FlowBox {
-                        set_activate_on_single_click: false,
-                        set_valign: Align::Start,
-                        set_row_spacing: 12,
-                        set_selection_mode: SelectionMode::None,
-                        insert(-1) = &PreferencesGroup {
-                            add = &ActionRow {
-                                set_title: "频率校准",
-                                add_suffix = &SpinButton::with_range(-0.1, 0.1, 0.0001) {
-                                    set_value: track!(model.changed(SlaveParameterTunerModel::propeller_pwm_frequency_calibration()), *model.get_propeller_pwm_frequency_calibration() as f64),
-                                    set_digits: 4,
-                                    set_valign: Align::Center,
-                                    connect_value_changed(sender) => move |button| {
-                                        send!(sender, SlaveParameterTunerMsg::SetPropellerPwmFreqCalibration(button.value()));
-                                    }
-                                },
-                            },
-                            add = &ActionRow {
-                                set_child = Some(&Scale::with_range(Orientation::Horizontal, -0.1, 0.1, 0.0001)) {
-                                    set_width_request: CARD_MIN_WIDTH,
-                                    set_round_digits: 4,
-                                    set_value: track!(model.changed(SlaveParameterTunerModel::propeller_pwm_frequency_calibration()), *model.get_propeller_pwm_frequency_calibration() as f64),
-                                    connect_value_changed(sender) => move |scale| {
-                                        send!(sender, SlaveParameterTunerMsg::SetPropellerPwmFreqCalibration(scale.value()));
-                                    }
-                                }
-                            },
-                        },
-                    },
-                },
-                add: group_propeller = &PreferencesGroup {
-                    set_title: "推进器参数",
-                    add = &FlowBox {
-                        set_activate_on_single_click: false,
-                        set_valign: Align::Start,
-                        set_row_spacing: 12,
-                        set_selection_mode: SelectionMode::None,
-                        factory!(model.propellers)
-                    },
-                },
+    /// 生成需要写入下位机的数据包，在下位机参数已知且未强制完整写入时仅包含发生变化的推进器与控制环，返回 `None` 表示无需写入
+    fn to_upload_packet(&self) -> Option<JsonValue> {
+        let packet = self.to_packet();
+        match self.get_device_parameters() {
+            Some(device_parameters) if !*self.get_force_full_upload() => json_diff(&serde_json::to_value(device_parameters).unwrap(), &serde_json::to_value(&packet).unwrap(), UPLOAD_DIFF_DEPTH),
+            _ => Some(serde_json::to_value(&packet).unwrap()),
+        }
+    }
+
+    /// 本地存在尚未写入下位机的修改，且与收到的下位机参数不一致时返回两者不同的字段
+    fn conflicting_fields(&self, parameters: &SlaveParameterTunerPacket) -> Vec<(String, JsonValue, JsonValue)> {
+        let local = serde_json::to_value(&self.to_packet()).unwrap();
+        match self.get_device_parameters() {
+            Some(device_parameters) if serde_json::to_value(device_parameters).unwrap() != local => {
+                let mut fields = Vec::new();
+                json_diff_fields(&local, &serde_json::to_value(parameters).unwrap(), String::new(), &mut fields);
+                fields
             },
-            add = &PreferencesPage {
+            _ => Vec::new(),
+        }
+    }
+
+    fn load_packet(&mut self, packet: SlaveParameterTunerPacket) {
+        self.set_propeller_pwm_frequency_calibration(packet.set_propeller_pwm_freq_calibration);
+        load_parameters(&mut self.propellers, &mut self.control_loops, &packet);
+    }
+
+    fn to_packet(&self) -> SlaveParameterTunerPacket {
+        SlaveParameterTunerPacket {
+            set_propeller_pwm_freq_calibration: self.propeller_pwm_frequency_calibration,
+            set_propeller_parameters: PropellerModel::vec_to_map(self.propellers.iter().collect()),
+            set_control_loop_parameters: ControlLoopModel::vec_to_map(self.control_loops.iter().collect()),
+            control_loop_feedback_display: HashMap::new(),
+            pwm_base_frequency: None,
+        }
+    }
+
+    /// 频率校准对应的百分比与实际 PWM 频率，仅用于显示，不改变写入的校准值
+    fn pwm_frequency_description(&self) -> String {
+        let (base, source) = match self.device_pwm_base_frequency {
+            Some(frequency) => (frequency, "下位机报告"),
+            None => (self.pwm_base_frequency, "首选项"),
+        };
+        format!("实际频率约 {:.2} Hz（基准 {:.0} Hz，来自{}）", base * (1.0 + self.propeller_pwm_frequency_calibration), base, source)
+    }
+
+    /// 应用下位机声明的反馈显示方式，未声明或声明无效的控制环使用默认值
+    fn load_feedback_display(&mut self, displays: HashMap<String, ControlLoopFeedbackDisplay>) {
+        apply_feedback_display(&mut self.control_loops, &displays);
+    }
+
+    /// 清除截止时间早于 `before` 的预览指示，为 `None` 时全部清除；正常情况下推进器归零时指示即被清除，这里用于连接中断等未能归零的情况
+    fn clear_propeller_previews(&mut self, before: Option<u128>) {
+        for index in 0..self.propellers.len() {
+            let propeller_model = self.propellers.get_mut(index).unwrap();
+            if propeller_model.previewing_until.map_or(false, |until| before.map_or(true, |before| until < before)) {
+                propeller_model.reset();
+                propeller_model.set_previewing_until(None);
+            }
+        }
+    }
+
+    /// 拒绝 NaN 并将无穷大限制在有效范围内，返回 `None` 时应忽略该值
+    fn checked_finite(&mut self, name: &str, value: f64, range: (f64, f64)) -> Option<f64> {
+        let checked = finite_parameter(value, range);
+        if checked != Some(value) {
+            let message = match checked {
+                Some(checked) => format!("{}的取值 {} 无效，已限制为 {}", name, value, checked),
+                None => format!("{}的取值 {} 无效，已忽略", name, value),
+            };
+            log::warn!("{}", message);
+            self.get_mut_toast_messages().borrow_mut().push_back(message);
+        }
+        checked
+    }
+
+    /// 修正来自下位机或参数文件中的非有限值，避免其被序列化为无效的 JSON 数值
+    fn sanitize_packet(&mut self, packet: &mut SlaveParameterTunerPacket, source: &str) {
+        let fields = packet.sanitize(&self.propeller_layout);
+        if !fields.is_empty() {
+            log::warn!("{}的参数包含非有限值，已修正：{}", source, fields.join("，"));
+            self.get_mut_toast_messages().borrow_mut().push_back(format!("{}的参数包含无效数值，已修正 {} 项", source, fields.len()));
+        }
+    }
+
+    fn checked_deadzone(&mut self, value: f64) -> i8 {
+        let (deadzone, clamped) = PropellerModel::deadzone_from_value(value);
+        if clamped {
+            log::warn!("死区值 {} 超出范围，已限制为 {}", value, deadzone);
+            self.get_mut_toast_messages().borrow_mut().push_back(format!("死区值 {} 超出范围，已限制为 {}", value, deadzone));
+        }
+        deadzone
+    }
+
+    /// 交换两个控制环卡片的位置并保存顺序，卡片按新的序号重新生成，数据保留在模型中
+    fn swap_control_loops(&mut self, index: usize, target: usize) {
+        if index.max(target) >= self.control_loops.len() {
+            return;
+        }
+        let mut control_loops: Vec<ControlLoopModel> = self.control_loops.iter().cloned().collect();
+        control_loops.swap(index, target);
+        self.control_loop_order.update(control_loops.iter().map(|pids| pids.get_key().clone()).collect());
+        if let Err(err) = self.control_loop_order.save() {
+            log::warn!("无法保存控制环的显示顺序：{}", err);
+        }
+        let factory = self.get_mut_control_loops();
+        factory.clear();
+        for pids in control_loops {
+            factory.push(pids);
+        }
+    }
+
+    /// 提示连接中断、写入失败等关键事件，窗口不在前台时同时在机位主界面提示，避免在后台发生的错误无人察觉
+    fn notify_critical(&mut self, parent_sender: &Sender<SlaveMsg>, message: String) {
+        if !self.window_active {
+            send!(parent_sender, SlaveMsg::ShowToastMessage(format!("参数调校：{}", message)));
+        }
+        self.get_mut_toast_messages().borrow_mut().push_back(message);
+    }
+
+    /// 结束当前的调试会话并清除会话中的临时状态，不关闭窗口
+    fn end_debug_session(&mut self, parent_sender: &Sender<SlaveMsg>) {
+        self.set_tcp_msg_sender(None);
+        self.clear_propeller_previews(None);
+        self.set_link_congested(false);
+        self.set_latency(None);
+        send!(parent_sender, SlaveMsg::ParameterTunerFeedbacksUpdated(None));
+    }
+
+    fn refresh_feedback_rate(&mut self) {
+        while self.feedback_timestamps.front().map_or(false, |instant| instant.elapsed() > Duration::from_secs(1)) {
+            self.feedback_timestamps.pop_front();
+        }
+        self.set_feedback_rate(self.feedback_timestamps.len());
+    }
+
+    fn feedback_rate_description(&self) -> String {
+        let rate = *self.get_feedback_rate();
+        let connected = *self.get_connection_state() == ConnectionState::Connected;
+        let description = if connected && rate < FEEDBACK_RATE_WARNING_THRESHOLD {
+            format!("⚠ 反馈频率：{} Hz，低于 {} Hz，请检查连接", rate, FEEDBACK_RATE_WARNING_THRESHOLD)
+        } else {
+            format!("反馈频率：{} Hz", rate)
+        };
+        match self.get_latency() {
+            Some(latency) if connected => format!("{}，延迟：{}", description, latency.to_string()),
+            _ => description,
+        }
+    }
+
+    fn notes_text(&self) -> String {
+        let (start, end) = self.notes.bounds();
+        self.notes.text(&start, &end, false).to_string()
+    }
+
+    fn export_metadata(&self) -> SlaveParameterTunerExportMetadata {
+        SlaveParameterTunerExportMetadata {
+            notes: self.notes_text(),
+            export_time: DateTime::now_local().unwrap().format_iso8601().unwrap().to_string(),
+            slave_address: self.get_slave_address().clone(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// 图表中的反馈记录，每行一个采样点，数值同时给出原始值与按显示方式换算后的值；
+    /// 启用异常值过滤时导出包括异常值在内的全部原始记录，`outlier` 列标记未绘制的异常值。
+    /// 导出精度与图表的显示精度无关，启用降采样时与图表相同，取每个区间原始值的最小值与最大值，`sample` 列保留原始序号
+    fn feedbacks_csv(&self) -> String {
+        let mut csv = match self.export_point_limit {
+            Some(limit) => format!("# 降采样：每个控制环最多 {} 个采样点，将记录等分为 {} 个区间，保留各区间原始值的最小值与最大值\n", limit, (limit / 2).max(1)),
+            None => String::from("# 全分辨率：包含全部采样点\n"),
+        };
+        csv.push_str("control_loop,sample,raw,value,unit,outlier\n");
+        for control_loop_model in self.control_loops.iter() {
+            let display = control_loop_model.get_feedback_display();
+            let feedbacks: Vec<f32> = (if self.feedback_outlier_limit.is_some() { control_loop_model.get_raw_feedbacks() } else { control_loop_model.get_feedbacks() }).iter().copied().collect();
+            let samples: Vec<usize> = match self.export_point_limit {
+                Some(limit) if feedbacks.len() > limit => decimate_indices(&feedbacks, (limit / 2).max(1)),
+                _ => (0..feedbacks.len()).collect(),
+            };
+            for sample in samples {
+                let raw = feedbacks[sample];
+                csv.push_str(&format!("{},{},{},{},{},{}\n", control_loop_model.get_key(), sample, raw, control_loop_model.displayed_feedback(raw) * display.scale, display.unit, !self.is_feedback_plausible(raw)));
+            }
+        }
+        csv
+    }
+
+    /// 反馈值是否在合理范围内，未启用异常值过滤时总是返回 `true`
+    fn is_feedback_plausible(&self, value: f32) -> bool {
+        self.feedback_outlier_limit.map_or(true, |limit| value.is_finite() && value.abs() <= limit)
+    }
+
+    /// 各控制环保存的快照，每行一个采样点，数值已按显示方式换算
+    fn snapshots_csv(&self) -> String {
+        let mut csv = String::from("control_loop,snapshot,label,sample,value,unit\n");
+        for control_loop_model in self.control_loops.iter() {
+            let display = control_loop_model.get_feedback_display();
+            for (index, snapshot) in control_loop_model.get_snapshots().iter().enumerate() {
+                for (sample, &value) in snapshot.values.iter().enumerate() {
+                    csv.push_str(&format!("{},{},\"{}\",{},{},{}\n", control_loop_model.get_key(), index + 1, snapshot.label, sample, value * display.scale, display.unit));
+                }
+            }
+        }
+        csv
+    }
+
+    /// 比较处理消息前后的参数，将发生变化的字段加入修改记录；首次读取下位机参数时只记录一条汇总
+    fn record_parameter_changes(&mut self, before: &JsonValue, source: ParameterChangeSource, initial_load: bool) {
+        let mut fields = Vec::new();
+        json_diff_fields(before, &serde_json::to_value(&self.to_packet()).unwrap(), String::new(), &mut fields);
+        if fields.is_empty() {
+            return;
+        }
+        let instant = Instant::now();
+        let time = DateTime::now_local().ok().and_then(|time| time.format("%Y-%m-%d %H:%M:%S").ok()).map(|time| time.to_string()).unwrap_or_default();
+        let changes: Vec<_> = if initial_load {
+            vec![ParameterChange { instant, time, source, field: "全部参数".to_string(), old: JsonValue::Null, new: JsonValue::String(format!("已从下位机读取（{} 个字段与本地不同）", fields.len())) }]
+        } else {
+            fields.into_iter().map(|(pointer, old, new)| ParameterChange { instant, time: time.clone(), source, field: parameter_pointer_to_string(&pointer, &self.propeller_layout), old, new }).collect()
+        };
+        let log = self.get_mut_parameter_change_log();
+        for change in changes {
+            match log.back_mut() {
+                Some(last) if source == ParameterChangeSource::User && last.source == source && last.field == change.field && last.instant.elapsed() < PARAMETER_CHANGE_MERGE_INTERVAL => {
+                    last.instant = change.instant;
+                    last.time = change.time;
+                    last.new = change.new;
+                    if last.old == last.new { // 改回原值时不再保留记录
+                        log.pop_back();
+                    }
+                },
+                _ => {
+                    if log.len() >= PARAMETER_CHANGE_LOG_LIMIT {
+                        log.pop_front();
+                    }
+                    log.push_back(change);
+                },
+            }
+        }
+    }
+
+    /// 面板中显示的修改记录，最新的在前
+    fn parameter_change_log_text(&self) -> String {
+        if self.parameter_change_log.is_empty() {
+            return "本次会话尚未修改参数".to_string();
+        }
+        self.parameter_change_log.iter().rev().take(PARAMETER_CHANGE_LOG_DISPLAY_LIMIT).map(ParameterChange::to_string).collect::<Vec<_>>().join("\n")
+    }
+
+    fn parameter_change_log_csv(&self) -> String {
+        let mut csv = String::from("time,source,field,old,new\n");
+        for change in self.parameter_change_log.iter() {
+            csv.push_str(&format!("{},{},\"{}\",{},{}\n", change.time, change.source.to_string(), change.field, change.old, change.new));
+        }
+        csv
+    }
+
+    /// 在 `directory` 下新建以时间命名的目录，写入当前参数（可直接导入）、反馈记录与连接信息，返回新建的目录
+    fn write_report(&self, directory: &Path) -> Result<PathBuf, IOError> {
+        let timestamp = DateTime::now_local().ok().and_then(|time| time.format("%Y%m%d-%H%M%S").ok()).map(|time| time.to_string()).unwrap_or_default();
+        let directory = directory.join(format!("调参报告-{}", timestamp));
+        fs::create_dir_all(&directory)?;
+        let metadata = self.export_metadata();
+        let parameters = SlaveParameterTunerExportFile { metadata: Some(metadata.clone()), parameters: self.to_packet() };
+        fs::write(directory.join("parameters.json"), serde_json::to_string_pretty(&parameters).unwrap())?;
+        fs::write(directory.join("feedbacks.csv"), self.feedbacks_csv())?;
+        if self.control_loops.iter().any(|control_loop_model| !control_loop_model.get_snapshots().is_empty()) {
+            fs::write(directory.join("snapshots.csv"), self.snapshots_csv())?;
+        }
+        if !self.parameter_change_log.is_empty() {
+            fs::write(directory.join("changes.csv"), self.parameter_change_log_csv())?;
+        }
+        let report = SlaveParameterTunerReport {
+            metadata,
+            connection_state: self.get_connection_state().to_string(),
+            feedback_rate: *self.get_feedback_rate(),
+            link_congested: *self.get_link_congested(),
+            device_parameters: self.get_device_parameters().clone(),
+            feedback_display: self.control_loops.iter().map(|control_loop_model| (control_loop_model.get_key().clone(), control_loop_model.get_feedback_display().clone())).collect(),
+            propeller_telemetry: self.propellers.iter().filter_map(|propeller_model| propeller_model.telemetry.clone().map(|telemetry| (propeller_model.get_key().clone(), telemetry))).collect(),
+        };
+        fs::write(directory.join("report.json"), serde_json::to_string_pretty(&report).unwrap())?;
+        Ok(directory)
+    }
+}
+
+new_action_group!(ParameterTunerActionGroup, "param-tuner");
+new_stateless_action!(ImportParametersAction, ParameterTunerActionGroup, "import");
+new_stateless_action!(ExportParametersAction, ParameterTunerActionGroup, "export");
+new_stateless_action!(GenerateReportAction, ParameterTunerActionGroup, "report");
+new_stateless_action!(EditNotesAction, ParameterTunerActionGroup, "notes");
+new_stateless_action!(ImportPropellerLayoutAction, ParameterTunerActionGroup, "import-layout");
+new_stateless_action!(LoadAggressivePresetAction, ParameterTunerActionGroup, "preset-aggressive");
+new_stateless_action!(LoadStablePresetAction, ParameterTunerActionGroup, "preset-stable");
+new_stateless_action!(LoadBenchSafePresetAction, ParameterTunerActionGroup, "preset-bench-safe");
+
+fn parameter_file_filter() -> FileFilter {
+    let filter = FileFilter::new();
+    filter.add_suffix("json");
+    filter.set_name(Some("参数文件"));
+    filter
+}
+
+/// 列出本地修改与下位机参数冲突的字段，由用户逐项或整体选择保留哪一方
+fn parameter_conflict_dialog<T: IsA<gtk::Window>>(fields: Vec<(String, JsonValue, JsonValue)>, layout: &PropellerLayout, sender: Sender<SlaveParameterTunerMsg>, window: &T) -> Dialog {
+    let switches: Vec<(String, Switch)> = fields.iter().map(|(pointer, _, _)| (pointer.clone(), Switch::new())).collect();
+    relm4_macros::view! {
+        group = PreferencesGroup {
+            set_description: Some("下位机发送的参数与尚未保存的本地修改不一致，开启的项将采用下位机的参数"),
+        }
+    }
+    for ((pointer, local, device), (_, switch)) in fields.iter().zip(switches.iter()) {
+        relm4_macros::view! {
+            row = ActionRow {
+                set_title: &parameter_pointer_to_string(pointer, layout),
+                set_subtitle: &format!("本地：{}　下位机：{}", local, device),
+                add_suffix: switch,
+                set_activatable_widget: Some(switch),
+            }
+        }
+        switch.set_valign(Align::Center);
+        group.add(&row);
+    }
+    relm4_macros::view! {
+        dialog = Dialog {
+            set_title: Some("参数冲突"),
+            set_modal: true,
+            set_transient_for: Some(window),
+            set_default_width: 400,
+            add_button: args!("保留本地", ResponseType::Reject),
+            add_button: args!("采用下位机", ResponseType::Accept),
+            add_button: args!("应用所选", ResponseType::Apply),
+            connect_response => move |dialog, response| {
+                let pointers = match response {
+                    ResponseType::Accept => switches.iter().map(|(pointer, _)| pointer.clone()).collect(),
+                    ResponseType::Apply => switches.iter().filter(|(_, switch)| switch.is_active()).map(|(pointer, _)| pointer.clone()).collect(),
+                    _ => Vec::new(),
+                };
+                send!(sender, SlaveParameterTunerMsg::ResolveParameterConflict(pointers));
+                dialog.destroy();
+            }
+        }
+    }
+    relm4_macros::view! {
+        scrolled_window = ScrolledWindow {
+            set_vexpand: true,
+            set_min_content_height: 300,
+            set_child = Some(&Clamp) {
+                set_margin_top: 12,
+                set_margin_bottom: 12,
+                set_margin_start: 12,
+                set_margin_end: 12,
+                set_child: Some(&group),
+            },
+        }
+    }
+    dialog.content_area().append(&scrolled_window);
+    dialog.show();
+    dialog
+}
+
+fn notes_dialog<T: IsA<gtk::Window>>(buffer: &TextBuffer, window: &T) -> Dialog {
+    relm4_macros::view! {
+        dialog = Dialog {
+            set_title: Some("备注"),
+            set_modal: true,
+            set_transient_for: Some(window),
+            set_default_width: 400,
+            set_default_height: 300,
+            add_button: args!("完成", ResponseType::Close),
+            connect_response => |dialog, _response| {
+                dialog.destroy();
+            }
+        }
+    }
+    relm4_macros::view! {
+        scrolled_window = ScrolledWindow {
+            set_vexpand: true,
+            set_child = Some(&TextView::with_buffer(buffer)) {
+                set_wrap_mode: WrapMode::WordChar,
+                set_left_margin: 6,
+                set_right_margin: 6,
+            },
+        }
+    }
+    dialog.content_area().append(&scrolled_window);
+    dialog.show();
+    dialog
+}
+
+#[micro_widget(pub)]
+impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
+    view! {
+        window = PreferencesWindow {
+            set_destroy_with_parent: true,
+            set_modal: true,
+            set_search_enabled: false,
+            add = &PreferencesPage {
+                set_title: "推进器",
+                set_icon_name: Some("weather-windy-symbolic"),
+                set_hexpand: true,
+                set_vexpand: true,
+                set_can_focus: false,
+                add: group_pwm = &PreferencesGroup {
+                    set_title: "PWM 控制器",
+                    set_sensitive: track!(model.changed(SlaveParameterTunerModel::uploading()) || model.changed(SlaveParameterTunerModel::read_only()), !*model.get_uploading() && !*model.get_read_only()),
+                    add = &FlowBox {
+                        set_activate_on_single_click: false,
+                        set_valign: Align::Start,
+                        set_row_spacing: 12,
+                        set_selection_mode: SelectionMode::None,
+                        insert(-1) = &PreferencesGroup {
+                            add = &ActionRow {
+                                set_title: "频率校准",
+                                set_subtitle: track!(model.changed(SlaveParameterTunerModel::propeller_pwm_frequency_calibration()) || model.changed(SlaveParameterTunerModel::device_pwm_base_frequency()), &model.pwm_frequency_description()),
+                                set_tooltip_text: Some("PWM 输出频率的相对校准量，范围 -0.1 ~ 0.1（即 -10% ~ +10%），实际频率 = 基准频率 × (1 + 校准量)，用于补偿主控晶振误差，通常保持 0 即可"),
+                                add_suffix = &Label {
+                                    add_css_class: "dim-label",
+                                    set_width_chars: 8,
+                                    set_xalign: 1.0,
+                                    set_label: track!(model.changed(SlaveParameterTunerModel::propeller_pwm_frequency_calibration()), &format!("{:+.2} %", model.propeller_pwm_frequency_calibration * 100.0)),
+                                },
+                                add_suffix = &SpinButton::with_range(-0.1, 0.1, 0.0001) {
+                                    set_value: track!(model.changed(SlaveParameterTunerModel::propeller_pwm_frequency_calibration()), *model.get_propeller_pwm_frequency_calibration() as f64),
+                                    connect_input => |button| validate_spin_button_input(button),
+                                    set_digits: 4,
+                                    set_valign: Align::Center,
+                                    connect_value_changed(sender) => move |button| {
+                                        send!(sender, SlaveParameterTunerMsg::SetPropellerPwmFreqCalibration(button.value()));
+                                    }
+                                },
+                            },
+                            add = &ActionRow {
+                                set_child = Some(&Scale::with_range(Orientation::Horizontal, -0.1, 0.1, 0.0001)) {
+                                    set_width_request: CARD_MIN_WIDTH,
+                                    set_round_digits: 4,
+                                    set_value: track!(model.changed(SlaveParameterTunerModel::propeller_pwm_frequency_calibration()), *model.get_propeller_pwm_frequency_calibration() as f64),
+                                    connect_value_changed_debounced(sender) => move |scale| {
+                                        send!(sender, SlaveParameterTunerMsg::SetPropellerPwmFreqCalibration(scale.value()));
+                                    }
+                                }
+                            },
+                        },
+                    },
+                },
+                add = &PreferencesGroup {
+                    set_title: "预览",
+                    set_description: track!(model.changed(SlaveParameterTunerModel::preview_latched()), model.preview_latched.then(|| "⚠ 保持模式已开启：预览值不会自动归零，推进器将持续转动，请使用“全部停止”（空格键）使推进器归零")),
+                    add = &ActionRow {
+                        set_title: "保持预览值",
+                        set_subtitle: "关闭时推进器在最后一次预览的 1 秒后自动归零；开启后保持预览值直至手动停止，用于台架上的持续运行测试",
+                        add_suffix: preview_latched_switch = &Switch {
+                            set_valign: Align::Center,
+                            set_active: track!(model.changed(SlaveParameterTunerModel::preview_latched()), model.preview_latched),
+                            connect_state_set(sender) => move |_switch, state| {
+                                send!(sender, SlaveParameterTunerMsg::SetPreviewLatched(state));
+                                Inhibit(false)
+                            },
+                        },
+                        set_activatable_widget: Some(&preview_latched_switch),
+                    },
+                },
+                add: group_propeller = &PreferencesGroup {
+                    set_title: "推进器参数",
+                    set_description: track!(model.changed(SlaveParameterTunerModel::link_congested()) || model.changed(SlaveParameterTunerModel::preview_latched()), match (model.link_congested, model.preview_latched) {
+                        (true, _) => Some("⚠ 链路拥塞，部分预览值未能及时发送，推进器响应可能滞后"),
+                        (false, true) => Some("⚠ 保持模式：调整参数后推进器将持续转动"),
+                        (false, false) => None,
+                    }),
+                    set_sensitive: track!(model.changed(SlaveParameterTunerModel::uploading()) || model.changed(SlaveParameterTunerModel::read_only()), !*model.get_uploading() && !*model.get_read_only()),
+                    add = &FlowBox {
+                        set_activate_on_single_click: false,
+                        set_valign: Align::Start,
+                        set_row_spacing: 12,
+                        set_selection_mode: SelectionMode::None,
+                        factory!(model.propellers)
+                    },
+                },
+            },
+            add = &PreferencesPage {
                 set_title: "控制环",
                 set_icon_name: Some("media-playlist-repeat-symbolic"),
                 set_hexpand: true,
@@ -496,6 +1584,8 @@ impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
                 set_can_focus: false,
                 add: group_pid = &PreferencesGroup {
                     set_title: "PID 参数",
+                    set_sensitive: track!(model.changed(SlaveParameterTunerModel::uploading()) || model.changed(SlaveParameterTunerModel::read_only()), !*model.get_uploading() && !*model.get_read_only()),
+                    set_description: track!(model.changed(SlaveParameterTunerModel::feedback_rate()) || model.changed(SlaveParameterTunerModel::connection_state()) || model.changed(SlaveParameterTunerModel::latency()), Some(&model.feedback_rate_description())),
                     add = &FlowBox {
                         set_activate_on_single_click: false,
                         set_valign: Align::Start,
@@ -505,14 +1595,61 @@ impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
                     },
                 },
             },
-            set_title: {
-                Some("参数调校")
+            add = &PreferencesPage {
+                set_title: "修改记录",
+                set_icon_name: Some("document-open-recent-symbolic"),
+                set_hexpand: true,
+                set_vexpand: true,
+                set_can_focus: false,
+                add = &PreferencesGroup {
+                    set_title: "本次会话",
+                    set_description: Some("记录本次打开调参窗口以来每个参数的修改，包括用户的修改、从下位机读取与导入的参数，关闭窗口后清空"),
+                    add = &ExpanderRow {
+                        set_title: "参数修改记录",
+                        set_subtitle: track!(model.changed(SlaveParameterTunerModel::parameter_change_log()), &format!("共 {} 条", model.parameter_change_log.len())),
+                        add_action = &Button {
+                            set_label: "导出",
+                            set_valign: Align::Center,
+                            set_sensitive: track!(model.changed(SlaveParameterTunerModel::parameter_change_log()), !model.parameter_change_log.is_empty()),
+                            connect_clicked(sender, window) => move |_button| {
+                                let filter = FileFilter::new();
+                                filter.add_suffix("csv");
+                                filter.set_name(Some("CSV 文件"));
+                                select_path(FileChooserAction::Save, &[filter], None, &window, clone!(@strong sender => move |path| {
+                                    if let Some(path) = path {
+                                        send!(sender, SlaveParameterTunerMsg::ExportChangeLog(path.with_extension("csv")));
+                                    }
+                                }));
+                            },
+                        },
+                        add_row = &Label {
+                            add_css_class: "monospace",
+                            set_xalign: 0.0,
+                            set_selectable: true,
+                            set_wrap: true,
+                            set_margin_top: 12,
+                            set_margin_bottom: 12,
+                            set_margin_start: 12,
+                            set_margin_end: 12,
+                            set_label: track!(model.changed(SlaveParameterTunerModel::parameter_change_log()), &model.parameter_change_log_text()),
+                        },
+                    },
+                },
             },
+            set_title: track!(model.changed(SlaveParameterTunerModel::connection_state()) || model.changed(SlaveParameterTunerModel::loaded_preset()) || model.changed(SlaveParameterTunerModel::read_only()), Some(&match (model.get_loaded_preset(), model.read_only) {
+                (_, true) => format!("参数调校（{}，只读）", model.get_connection_state().to_string()),
+                (Some(preset), false) => format!("参数调校（{}，预设“{}”尚未保存）", model.get_connection_state().to_string(), preset),
+                (None, false) => format!("参数调校（{}）", model.get_connection_state().to_string()),
+            })),
+            add_toast?: watch!(model.get_toast_messages().borrow_mut().pop_front().map(|x| Toast::new(&x)).as_ref()),
             set_destroy: track!(model.changed(SlaveParameterTunerModel::stopped()), *model.get_stopped()),
             connect_close_request(sender) => move |_window| {
                 send!(sender, SlaveParameterTunerMsg::StopDebug);
                 Inhibit(false)
             },
+            connect_is_active_notify(sender) => move |window| {
+                send!(sender, SlaveParameterTunerMsg::SetWindowActive(window.is_active()));
+            },
         }
     }
     fn post_init() {
@@ -527,7 +1664,7 @@ impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
         let header_bar: HeaderBar = root_box.first_child().unwrap().dynamic_cast().unwrap();
         relm4_macros::view! {
             HeaderBar::from(header_bar) {
-                pack_start = &Button {
+                pack_start: apply_button = &Button {
                     set_css_classes: &["suggested-action"],
                     set_halign: Align::Center,
                     set_child = Some(&GtkBox) {
@@ -543,7 +1680,24 @@ impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
                         send!(sender, SlaveParameterTunerMsg::ApplyParameters);
                     },
                 },
-                pack_end = &Button {
+                pack_start = &Button {
+                    set_css_classes: &["destructive-action"],
+                    set_halign: Align::Center,
+                    set_tooltip_text: Some("立即停止全部推进器（空格键）"),
+                    set_child = Some(&GtkBox) {
+                        set_spacing: 6,
+                        append = &Image {
+                            set_icon_name: Some("media-playback-stop-symbolic"),
+                        },
+                        append = &Label {
+                            set_label: "全部停止",
+                        },
+                    },
+                    connect_clicked(sender) => move |_button| {
+                        send!(sender, SlaveParameterTunerMsg::StopAllPropellers);
+                    },
+                },
+                pack_end: reset_button = &Button {
                     set_css_classes: &["destructive-action"],
                     set_halign: Align::Center,
                     set_child = Some(&GtkBox) {
@@ -559,6 +1713,115 @@ impl MicroWidgets<SlaveParameterTunerModel> for SlaveParameterTunerWidgets {
                         send!(sender, SlaveParameterTunerMsg::ResetParameters);
                     },
                 },
+                pack_end: read_only_button = &ToggleButton {
+                    set_icon_name: "changes-prevent-symbolic",
+                    set_tooltip_text: Some("只读模式：仅查看下位机的参数与反馈，禁止修改与写入，适用于培训或监督"),
+                    set_valign: Align::Center,
+                    connect_active_notify(sender) => move |button| {
+                        send!(sender, SlaveParameterTunerMsg::SetReadOnly(button.is_active()));
+                    },
+                },
+                pack_end = &MenuButton {
+                    set_menu_model: Some(&parameter_tuner_menu),
+                    set_icon_name: "open-menu-symbolic",
+                    set_focus_on_click: false,
+                    set_valign: Align::Center,
+                },
+            }
+        }
+        let shortcut_controller = ShortcutController::new();
+        shortcut_controller.set_propagation_phase(PropagationPhase::Capture); // 先于获得焦点的控件处理，拖动滑块时同样有效
+        shortcut_controller.add_shortcut(&Shortcut::new(ShortcutTrigger::parse_string("space").as_ref(), Some(&CallbackAction::new(clone!(@strong sender => move |_, _| {
+            send!(sender, SlaveParameterTunerMsg::StopAllPropellers);
+            true
+        })))));
+        window.add_controller(&shortcut_controller);
+        let action_group = RelmActionGroup::<ParameterTunerActionGroup>::new();
+        let action_import: RelmAction<ImportParametersAction> = RelmAction::new_stateless(clone!(@strong sender, @weak window => move |_| {
+            select_path(FileChooserAction::Open, &[parameter_file_filter()], None, &window, clone!(@strong sender => move |path| {
+                if let Some(path) = path {
+                    send!(sender, SlaveParameterTunerMsg::ImportParameters(path));
+                }
+            }));
+        }));
+        let action_export: RelmAction<ExportParametersAction> = RelmAction::new_stateless(clone!(@strong sender, @weak window => move |_| {
+            select_path(FileChooserAction::Save, &[parameter_file_filter()], None, &window, clone!(@strong sender => move |path| {
+                if let Some(path) = path {
+                    send!(sender, SlaveParameterTunerMsg::ExportParameters(path.with_extension("json")));
+                }
+            }));
+        }));
+        let action_report: RelmAction<GenerateReportAction> = RelmAction::new_stateless(clone!(@strong sender, @weak window => move |_| {
+            select_path(FileChooserAction::SelectFolder, &[], None, &window, clone!(@strong sender => move |path| {
+                if let Some(path) = path {
+                    send!(sender, SlaveParameterTunerMsg::GenerateReport(path));
+                }
+            }));
+        }));
+        let action_import_layout: RelmAction<ImportPropellerLayoutAction> = RelmAction::new_stateless(clone!(@strong sender, @weak window => move |_| {
+            select_path(FileChooserAction::Open, &[parameter_file_filter()], None, &window, clone!(@strong sender => move |path| {
+                if let Some(path) = path {
+                    send!(sender, SlaveParameterTunerMsg::ImportPropellerLayout(path));
+                }
+            }));
+        }));
+        let notes = model.get_notes().clone();
+        let action_notes: RelmAction<EditNotesAction> = RelmAction::new_stateless(clone!(@weak window => move |_| {
+            notes_dialog(&notes, &window);
+        }));
+        action_group.add_action(action_import);
+        action_group.add_action(action_export);
+        action_group.add_action(action_report);
+        action_group.add_action(action_notes);
+        action_group.add_action(action_import_layout);
+        let action_preset_aggressive: RelmAction<LoadAggressivePresetAction> = RelmAction::new_stateless(clone!(@strong sender => move |_| {
+            send!(sender, SlaveParameterTunerMsg::LoadPreset(0));
+        }));
+        let action_preset_stable: RelmAction<LoadStablePresetAction> = RelmAction::new_stateless(clone!(@strong sender => move |_| {
+            send!(sender, SlaveParameterTunerMsg::LoadPreset(1));
+        }));
+        let action_preset_bench_safe: RelmAction<LoadBenchSafePresetAction> = RelmAction::new_stateless(clone!(@strong sender => move |_| {
+            send!(sender, SlaveParameterTunerMsg::LoadPreset(2));
+        }));
+        action_group.add_action(action_preset_aggressive);
+        action_group.add_action(action_preset_stable);
+        action_group.add_action(action_preset_bench_safe);
+        window.insert_action_group("param-tuner", Some(&action_group.into_action_group()));
+    }
+
+    additional_fields! {
+        apply_button: Button,
+        reset_button: Button,
+        read_only_button: ToggleButton,
+    }
+
+    menu! {
+        parameter_tuner_menu: {
+            "导入参数" => ImportParametersAction,
+            "导出参数" => ExportParametersAction,
+            "生成调参报告" => GenerateReportAction,
+            "备注"     => EditNotesAction,
+            "导入推进器布局" => ImportPropellerLayoutAction,
+            "预设：激进"     => LoadAggressivePresetAction,
+            "预设：稳定"     => LoadStablePresetAction,
+            "预设：台架安全" => LoadBenchSafePresetAction,
+        }
+    }
+
+    fn post_view() {
+        if model.changed(SlaveParameterTunerModel::uploading()) || model.changed(SlaveParameterTunerModel::read_only()) {
+            self.apply_button.set_sensitive(!*model.get_uploading() && !*model.get_read_only());
+            self.reset_button.set_sensitive(!*model.get_uploading());
+            self.read_only_button.set_active(*model.get_read_only());
+        }
+        if model.changed(SlaveParameterTunerModel::imported_metadata()) {
+            if let Some(metadata) = model.get_imported_metadata() {
+                info_message("导入参数", &metadata.to_string(), Some(&self.window));
+            }
+        }
+        if model.changed(SlaveParameterTunerModel::pending_device_parameters()) {
+            if let Some(parameters) = model.get_pending_device_parameters() {
+                parameter_conflict_dialog(model.conflicting_fields(parameters), model.get_propeller_layout(), sender.clone(), &self.window);
             }
         }
     }
@@ -601,6 +1864,186 @@ pub struct SlaveParameterTunerPacket {
     set_propeller_pwm_freq_calibration: f64,
     set_propeller_parameters: HashMap<String, Propeller>,
     set_control_loop_parameters: HashMap<String, ControlLoop>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    control_loop_feedback_display: HashMap<String, ControlLoopFeedbackDisplay>, // 仅由下位机发送，不属于可调参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pwm_base_frequency: Option<f64>, // 仅由下位机发送，未校准时的 PWM 输出频率（Hz）
+}
+
+/// 将参数包中推进器与控制环的参数写入对应的模型，参数包中没有的推进器与控制环保持不变；不涉及窗口，载入前应先修正非有限值
+fn load_parameters(propellers: &mut FactoryVec<PropellerModel>, control_loops: &mut FactoryVec<ControlLoopModel>, packet: &SlaveParameterTunerPacket) {
+    for index in 0..propellers.len() {
+        let propeller_model = propellers.get_mut(index).unwrap();
+        if let Some(propeller) = packet.set_propeller_parameters.get(propeller_model.get_key()) {
+            propeller_model.set_deadzone_lower(propeller.deadzone_lower.min(propeller.deadzone_upper));
+            propeller_model.set_deadzone_upper(propeller.deadzone_upper.max(propeller.deadzone_lower));
+            propeller_model.set_power_positive(propeller.power_positive);
+            propeller_model.set_power_negative(propeller.power_negative);
+            propeller_model.set_power_linked(propeller.power_positive == propeller.power_negative); // 读取到不对称的参数时取消联动，避免被单个滑块覆盖
+            propeller_model.set_reversed(propeller.reversed);
+            propeller_model.set_enabled(propeller.enabled);
+        }
+    }
+    for index in 0..control_loops.len() {
+        let control_loop_model = control_loops.get_mut(index).unwrap();
+        if let Some(control_loop) = packet.set_control_loop_parameters.get(control_loop_model.get_key()) {
+            control_loop_model.set_p(control_loop.p);
+            control_loop_model.set_i(control_loop.i);
+            control_loop_model.set_d(control_loop.d);
+            control_loop_model.set_enabled(control_loop.enabled);
+            if let Some(bias) = control_loop.bias {
+                control_loop_model.set_bias(bias);
+                control_loop_model.set_bias_on_device(true);
+            }
+        }
+    }
+}
+
+/// 将下位机声明的反馈显示方式应用到各控制环，未声明或声明无效的控制环使用默认值
+fn apply_feedback_display(control_loops: &mut FactoryVec<ControlLoopModel>, displays: &HashMap<String, ControlLoopFeedbackDisplay>) {
+    for index in 0..control_loops.len() {
+        let control_loop_model = control_loops.get_mut(index).unwrap();
+        let display = match displays.get(control_loop_model.get_key()) {
+            Some(display) if display.is_valid() => display.clone(),
+            Some(display) => {
+                log::warn!("下位机声明的{}反馈显示方式无效：{:?}", ControlLoopModel::key_to_string(control_loop_model.get_key()), display);
+                ControlLoopModel::default_feedback_display(control_loop_model.get_key())
+            },
+            None => ControlLoopModel::default_feedback_display(control_loop_model.get_key()),
+        };
+        if *control_loop_model.get_feedback_display() != display {
+            control_loop_model.set_feedback_display(display);
+        }
+    }
+}
+
+/// 有限值原样返回，无穷大限制在有效范围内，NaN 无法确定取值，返回 `None`
+fn finite_parameter(value: f64, (lower, upper): (f64, f64)) -> Option<f64> {
+    if value.is_finite() {
+        Some(value)
+    } else if value.is_nan() {
+        None
+    } else {
+        Some(value.clamp(lower, upper))
+    }
+}
+
+impl SlaveParameterTunerPacket {
+    /// 将非有限的参数替换为有效值（NaN 替换为默认值），返回被修正的字段说明
+    fn sanitize(&mut self, layout: &PropellerLayout) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut check = |name: String, value: &mut f64, range: (f64, f64), default: f64| {
+            if !value.is_finite() {
+                let sanitized = finite_parameter(*value, range).unwrap_or(default);
+                fields.push(format!("{}（{} → {}）", name, value, sanitized));
+                *value = sanitized;
+            }
+        };
+        check("PWM 频率校准".to_string(), &mut self.set_propeller_pwm_freq_calibration, PWM_FREQ_CALIBRATION_RANGE, 0.0);
+        for (key, propeller) in self.set_propeller_parameters.iter_mut() {
+            let name = PropellerModel::key_to_string(key, layout);
+            check(format!("{} · 正向动力", name), &mut propeller.power_positive, POWER_RANGE, 0.75);
+            check(format!("{} · 反向动力", name), &mut propeller.power_negative, POWER_RANGE, 0.75);
+        }
+        for (key, control_loop) in self.set_control_loop_parameters.iter_mut() {
+            let name = ControlLoopModel::key_to_string(key);
+            check(format!("{} · P", name), &mut control_loop.p, PID_RANGE, 1.0);
+            check(format!("{} · I", name), &mut control_loop.i, PID_RANGE, 1.0);
+            check(format!("{} · D", name), &mut control_loop.d, PID_RANGE, 1.0);
+            if let Some(bias) = control_loop.bias.as_mut() {
+                check(format!("{} · 零点偏置", name), bias, FEEDBACK_BIAS_RANGE, 0.0);
+            }
+        }
+        fields
+    }
+}
+
+/// 逐个列出两份 JSON 参数中取值不同的字段，返回 (JSON Pointer, 旧值, 新值)
+fn json_diff_fields(old: &JsonValue, new: &JsonValue, pointer: String, fields: &mut Vec<(String, JsonValue, JsonValue)>) {
+    match (old, new) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            for (key, value) in new {
+                let pointer = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                json_diff_fields(old.get(key).unwrap_or(&JsonValue::Null), value, pointer, fields);
+            }
+        },
+        (old, new) if old == new => (),
+        (old, new) => fields.push((pointer, old.clone(), new.clone())),
+    }
+}
+
+/// 将参数字段的 JSON Pointer 转换为便于阅读的名称，如“左前 · 正向动力”
+fn parameter_pointer_to_string(pointer: &str, layout: &PropellerLayout) -> String {
+    pointer.split('/').skip(1).filter_map(|segment| match segment {
+        "set_propeller_parameters" | "set_control_loop_parameters" => None,
+        "set_propeller_pwm_freq_calibration" => Some("PWM 频率校准"),
+        "deadzone_lower" => Some("死区下限"),
+        "deadzone_upper" => Some("死区上限"),
+        "power_positive" => Some("正向动力"),
+        "power_negative" => Some("反向动力"),
+        "reversed" => Some("反转"),
+        "enabled" => Some("启用"),
+        "p" => Some("P"),
+        "i" => Some("I"),
+        "d" => Some("D"),
+        key => Some(ControlLoopModel::key_to_string(PropellerModel::key_to_string(key, layout))),
+    }).collect::<Vec<_>>().join(" · ")
+}
+
+/// 比较两份 JSON 参数，仅保留新参数中与旧参数不同的字段，完全相同时返回 `None`；
+/// 对象只向下比较 `depth` 层，更深的对象与其余类型一样整体比较，有任何字段不同时发送整个对象
+fn json_diff(old: &JsonValue, new: &JsonValue, depth: usize) -> Option<JsonValue> {
+    match (old, new) {
+        (JsonValue::Object(old), JsonValue::Object(new)) if depth > 0 => {
+            let diff: serde_json::Map<String, JsonValue> = new.iter()
+                .filter_map(|(key, value)| match old.get(key) {
+                    Some(old_value) => json_diff(old_value, value, depth - 1),
+                    None => Some(value.clone()),
+                }.map(|value| (key.clone(), value)))
+                .collect();
+            if diff.is_empty() { None } else { Some(JsonValue::Object(diff)) }
+        },
+        (old, new) if old == new => None,
+        (_, new) => Some(new.clone()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveParameterTunerExportMetadata {
+    notes: String,
+    export_time: String,
+    slave_address: Option<String>,
+    app_version: String,
+}
+
+impl ToString for SlaveParameterTunerExportMetadata {
+    fn to_string(&self) -> String {
+        format!("导出时间：{}\n下位机地址：{}\n上位机版本：{}\n\n{}",
+                self.export_time,
+                self.slave_address.as_deref().unwrap_or("未知"),
+                self.app_version,
+                if self.notes.is_empty() { "无备注" } else { &self.notes })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct SlaveParameterTunerExportFile {
+    #[serde(default)]
+    metadata: Option<SlaveParameterTunerExportMetadata>, // 仅保存在文件中，不会发送至下位机
+    #[serde(flatten)]
+    parameters: SlaveParameterTunerPacket,
+}
+
+/// 调参报告中的连接与状态信息，便于远程排查问题
+#[derive(Debug, Clone, Serialize)]
+struct SlaveParameterTunerReport {
+    metadata: SlaveParameterTunerExportMetadata,
+    connection_state: String,
+    feedback_rate: usize, // 单位：Hz
+    link_congested: bool,
+    device_parameters: Option<SlaveParameterTunerPacket>, // 最近一次从下位机接收或成功写入下位机的参数
+    feedback_display: HashMap<String, ControlLoopFeedbackDisplay>,
+    propeller_telemetry: HashMap<String, PropellerTelemetry>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -613,65 +2056,170 @@ pub struct SlaveParameterTunerFeedbackValuePacket {
     control_loops: HashMap<String, f32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveParameterTunerTelemetryPacket {
+    telemetry: SlaveParameterTunerTelemetryValuePacket,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SlaveParameterTunerTelemetryValuePacket {
+    propellers: HashMap<String, PropellerTelemetry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PropellerTelemetry {
+    #[serde(default)]
+    current: Option<f32>, // 单位：A
+    #[serde(default)]
+    temperature: Option<f32>, // 单位：°C
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct SlaveParameterTunerUpdatePacket {
     update_parameters: ()
 }
 
+/// 统计最近一段时间内的预览值中因链路拥塞而丢失的比例。
+///
+/// 预览值在等待发送时被新值覆盖属于正常的合并，仅当等待时间超过两个发送间隔（发送跟不上）时才计为丢失；
+/// 此外处理预览值时消息通道接近占满，说明界面发送的预览值可能已被丢弃，同样计为丢失。
+#[derive(Debug, Default)]
+struct PreviewCongestionMonitor {
+    samples: VecDeque<(Instant, bool)>, // (记录时间, 是否丢失)
+    congested: bool,
+}
+
+impl PreviewCongestionMonitor {
+    /// 记录一个预览值，拥塞状态发生变化时返回新的状态
+    fn record(&mut self, lost: bool) -> Option<bool> {
+        self.samples.push_back((Instant::now(), lost));
+        self.refresh()
+    }
+
+    /// 移除时间窗口之外的记录并重新判断拥塞状态，没有新的预览值时也需定期调用以便取消提示
+    fn refresh(&mut self) -> Option<bool> {
+        while self.samples.front().map_or(false, |(instant, _)| instant.elapsed() > LINK_CONGESTION_WINDOW) {
+            self.samples.pop_front();
+        }
+        let rate = if self.samples.len() < LINK_CONGESTION_MIN_SAMPLES {
+            0.0
+        } else {
+            self.samples.iter().filter(|(_, lost)| *lost).count() as f32 / self.samples.len() as f32
+        };
+        let congested = if self.congested { rate > LINK_CONGESTION_EXIT_RATE } else { rate > LINK_CONGESTION_ENTER_RATE };
+        if congested != self.congested {
+            self.congested = congested;
+            Some(congested)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug)]
 enum SlaveParameterTunerTcpMsg {
-    UploadParameters(SlaveParameterTunerPacket),
+    UploadParameters(JsonValue),
     RequestParameters,
     SetDebugModeEnabled(bool),
     PreviewPropeller(String, i8),
     PreviewPropellers(HashMap<String, i8>),
     PreviewControlLoop(String, ControlLoop),
     PreviewControlLoops(HashMap<String, ControlLoop>),
+    StopAllPropellers,
+    SetPreviewLatched(bool),
+    Ping,
     ConnectionLost(IOError),
     Terminate,
 }
 
+/// 序列化发往下位机的数据包，调试协议时可选择格式化输出，便于在抓包工具中阅读
+fn encode_packet<T: Serialize>(packet: &T, pretty: bool) -> String {
+    if pretty { serde_json::to_string_pretty(packet) } else { serde_json::to_string(packet) }.unwrap()
+}
+
+/// 一次调试会话的选项，在会话开始时由窗口的当前设置与下位机声明的功能确定
+#[derive(Debug, Clone)]
+struct ParameterTunerSessionOptions {
+    preview_interval: Duration,
+    pretty_json: bool,
+    propeller_keys: Vec<String>, // 停止预览时归零的推进器
+    ping: bool, // 下位机回复延迟测量数据包时定期测量延迟
+    framing: FrameFormat,
+}
+
+/// 开始调试会话：`tcp_msg_sender` 已有会话的通道时返回 `false` 且不做任何事，避免两个处理任务同时读写连接；
+/// 否则创建会话的通道并存入 `tcp_msg_sender`，写入开启调试模式等初始消息，由 `spawn` 启动处理任务，再将任务交给 `block` 独占连接
+fn start_debug_session(tcp_msg_sender: &mut Option<async_std::channel::Sender<SlaveParameterTunerTcpMsg>>,
+                       preview_latched: bool,
+                       spawn: impl FnOnce(async_std::channel::Sender<SlaveParameterTunerTcpMsg>, async_std::channel::Receiver<SlaveParameterTunerTcpMsg>) -> task::JoinHandle<Result<(), IOError>>,
+                       block: impl FnOnce(task::JoinHandle<Result<(), IOError>>)) -> bool {
+    if tcp_msg_sender.is_some() {
+        return false;
+    }
+    let (tcp_sender, tcp_receiver) = async_std::channel::bounded::<SlaveParameterTunerTcpMsg>(128);
+    *tcp_msg_sender = Some(tcp_sender.clone());
+    tcp_sender.try_send(SlaveParameterTunerTcpMsg::SetDebugModeEnabled(true)).unwrap_or(());
+    tcp_sender.try_send(SlaveParameterTunerTcpMsg::SetPreviewLatched(preview_latched)).unwrap_or(());
+    block(spawn(tcp_sender, tcp_receiver));
+    true
+}
+
 async fn parameter_tuner_handler(mut tcp_stream: TcpStream,
+                                 options: ParameterTunerSessionOptions,
                                  tcp_sender: async_std::channel::Sender<SlaveParameterTunerTcpMsg>,
                                  tcp_receiver: async_std::channel::Receiver<SlaveParameterTunerTcpMsg>,
                                  model_sender: Sender<SlaveParameterTunerMsg>) -> Result<(), IOError> {
-    fn current_millis() -> u128 {
-        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis()
-    }
-    const PREVIEW_TIME_MILLIS: u128 = 1000;
+    let ParameterTunerSessionOptions { preview_interval, pretty_json, propeller_keys, ping, framing } = options;
+    let peer_address = tcp_stream.peer_addr().map_or_else(|_| "未知".to_string(), |addr| addr.to_string());
+    log::info!("[{}] 开始参数调校，预览发送间隔 {:?}", peer_address, preview_interval);
     let last_propeller_preview_timestamp = async_std::sync::Arc::new(async_std::sync::Mutex::new(None as Option<u128>));
     let preview_propellers_value = async_std::sync::Arc::new(async_std::sync::Mutex::new(HashMap::<String, i8>::new()));
     let preview_control_loops = async_std::sync::Arc::new(async_std::sync::Mutex::new(HashMap::<String, ControlLoop>::new()));
-    let receive_task = task::spawn(clone!(@strong tcp_stream, @strong model_sender, @strong tcp_sender => async move {
+    let congestion_monitor = async_std::sync::Arc::new(async_std::sync::Mutex::new(PreviewCongestionMonitor::default()));
+    let mut preview_pending_since = None as Option<Instant>; // 尚未发送的预览值中最早的一个的记录时间
+    let mut preview_latched = false; // 保持模式下不记录预览时间，自动归零任务不会触发
+    let latency_estimator = async_std::sync::Arc::new(async_std::sync::Mutex::new(LatencyEstimator::new()));
+    let receive_task = task::spawn(clone!(@strong tcp_stream, @strong model_sender, @strong tcp_sender, @strong peer_address, @strong latency_estimator => async move {
         let mut tcp_stream = tcp_stream.clone();
-        let mut buf = [0u8; 1024];
+        let mut frame_reader = FrameReader::new(framing);
+        let mut protocol_error_reported = false; // 每次会话只提示一次，避免持续收到无法识别的数据包时刷屏
         tcp_sender.try_send(SlaveParameterTunerTcpMsg::RequestParameters).unwrap_or(());
         loop {
-            buf.fill(0);
-            if let Err(err) = tcp_stream.read(&mut buf).await {
-                tcp_sender.send(SlaveParameterTunerTcpMsg::ConnectionLost(err)).await.unwrap_or_default();
-                break;
-            } else {
-                let json_string = match std::str::from_utf8(buf.split(|x| x.eq(&0)).next().unwrap()) {
-                    Ok(string) => string,
-                    Err(_) => continue,
-                };
-                if json_string.is_empty() {
-                    tcp_sender.send(SlaveParameterTunerTcpMsg::ConnectionLost(IOError::new(std::io::ErrorKind::ConnectionAborted, "下位机主动断开连接（EOF）"))).await.unwrap_or_default();
+            let json_string = match frame_reader.read_frame(&mut tcp_stream).await {
+                Ok(json_string) => json_string,
+                Err(err) => {
+                    tcp_sender.send(SlaveParameterTunerTcpMsg::ConnectionLost(err)).await.unwrap_or_default();
                     break;
+                },
+            };
+            if let Some(pong) = SlavePongPacket::parse(&json_string) { // 也可能是独占连接前主连接发出的数据包的回复，序号不匹配时被忽略
+                if let Some(latency) = latency_estimator.lock().await.receive_pong(&pong) {
+                    send!(model_sender, SlaveParameterTunerMsg::LatencyUpdated(latency));
                 }
-                let msg = serde_json::from_str::<SlaveParameterTunerFeedbackPacket>(&json_string).map(SlaveParameterTunerMsg::FeedbacksReceived)
-                    .or_else(|_| serde_json::from_str::<SlaveParameterTunerPacket>(&json_string).map(SlaveParameterTunerMsg::ParametersReceived));
-                match msg {
-                    Ok(msg @ SlaveParameterTunerMsg::FeedbacksReceived(_)) => {
-                        send!(model_sender, msg);
-                    },
-                    Ok(msg @ SlaveParameterTunerMsg::ParametersReceived(_)) => {
-                        send!(model_sender, msg);
-                    },
-                    Ok(_) => unreachable!(),
-                    Err(err) => eprintln!("无法识别来自于下位机的JSON数据包（{}）：“{}”", err.to_string(), json_string),
-                }
+                continue;
+            }
+            let msg = serde_json::from_str::<SlaveParameterTunerFeedbackPacket>(&json_string).map(SlaveParameterTunerMsg::FeedbacksReceived)
+                .or_else(|_| serde_json::from_str::<SlaveParameterTunerTelemetryPacket>(&json_string).map(SlaveParameterTunerMsg::TelemetryReceived))
+                .or_else(|_| serde_json::from_str::<SlaveParameterTunerPacket>(&json_string).map(SlaveParameterTunerMsg::ParametersReceived));
+            match msg {
+                Ok(msg @ SlaveParameterTunerMsg::FeedbacksReceived(_)) => {
+                    send!(model_sender, msg);
+                },
+                Ok(msg @ SlaveParameterTunerMsg::TelemetryReceived(_)) => {
+                    send!(model_sender, msg);
+                },
+                Ok(msg @ SlaveParameterTunerMsg::ParametersReceived(_)) => {
+                    log::debug!("[{}] 收到下位机参数：{}", peer_address, json_string);
+                    send!(model_sender, msg);
+                },
+                Ok(_) => unreachable!(),
+                Err(err) => {
+                    log::warn!("[{}] 无法识别来自于下位机的 JSON 数据包（{}）：“{}”", peer_address, err, json_string);
+                    if !protocol_error_reported {
+                        protocol_error_reported = true;
+                        send!(model_sender, SlaveParameterTunerMsg::ProtocolError(format!("无法识别来自于下位机的数据包（{}），上位机与下位机的协议可能不一致", err)));
+                    }
+                },
             }
         }
     }));
@@ -690,17 +2238,17 @@ async fn parameter_tuner_handler(mut tcp_stream: TcpStream,
                     break;
                 }
             }
-            task::sleep(Duration::from_millis(100)).await;
+            task::sleep(preview_interval).await;
             
         }
     }));
     
-    let stop_propeller_preview_task = task::spawn(clone!(@strong tcp_sender, @strong last_propeller_preview_timestamp => async move {
+    let stop_propeller_preview_task = task::spawn(clone!(@strong tcp_sender, @strong last_propeller_preview_timestamp, @strong propeller_keys => async move {
         loop {
             let mut last_millis = last_propeller_preview_timestamp.lock().await;
             if let Some(millis) = *last_millis {
                 if current_millis() - millis >= PREVIEW_TIME_MILLIS {
-                    if tcp_sender.send(SlaveParameterTunerTcpMsg::PreviewPropellers(DEFAULT_PROPELLERS.iter().map(|x| (x.to_string(), 0i8)).collect())).await.is_err() {
+                    if tcp_sender.send(SlaveParameterTunerTcpMsg::PreviewPropellers(propeller_keys.iter().map(|x| (x.clone(), 0i8)).collect())).await.is_err() {
                         break;
                     }
                     *last_millis = None;
@@ -711,63 +2259,127 @@ async fn parameter_tuner_handler(mut tcp_stream: TcpStream,
         }
     }));
     
+    let ping_task = task::spawn(clone!(@strong tcp_sender => async move {
+        while ping && tcp_sender.send(SlaveParameterTunerTcpMsg::Ping).await.is_ok() { // 经由主循环写入，不会与其他数据包穿插
+            task::sleep(PING_INTERVAL).await;
+        }
+    }));
+
+    let feedback_rate_task = task::spawn(clone!(@strong tcp_sender, @strong model_sender, @strong congestion_monitor => async move {
+        while !tcp_sender.is_closed() { // 没有新的反馈时同样需要刷新频率
+            send!(model_sender, SlaveParameterTunerMsg::RefreshFeedbackRate);
+            if let Some(congested) = congestion_monitor.lock().await.refresh() {
+                send!(model_sender, SlaveParameterTunerMsg::LinkCongestionChanged(congested));
+            }
+            task::sleep(Duration::from_millis(500)).await;
+        }
+    }));
+    
     loop {
         match tcp_receiver.recv().await {
             Ok(msg) => {
                 match msg {
                     SlaveParameterTunerTcpMsg::UploadParameters(parameters) => {
-                        let json_string = serde_json::to_string(&parameters).unwrap();
-                        tcp_stream.write_all(json_string.as_bytes()).await?;
-                        tcp_stream.flush().await?;
-                        let json_string = serde_json::to_string(&SlaveParameterTunerSavePacket::default()).unwrap();
-                        tcp_stream.write_all(json_string.as_bytes()).await.unwrap_or_default();
-                        tcp_stream.flush().await?;
+                        let result = async {
+                            let json_string = encode_packet(&parameters, pretty_json);
+                            log::debug!("[{}] 写入参数：{}", peer_address, json_string);
+                            write_frame(&mut tcp_stream, framing, &json_string).await?;
+                            let json_string = encode_packet(&SlaveParameterTunerSavePacket::default(), pretty_json);
+                            write_frame(&mut tcp_stream, framing, &json_string).await
+                        }.await;
+                        if let Err(err) = &result {
+                            log::error!("[{}] 参数写入失败：{}", peer_address, err);
+                        }
+                        send!(model_sender, SlaveParameterTunerMsg::ApplyResult(result.as_ref().map(|_| ()).map_err(ToString::to_string)));
+                        result?;
                     },
                     SlaveParameterTunerTcpMsg::RequestParameters => {
-                        let json_string = serde_json::to_string(&SlaveParameterTunerLoadPacket::default()).unwrap();
-                        tcp_stream.write_all(json_string.as_bytes()).await?;
-                        tcp_stream.flush().await?;
+                        log::debug!("[{}] 请求读取下位机参数", peer_address);
+                        let json_string = encode_packet(&SlaveParameterTunerLoadPacket::default(), pretty_json);
+                        write_frame(&mut tcp_stream, framing, &json_string).await?;
                     },
                     SlaveParameterTunerTcpMsg::Terminate => {
                         receive_task.cancel().await;
                         parameter_preview_task.cancel().await;
                         stop_propeller_preview_task.cancel().await;
+                        feedback_rate_task.cancel().await;
+                        ping_task.cancel().await;
+                        log::info!("[{}] 结束参数调校", peer_address);
                         break;
                     },
                     SlaveParameterTunerTcpMsg::ConnectionLost(err) => {
-                        send!(model_sender, SlaveParameterTunerMsg::StopDebug);
+                        log::error!("[{}] 参数调校连接中断：{}", peer_address, err);
+                        send!(model_sender, SlaveParameterTunerMsg::ConnectionLost(err.to_string()));
                         tcp_stream.shutdown(std::net::Shutdown::Both).unwrap_or_default();
                         tcp_receiver.close();
                         return Err(err);
                     },
                     SlaveParameterTunerTcpMsg::SetDebugModeEnabled(enabled) => {
-                        let json_string = serde_json::to_string(&SlaveParameterTunerSetDebugModeEnabledPacket {
+                        log::debug!("[{}] {}调试模式", peer_address, if enabled { "启用" } else { "停用" });
+                        let json_string = encode_packet(&SlaveParameterTunerSetDebugModeEnabledPacket {
                             set_debug_mode_enabled: enabled,
-                        }).unwrap();
-                        tcp_stream.write_all(json_string.as_bytes()).await?;
-                        tcp_stream.flush().await?;
+                        }, pretty_json);
+                        write_frame(&mut tcp_stream, framing, &json_string).await?;
+                        if enabled {
+                            send!(model_sender, SlaveParameterTunerMsg::ConnectionStateChanged(ConnectionState::Connected));
+                        }
                     },
                     SlaveParameterTunerTcpMsg::PreviewPropeller(name, value) => {
-                        preview_propellers_value.lock().await.insert(name, value);
-                        *last_propeller_preview_timestamp.lock().await = Some(current_millis());
+                        let mut preview_propellers_value = preview_propellers_value.lock().await;
+                        if preview_propellers_value.is_empty() {
+                            preview_pending_since = Some(Instant::now());
+                        }
+                        let lagging = preview_pending_since.map_or(false, |instant| instant.elapsed() > preview_interval * 2);
+                        let saturated = tcp_receiver.capacity().map_or(false, |capacity| tcp_receiver.len() * 4 >= capacity * 3);
+                        let coalesced = preview_propellers_value.insert(name, value).is_some();
+                        drop(preview_propellers_value);
+                        if let Some(congested) = congestion_monitor.lock().await.record(saturated || (coalesced && lagging)) {
+                            log::warn!("[{}] {}", peer_address, if congested { "预览值大量丢失，链路可能拥塞" } else { "链路拥塞已缓解" });
+                            send!(model_sender, SlaveParameterTunerMsg::LinkCongestionChanged(congested));
+                        }
+                        if !preview_latched {
+                            *last_propeller_preview_timestamp.lock().await = Some(current_millis());
+                        }
+                    },
+                    SlaveParameterTunerTcpMsg::SetPreviewLatched(latched) => {
+                        log::info!("[{}] 预览模式切换为{}", peer_address, if latched { "保持" } else { "脉冲" });
+                        preview_latched = latched;
+                        // 切换回脉冲模式时从此刻开始计时，保持中的推进器在预览时长后归零
+                        *last_propeller_preview_timestamp.lock().await = (!latched).then(current_millis);
                     },
                     SlaveParameterTunerTcpMsg::PreviewPropellers(propeller_values) => {
-                        let json_string = serde_json::to_string(&SlaveParameterTunerSetPropellerPacket {
-                            set_propeller_values: propeller_values,
-                        }).unwrap();
-                        tcp_stream.write_all(json_string.as_bytes()).await?;
-                        tcp_stream.flush().await?;
+                        let json_string = encode_packet(&SlaveParameterTunerSetPropellerPacket {
+                            set_propeller_values: propeller_values.clone(),
+                        }, pretty_json);
+                        write_frame(&mut tcp_stream, framing, &json_string).await?;
+                        send!(model_sender, SlaveParameterTunerMsg::PreviewSent(current_millis(), propeller_values));
                     },
                     SlaveParameterTunerTcpMsg::PreviewControlLoops(control_loops) => {
-                        let json_string = serde_json::to_string(&SlaveParameterTunerSetControlLoopPacket {
+                        let json_string = encode_packet(&SlaveParameterTunerSetControlLoopPacket {
                             set_control_loop_parameters: control_loops,
-                        }).unwrap();
-                        tcp_stream.write_all(json_string.as_bytes()).await?;
-                        tcp_stream.flush().await?;
+                        }, pretty_json);
+                        write_frame(&mut tcp_stream, framing, &json_string).await?;
                     },
                     SlaveParameterTunerTcpMsg::PreviewControlLoop(name, value) => {
                         preview_control_loops.lock().await.insert(name, value);
                     },
+                    SlaveParameterTunerTcpMsg::Ping => {
+                        let ping = latency_estimator.lock().await.next_ping();
+                        if let Some(json_string) = ping {
+                            write_frame(&mut tcp_stream, framing, &json_string).await?;
+                        }
+                    },
+                    SlaveParameterTunerTcpMsg::StopAllPropellers => {
+                        log::info!("[{}] 停止全部推进器", peer_address);
+                        preview_propellers_value.lock().await.clear(); // 丢弃尚未发送的预览值
+                        *last_propeller_preview_timestamp.lock().await = None;
+                        let propeller_values: HashMap<String, i8> = propeller_keys.iter().map(|x| (x.clone(), 0i8)).collect();
+                        let json_string = encode_packet(&SlaveParameterTunerSetPropellerPacket {
+                            set_propeller_values: propeller_values.clone(),
+                        }, pretty_json);
+                        write_frame(&mut tcp_stream, framing, &json_string).await?;
+                        send!(model_sender, SlaveParameterTunerMsg::PreviewSent(current_millis(), propeller_values));
+                    },
                 }
             },
             Err(_) => (),
@@ -784,38 +2396,105 @@ impl MicroModel for SlaveParameterTunerModel {
     
     fn update(&mut self, msg: SlaveParameterTunerMsg, parent_sender: &Sender<SlaveMsg>, sender: Sender<SlaveParameterTunerMsg>) {
         self.reset();
-        
+        if self.read_only && msg.modifies_parameters() {
+            return;
+        }
+        let change_source = msg.parameter_change_source();
+        let initial_load = matches!(msg, SlaveParameterTunerMsg::ParametersReceived(_)) && self.device_parameters.is_none();
+        let before = change_source.map(|_| serde_json::to_value(&self.to_packet()).unwrap());
+        self.handle_msg(msg, parent_sender, sender);
+        if let (Some(source), Some(before)) = (change_source, before) {
+            self.record_parameter_changes(&before, source, initial_load);
+        }
+    }
+}
+
+impl SlaveParameterTunerModel {
+    fn handle_msg(&mut self, msg: SlaveParameterTunerMsg, parent_sender: &Sender<SlaveMsg>, sender: Sender<SlaveParameterTunerMsg>) {
         match msg {
+            SlaveParameterTunerMsg::ExportChangeLog(path) => {
+                let message = match fs::write(&path, self.parameter_change_log_csv()) {
+                    Ok(_) => format!("修改记录已导出至 {}", path.to_str().unwrap_or_default()),
+                    Err(err) => format!("无法导出修改记录：{}", err),
+                };
+                self.get_mut_toast_messages().borrow_mut().push_back(message);
+            },
+            SlaveParameterTunerMsg::SetPreviewLatched(latched) => {
+                if latched == self.preview_latched {
+                    return;
+                }
+                self.set_preview_latched(latched);
+                if let Some(msg_sender) = self.get_tcp_msg_sender() {
+                    msg_sender.try_send(SlaveParameterTunerTcpMsg::SetPreviewLatched(latched)).unwrap_or(());
+                }
+                let now = current_millis();
+                for index in 0..self.propellers.len() {
+                    let propeller_model = self.propellers.get_mut(index).unwrap();
+                    if propeller_model.previewing_until.is_some() {
+                        propeller_model.set_previewing_until(Some(if latched { PREVIEW_LATCHED_UNTIL } else { now + PREVIEW_TIME_MILLIS }));
+                    }
+                }
+            },
+            SlaveParameterTunerMsg::SetReadOnly(read_only) => {
+                if read_only {
+                    self.set_pending_device_parameters(None);
+                }
+                self.set_read_only(read_only);
+            },
             SlaveParameterTunerMsg::SetPropellerLowerDeadzone(index, value) => {
+                let value = self.checked_deadzone(value);
                 if let Some(propeller) = self.propellers.get_mut(index) {
                     propeller.reset();
-                    propeller.set_deadzone_lower(value);
-                    propeller.set_deadzone_upper(max(*propeller.get_deadzone_upper(), value));
+                    propeller.update_deadzone_lower(value);
                 }               // 不使用 unsafe 似乎无法在结束可变借用生命周期的同时将其转换为不可变借用？
                 if let (Some(propeller), Some(msg_sender)) = (self.propellers.get(index), self.get_tcp_msg_sender()) {
                     msg_sender.try_send(SlaveParameterTunerTcpMsg::PreviewPropeller(propeller.get_key().clone(), value)).unwrap_or(());
                 }
             },
             SlaveParameterTunerMsg::SetPropellerUpperDeadzone(index, value) => {
+                let value = self.checked_deadzone(value);
                 if let Some(propeller) = self.propellers.get_mut(index) {
                     propeller.reset();
-                    propeller.set_deadzone_upper(value);
-                    propeller.set_deadzone_lower(min(*propeller.get_deadzone_lower(), value));
+                    propeller.update_deadzone_upper(value);
                 }
                 if let (Some(propeller), Some(msg_sender)) = (self.propellers.get(index), self.get_tcp_msg_sender()) {
                     msg_sender.try_send(SlaveParameterTunerTcpMsg::PreviewPropeller(propeller.get_key().clone(), value)).unwrap_or(());
                 }
             },
             SlaveParameterTunerMsg::SetPropellerPowerPositive(index, value) => {
+                let value = match self.checked_finite("正向动力", value, POWER_RANGE) {
+                    Some(value) => value,
+                    None => return,
+                };
                 if let Some(propeller) = self.propellers.get_mut(index) {
                     propeller.reset();
                     propeller.set_power_positive(value);
+                    if *propeller.get_power_linked() && *propeller.get_power_negative() != value {
+                        send!(sender, SlaveParameterTunerMsg::SetPropellerPowerNegative(index, value));
+                    }
                 }
             },
             SlaveParameterTunerMsg::SetPropellerPowerNegative(index, value) => {
+                let value = match self.checked_finite("反向动力", value, POWER_RANGE) {
+                    Some(value) => value,
+                    None => return,
+                };
                 if let Some(propeller) = self.propellers.get_mut(index) {
                     propeller.reset();
                     propeller.set_power_negative(value);
+                    if *propeller.get_power_linked() && *propeller.get_power_positive() != value {
+                        send!(sender, SlaveParameterTunerMsg::SetPropellerPowerPositive(index, value));
+                    }
+                }
+            },
+            SlaveParameterTunerMsg::SetPropellerPowerLinked(index, linked) => {
+                if let Some(propeller) = self.propellers.get_mut(index) {
+                    propeller.reset();
+                    propeller.set_power_linked(linked);
+                    if linked {  // 以正向动力为准统一两者
+                        let value = *propeller.get_power_positive();
+                        send!(sender, SlaveParameterTunerMsg::SetPropellerPowerNegative(index, value));
+                    }
                 }
             },
             SlaveParameterTunerMsg::SetPropellerReversed(index, reversed) => {
@@ -830,7 +2509,32 @@ impl MicroModel for SlaveParameterTunerModel {
                     propeller.set_enabled(enabled);
                 }
             },
+            SlaveParameterTunerMsg::SetPropellerPreviewHistoryDisplayed(index, displayed) => {
+                if let Some(propeller) = self.propellers.get_mut(index) {
+                    propeller.reset();
+                    propeller.set_preview_history_displayed(displayed);
+                }
+            },
+            SlaveParameterTunerMsg::PreviewSent(timestamp, propeller_values) => {
+                let limit = *self.get_graph_view_point_num_limit() as usize;
+                for index in 0..self.propellers.len() {
+                    let propeller_model = self.propellers.get_mut(index).unwrap();
+                    if let Some(&value) = propeller_values.get(propeller_model.get_key()) {
+                        propeller_model.reset();
+                        let history = propeller_model.get_mut_preview_history();
+                        if history.len() >= limit {
+                            history.pop_front();
+                        }
+                        history.push_back((timestamp, value));
+                        propeller_model.set_previewing_until((value != 0).then(|| if self.preview_latched { PREVIEW_LATCHED_UNTIL } else { timestamp + PREVIEW_TIME_MILLIS }));
+                    }
+                }
+            },
             SlaveParameterTunerMsg::SetP(index, value) => {
+                let value = match self.checked_finite("P ", value, PID_RANGE) {
+                    Some(value) => value,
+                    None => return,
+                };
                 if let Some(pids) = self.control_loops.get_mut(index) {
                     pids.reset();
                     pids.set_p(value);
@@ -840,6 +2544,10 @@ impl MicroModel for SlaveParameterTunerModel {
                 }
             },
             SlaveParameterTunerMsg::SetI(index, value) => {
+                let value = match self.checked_finite("I ", value, PID_RANGE) {
+                    Some(value) => value,
+                    None => return,
+                };
                 if let Some(pids) = self.control_loops.get_mut(index) {
                     pids.reset();
                     pids.set_i(value);
@@ -849,6 +2557,10 @@ impl MicroModel for SlaveParameterTunerModel {
                 }
             },
             SlaveParameterTunerMsg::SetD(index, value) => {
+                let value = match self.checked_finite("D ", value, PID_RANGE) {
+                    Some(value) => value,
+                    None => return,
+                };
                 if let Some(pids) = self.control_loops.get_mut(index) {
                     pids.reset();
                     pids.set_d(value);
@@ -857,75 +2569,597 @@ impl MicroModel for SlaveParameterTunerModel {
                     msg_sender.try_send(SlaveParameterTunerTcpMsg::PreviewControlLoop.apply(pids.to_control_loop())).unwrap_or(());
                 }
             },
+            SlaveParameterTunerMsg::SetControlLoopEnabled(index, enabled) => {
+                if let Some(pids) = self.control_loops.get_mut(index) {
+                    pids.reset();
+                    pids.set_enabled(enabled);
+                }
+                if let (Some(pids), Some(msg_sender)) = (self.control_loops.get(index), self.get_tcp_msg_sender()) {
+                    msg_sender.try_send(SlaveParameterTunerTcpMsg::PreviewControlLoop.apply(pids.to_control_loop())).unwrap_or(());
+                }
+            },
+            SlaveParameterTunerMsg::SetControlLoopBias(index, value) => {
+                let scale = match self.control_loops.get(index) {
+                    Some(pids) => pids.feedback_display.scale as f64,
+                    None => return,
+                };
+                let value = match self.checked_finite("零点偏置", value / scale, FEEDBACK_BIAS_RANGE) {
+                    Some(value) => value,
+                    None => return,
+                };
+                if let Some(pids) = self.control_loops.get_mut(index) {
+                    pids.reset();
+                    pids.set_bias(value);
+                }
+                if let (Some(pids), Some(msg_sender)) = (self.control_loops.get(index), self.get_tcp_msg_sender()) {
+                    if pids.bias_on_device {
+                        msg_sender.try_send(SlaveParameterTunerTcpMsg::PreviewControlLoop.apply(pids.to_control_loop())).unwrap_or(());
+                    }
+                }
+            },
+            SlaveParameterTunerMsg::SetControlLoopBiasOnDevice(index, on_device) => {
+                if let Some(pids) = self.control_loops.get_mut(index) {
+                    if pids.bias_on_device == on_device {
+                        return;
+                    }
+                    pids.reset();
+                    pids.set_bias_on_device(on_device);
+                    pids.get_mut_feedbacks().clear(); // 切换前后收到的反馈修正方式不同，不应绘制在同一图表中
+                    pids.get_mut_raw_feedbacks().clear();
+                    pids.get_mut_feedback_gaps().clear();
+                }
+                if let (Some(pids), Some(msg_sender)) = (self.control_loops.get(index), self.get_tcp_msg_sender()) {
+                    let (key, mut control_loop) = pids.to_control_loop();
+                    control_loop.bias.get_or_insert(0.0); // 关闭时通知下位机撤销修正
+                    msg_sender.try_send(SlaveParameterTunerTcpMsg::PreviewControlLoop.apply((key, control_loop))).unwrap_or(());
+                }
+            },
+            SlaveParameterTunerMsg::CaptureControlLoopSnapshot(index) => {
+                if self.control_loops.get(index).map_or(false, |pids| pids.feedbacks.is_empty()) {
+                    self.get_mut_toast_messages().borrow_mut().push_back("尚未收到反馈，无法拍摄快照".to_string());
+                    return;
+                }
+                if let Some(pids) = self.control_loops.get_mut(index) {
+                    let time = DateTime::now_local().ok().and_then(|time| time.format("%H:%M:%S").ok()).map(|time| time.to_string()).unwrap_or_default();
+                    let label = format!("快照 {}（P {:.2} I {:.2} D {:.2}，{}）", pids.snapshots.len() + 1, pids.p, pids.i, pids.d, time);
+                    let values = pids.feedbacks.iter().map(|&x| pids.displayed_feedback(x)).collect();
+                    pids.reset();
+                    pids.get_mut_snapshots().push(ControlLoopSnapshot { label, values });
+                }
+            },
+            SlaveParameterTunerMsg::RecallControlLoopSnapshot(index, snapshot) => {
+                if let Some(pids) = self.control_loops.get_mut(index) {
+                    pids.reset();
+                    pids.set_recalled_snapshot(snapshot.filter(|&snapshot| snapshot < pids.snapshots.len()));
+                }
+            },
+            SlaveParameterTunerMsg::RemoveControlLoopSnapshot(index) => {
+                if let Some(pids) = self.control_loops.get_mut(index) {
+                    if let Some(snapshot) = pids.recalled_snapshot.filter(|&snapshot| snapshot < pids.snapshots.len()) {
+                        pids.reset();
+                        pids.get_mut_snapshots().remove(snapshot);
+                        pids.set_recalled_snapshot(None);
+                    }
+                }
+            },
+            SlaveParameterTunerMsg::MoveControlLoopBackward(index) => if let Some(target) = index.checked_sub(1) {
+                self.swap_control_loops(index, target);
+            },
+            SlaveParameterTunerMsg::MoveControlLoopForward(index) => self.swap_control_loops(index, index + 1),
+            SlaveParameterTunerMsg::ZeroControlLoopFeedback(index) => {
+                let bias = match self.control_loops.get(index).map(ControlLoopModel::zeroing_bias) {
+                    Some(Some(bias)) => bias,
+                    Some(None) => {
+                        self.get_mut_toast_messages().borrow_mut().push_back("尚未收到反馈，无法设为零点".to_string());
+                        return;
+                    },
+                    None => return,
+                };
+                let scale = self.control_loops.get(index).map_or(1.0, |pids| pids.feedback_display.scale as f64);
+                send!(sender, SlaveParameterTunerMsg::SetControlLoopBias(index, bias * scale));
+            },
             SlaveParameterTunerMsg::ResetParameters => {
+                if *self.get_uploading() {
+                    return;
+                }
                 if let Some(msg_sender) = self.get_tcp_msg_sender() {
                     msg_sender.try_send(SlaveParameterTunerTcpMsg::RequestParameters).unwrap_or(());
                 }
             },
+            SlaveParameterTunerMsg::StopAllPropellers => {
+                if let Some(msg_sender) = self.get_tcp_msg_sender() {
+                    match msg_sender.try_send(SlaveParameterTunerTcpMsg::StopAllPropellers) {
+                        Ok(_) => self.get_mut_toast_messages().borrow_mut().push_back("已停止全部推进器".to_string()),
+                        Err(err) => self.get_mut_toast_messages().borrow_mut().push_back(format!("无法停止推进器：{}", err)),
+                    }
+                }
+            },
             SlaveParameterTunerMsg::ApplyParameters => {
+                if *self.get_uploading() {
+                    return;
+                }
                 if let Some(msg_sender) = self.get_tcp_msg_sender() {
-                    msg_sender.try_send(SlaveParameterTunerTcpMsg::UploadParameters(SlaveParameterTunerPacket {
-                        set_propeller_pwm_freq_calibration: self.propeller_pwm_frequency_calibration,
-                        set_propeller_parameters: PropellerModel::vec_to_map(self.propellers.iter().collect()),
-                        set_control_loop_parameters: ControlLoopModel::vec_to_map(self.control_loops.iter().collect()),
-                    })).unwrap_or(());
-                    
+                    match self.to_upload_packet() {
+                        Some(packet) => {
+                            if msg_sender.try_send(SlaveParameterTunerTcpMsg::UploadParameters(packet)).is_ok() {
+                                self.uploading_parameters = Some(self.to_packet());
+                                self.set_uploading(true);
+                            }
+                        },
+                        None => self.get_mut_toast_messages().borrow_mut().push_back("参数未发生变化，无需写入".to_string()),
+                    }
+                }
+            },
+            SlaveParameterTunerMsg::ApplyResult(result) => {
+                self.set_uploading(false);
+                let parameters = self.uploading_parameters.take();
+                match result {
+                    Ok(()) => {
+                        self.set_device_parameters(parameters);
+                        self.set_loaded_preset(None);
+                        self.get_mut_toast_messages().borrow_mut().push_back("参数已写入下位机".to_string());
+                    },
+                    Err(err) => {
+                        self.set_device_parameters(None); // 无法确定下位机当前的参数，下次写入时发送全部参数
+                        self.notify_critical(parent_sender, format!("参数写入失败：{}", err));
+                    },
+                }
+            },
+            SlaveParameterTunerMsg::ExportParameters(path) => {
+                let file = SlaveParameterTunerExportFile {
+                    metadata: Some(self.export_metadata()),
+                    parameters: self.to_packet(),
+                };
+                let message = match fs::write(&path, serde_json::to_string_pretty(&file).unwrap()) {
+                    Ok(_) => format!("参数已导出至 {}", path.to_str().unwrap_or_default()),
+                    Err(err) => format!("无法导出参数：{}", err),
+                };
+                self.get_mut_toast_messages().borrow_mut().push_back(message);
+            },
+            SlaveParameterTunerMsg::GenerateReport(path) => {
+                let message = match self.write_report(&path) {
+                    Ok(directory) => format!("调参报告已保存至 {}", directory.to_str().unwrap_or_default()),
+                    Err(err) => format!("无法生成调参报告：{}", err),
+                };
+                self.get_mut_toast_messages().borrow_mut().push_back(message);
+            },
+            SlaveParameterTunerMsg::ImportParameters(path) => {
+                match fs::read_to_string(&path).map_err(|err| err.to_string())
+                    .and_then(|json| serde_json::from_str::<SlaveParameterTunerExportFile>(&json).map_err(|err| err.to_string())) {
+                    Ok(SlaveParameterTunerExportFile { metadata, mut parameters }) => {
+                        self.sanitize_packet(&mut parameters, "参数文件");
+                        if let Some(metadata) = &metadata {
+                            self.notes.set_text(&metadata.notes);
+                        }
+                        self.set_imported_metadata(metadata);
+                        self.load_packet(parameters);
+                    },
+                    Err(err) => self.get_mut_toast_messages().borrow_mut().push_back(format!("无法导入参数：{}", err)),
+                }
+            },
+            SlaveParameterTunerMsg::ImportPropellerLayout(path) => {
+                if self.tcp_msg_sender.is_some() {
+                    self.get_mut_toast_messages().borrow_mut().push_back("请先停止调试再更换推进器布局".to_string());
+                    return;
+                }
+                match fs::read_to_string(&path).map_err(|err| err.to_string()).and_then(|json| PropellerLayout::from_json(&json)) {
+                    Ok(layout) => {
+                        if let Err(err) = layout.save() {
+                            log::warn!("无法保存推进器布局：{}", err);
+                        }
+                        let packet = self.to_packet(); // 保留布局中仍存在的推进器的参数
+                        let propellers = self.get_mut_propellers();
+                        propellers.clear();
+                        for key in layout.keys() {
+                            propellers.push(PropellerModel::new(&key, &layout));
+                        }
+                        self.get_mut_toast_messages().borrow_mut().push_back(format!("已导入推进器布局，共 {} 个推进器", layout.propellers.len()));
+                        self.set_propeller_layout(layout);
+                        self.load_packet(packet);
+                    },
+                    Err(err) => self.get_mut_toast_messages().borrow_mut().push_back(format!("无法导入推进器布局：{}", err)),
+                }
+            },
+            SlaveParameterTunerMsg::LoadPreset(index) => {
+                if *self.get_uploading() {
+                    return;
+                }
+                let (name, json) = PARAMETER_PRESETS[index];
+                match serde_json::from_str::<SlaveParameterTunerPacket>(json) {
+                    Ok(mut preset) => {
+                        // 转向、死区与 PWM 频率校准取决于具体的机器人，保留当前值
+                        let current = self.to_packet();
+                        preset.set_propeller_pwm_freq_calibration = current.set_propeller_pwm_freq_calibration;
+                        for (key, propeller) in preset.set_propeller_parameters.iter_mut() {
+                            if let Some(current) = current.set_propeller_parameters.get(key) {
+                                propeller.reversed = current.reversed;
+                                propeller.deadzone_lower = current.deadzone_lower;
+                                propeller.deadzone_upper = current.deadzone_upper;
+                            }
+                        }
+                        self.load_packet(preset);
+                        self.set_loaded_preset(Some(name));
+                        self.get_mut_toast_messages().borrow_mut().push_back(format!("已载入预设“{}”，保存后写入下位机", name));
+                    },
+                    Err(err) => log::error!("无法解析参数预设“{}”：{}", name, err),
                 }
             },
             SlaveParameterTunerMsg::StartDebug(tcp_stream) => {
-                let (tcp_sender, tcp_receiver) = async_std::channel::bounded::<SlaveParameterTunerTcpMsg>(128);
-                self.tcp_msg_sender = Some(tcp_sender.clone());
-                let sender = sender.clone();
-                tcp_sender.try_send(SlaveParameterTunerTcpMsg::SetDebugModeEnabled(true)).unwrap_or(());
-                let handle = task::spawn(parameter_tuner_handler(tcp_stream, tcp_sender, tcp_receiver, sender));
-                send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(handle)));
+                if self.tcp_msg_sender.is_none() && self.exclusive_tcp_activity.get() == Some(ExclusiveTcpActivity::FirmwareUpdate) {
+                    self.set_connection_state(ConnectionState::Error("固件更新正在使用下位机连接".to_string()));
+                    return;
+                }
+                let slave_address = tcp_stream.peer_addr().ok().map(|addr| addr.to_string());
+                let options = ParameterTunerSessionOptions {
+                    preview_interval: *self.get_preview_interval(),
+                    pretty_json: *self.get_pretty_json(),
+                    propeller_keys: self.propeller_layout.keys(),
+                    ping: self.capabilities.ping,
+                    framing: FrameFormat::from_capabilities(&self.capabilities),
+                };
+                let started = start_debug_session(&mut self.tcp_msg_sender, self.preview_latched,
+                    |tcp_sender, tcp_receiver| task::spawn(parameter_tuner_handler(tcp_stream, options, tcp_sender, tcp_receiver, sender.clone())),
+                    |handle| send!(parent_sender, SlaveMsg::TcpMessage(SlaveTcpMsg::Block(ExclusiveTcpActivity::ParameterTuning, handle))));
+                if !started {
+                    log::warn!("调试会话已在进行中，忽略重复的调试请求");
+                    return;
+                }
+                self.set_slave_address(slave_address);
+                self.set_connection_state(ConnectionState::Connecting);
             },
             SlaveParameterTunerMsg::StopDebug => {
                 if let Some(msg_sender) = self.get_tcp_msg_sender() {
+                    if self.preview_latched { // 保持模式下推进器不会自动归零，退出前先停止
+                        msg_sender.try_send(SlaveParameterTunerTcpMsg::StopAllPropellers).unwrap_or(());
+                    }
                     msg_sender.try_send(SlaveParameterTunerTcpMsg::SetDebugModeEnabled(false)).unwrap_or(());
                     msg_sender.try_send(SlaveParameterTunerTcpMsg::Terminate).unwrap_or_default();
-                    self.set_tcp_msg_sender(None);
+                    self.end_debug_session(parent_sender);
+                    if !matches!(self.get_connection_state(), ConnectionState::Error(_)) {
+                        self.set_connection_state(ConnectionState::Disconnected);
+                    }
                     self.set_stopped(true);
+                } else if self.awaiting_reconnect { // 等待重连期间关闭窗口
+                    self.set_awaiting_reconnect(false);
+                    self.set_stopped(true);
+                }
+            },
+            SlaveParameterTunerMsg::CapabilitiesReceived(capabilities) => {
+                let keys = self.control_loops.iter().map(|pids| pids.get_key().clone()).collect::<Vec<_>>();
+                let advertised_keys = self.control_loop_order.sort(capabilities.control_loops.iter().map(String::as_str));
+                if !advertised_keys.is_empty() && advertised_keys != keys {
+                    log::info!("下位机声明的控制环：{:?}", capabilities.control_loops);
+                    let control_loops = self.get_mut_control_loops();
+                    control_loops.clear();
+                    for key in advertised_keys.iter() {
+                        control_loops.push(ControlLoopModel::new(key));
+                    }
+                }
+                self.set_capabilities(capabilities);
+            },
+            SlaveParameterTunerMsg::ConnectionLost(err) => {
+                if self.auto_reconnect && self.get_tcp_msg_sender().is_some() {
+                    self.end_debug_session(parent_sender);
+                    self.set_connection_state(ConnectionState::Reconnecting);
+                    self.set_awaiting_reconnect(true);
+                    self.notify_critical(parent_sender, format!("与下位机的连接中断（{}），重新连接后将自动恢复调试", err));
+                } else {
+                    self.notify_critical(parent_sender, format!("与下位机的连接中断：{}", err));
+                    self.set_connection_state(ConnectionState::Error(err));
+                    send!(sender, SlaveParameterTunerMsg::StopDebug);
+                }
+            },
+            SlaveParameterTunerMsg::SlaveConnectionChanged(tcp_stream) => {
+                if !self.awaiting_reconnect {
+                    return;
+                }
+                self.set_awaiting_reconnect(false);
+                match tcp_stream {
+                    Some(tcp_stream) => {
+                        let keep_history = self.keep_history_on_reconnect;
+                        for index in 0..self.control_loops.len() {
+                            let pids = self.control_loops.get_mut(index).unwrap();
+                            pids.reset();
+                            if keep_history { // 继续在原有曲线后绘制，并标记中断位置
+                                if !pids.feedbacks.is_empty() {
+                                    let gap = pids.feedback_count;
+                                    pids.get_mut_feedback_gaps().push_back(gap);
+                                }
+                            } else {
+                                pids.get_mut_feedbacks().clear();
+                                pids.get_mut_raw_feedbacks().clear();
+                                pids.get_mut_feedback_gaps().clear();
+                            }
+                        }
+                        self.feedback_timestamps.clear();
+                        send!(sender, SlaveParameterTunerMsg::StartDebug(tcp_stream));
+                    },
+                    None => {
+                        self.notify_critical(parent_sender, "自动重连失败，调试已停止".to_string());
+                        self.set_connection_state(ConnectionState::Error("自动重连失败，请关闭窗口后重新连接".to_string()));
+                    },
                 }
             },
+            SlaveParameterTunerMsg::ProtocolError(err) => self.notify_critical(parent_sender, err),
+            SlaveParameterTunerMsg::SetWindowActive(active) => self.window_active = active,
+            SlaveParameterTunerMsg::ConnectionStateChanged(state) => self.set_connection_state(state),
+            SlaveParameterTunerMsg::LinkCongestionChanged(congested) => self.set_link_congested(congested),
+            SlaveParameterTunerMsg::LatencyUpdated(latency) => {
+                self.set_latency(Some(latency));
+                send!(parent_sender, SlaveMsg::LatencyUpdated(Some(latency))); // 调试期间主连接不再测量，由此更新机位界面
+            },
+            SlaveParameterTunerMsg::RefreshFeedbackRate => {
+                self.refresh_feedback_rate();
+                self.clear_propeller_previews(Some(current_millis().saturating_sub(PREVIEW_TIME_MILLIS)));
+            },
             SlaveParameterTunerMsg::FeedbacksReceived(SlaveParameterTunerFeedbackPacket { feedbacks: SlaveParameterTunerFeedbackValuePacket { control_loops } }) => {
+                self.feedback_timestamps.push_back(Instant::now());
+                self.refresh_feedback_rate();
+                if self.last_feedbacks_forwarded.map_or(true, |instant| instant.elapsed() >= FEEDBACKS_FORWARD_INTERVAL) {
+                    self.last_feedbacks_forwarded = Some(Instant::now());
+                    send!(parent_sender, SlaveMsg::ParameterTunerFeedbacksUpdated(Some(control_loops.clone())));
+                }
                 let limit = *self.get_graph_view_point_num_limit() as usize;
+                let record_raw = self.feedback_outlier_limit.is_some();
                 for index in 0..self.control_loops.len() {
+                    let plausible = control_loops.get(self.control_loops.get(index).unwrap().get_key()).map_or(true, |&value| self.is_feedback_plausible(value));
                     let control_loop_model = self.control_loops.get_mut(index).unwrap();
                     if let Some(&control_loop_value) = control_loops.get(control_loop_model.get_key()) {
-                        let feedbacks = control_loop_model.get_mut_feedbacks();
-                        if feedbacks.len() == limit {
-                            feedbacks.pop_front();
+                        if record_raw {
+                            let raw_feedbacks = control_loop_model.get_mut_raw_feedbacks();
+                            if raw_feedbacks.len() == limit {
+                                raw_feedbacks.pop_front();
+                            }
+                            raw_feedbacks.push_back(control_loop_value);
+                        }
+                        if !plausible {
+                            log::warn!("丢弃{}的异常反馈值 {}（上限 ±{}）", ControlLoopModel::key_to_string(control_loop_model.get_key()), control_loop_value, self.feedback_outlier_limit.unwrap_or_default());
+                            continue;
                         }
-                        feedbacks.push_back(control_loop_value);
+                        control_loop_model.push_feedback(control_loop_value, limit);
                     }
                 }
             },
-            SlaveParameterTunerMsg::ParametersReceived(SlaveParameterTunerPacket { set_propeller_pwm_freq_calibration: pwm_freq_calibration, set_propeller_parameters: propellers, set_control_loop_parameters: control_loops }) => {
-                self.set_propeller_pwm_frequency_calibration(pwm_freq_calibration);
+            SlaveParameterTunerMsg::TelemetryReceived(_) if !self.capabilities.propeller_telemetry => {
+                log::debug!("下位机未声明支持推进器遥测，忽略收到的遥测数据");
+            },
+            SlaveParameterTunerMsg::TelemetryReceived(SlaveParameterTunerTelemetryPacket { telemetry: SlaveParameterTunerTelemetryValuePacket { propellers } }) => {
                 for index in 0..self.propellers.len() {
                     let propeller_model = self.propellers.get_mut(index).unwrap();
-                    if let Some(propeller) = propellers.get(propeller_model.get_key()) {
-                        propeller_model.set_deadzone_lower(propeller.deadzone_lower.min(propeller.deadzone_upper));
-                        propeller_model.set_deadzone_upper(propeller.deadzone_upper.max(propeller.deadzone_lower));
-                        propeller_model.set_power_positive(propeller.power_positive);
-                        propeller_model.set_power_negative(propeller.power_negative);
-                        propeller_model.set_reversed(propeller.reversed);
-                        propeller_model.set_enabled(propeller.enabled);
+                    if let Some(telemetry) = propellers.get(propeller_model.get_key()) {
+                        propeller_model.reset();
+                        propeller_model.set_telemetry(Some(telemetry.clone()));
                     }
                 }
-                for index in 0..self.control_loops.len() {
-                    let control_loop_model = self.control_loops.get_mut(index).unwrap();
-                    if let Some(control_loop) = control_loops.get(control_loop_model.get_key()) {
-                        control_loop_model.set_p(control_loop.p);
-                        control_loop_model.set_i(control_loop.i);
-                        control_loop_model.set_d(control_loop.d);
+            },
+            SlaveParameterTunerMsg::ParametersReceived(mut parameters) => {
+                self.load_feedback_display(std::mem::take(&mut parameters.control_loop_feedback_display));
+                if let Some(frequency) = parameters.pwm_base_frequency.take().filter(|frequency| frequency.is_finite() && *frequency > 0.0) {
+                    self.set_device_pwm_base_frequency(Some(frequency));
+                }
+                self.sanitize_packet(&mut parameters, "下位机");
+                if *self.get_preserve_local_edits() && !self.read_only && !self.conflicting_fields(&parameters).is_empty() {
+                    self.set_pending_device_parameters(Some(parameters));
+                } else {
+                    self.set_device_parameters(Some(parameters.clone()));
+                    self.load_packet(parameters);
+                    self.set_loaded_preset(None);
+                }
+            },
+            SlaveParameterTunerMsg::ResolveParameterConflict(pointers) => {
+                if let Some(parameters) = self.pending_device_parameters.take() {
+                    let device = serde_json::to_value(&parameters).unwrap();
+                    let mut merged = serde_json::to_value(&self.to_packet()).unwrap();
+                    for pointer in pointers {
+                        if let (Some(field), Some(value)) = (merged.pointer_mut(&pointer), device.pointer(&pointer)) {
+                            *field = value.clone();
+                        }
                     }
+                    match serde_json::from_value(merged) {
+                        Ok(merged) => self.load_packet(merged),
+                        Err(err) => log::error!("无法合并参数：{}", err),
+                    }
+                    self.set_device_parameters(Some(parameters));
                 }
             },
             SlaveParameterTunerMsg::SetPropellerPwmFreqCalibration(cal) => {
-              self.set_propeller_pwm_frequency_calibration(cal);
+                if let Some(cal) = self.checked_finite("PWM 频率校准", cal, PWM_FREQ_CALIBRATION_RANGE) {
+                    self.set_propeller_pwm_frequency_calibration(cal);
+                }
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    /// 控件可能给出的任意数值，包括 NaN、正负无穷与 `i8` 范围边界附近的值
+    fn arbitrary_f64(rng: &mut StdRng) -> f64 {
+        const SPECIAL: [f64; 11] = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, f64::MAX, f64::MIN, 127.5, 127.4, -128.5, -128.4, 0.5, -0.0];
+        match rng.gen_range(0..3) {
+            0 => SPECIAL[rng.gen_range(0..SPECIAL.len())],
+            1 => f64::from_bits(rng.gen()),
+            _ => rng.gen_range(-300.0..300.0),
+        }
+    }
+
+    #[test]
+    fn deadzone_from_value_clamps_into_i8() {
+        assert_eq!(PropellerModel::deadzone_from_value(f64::NAN), (0, true));
+        assert_eq!(PropellerModel::deadzone_from_value(f64::INFINITY), (i8::MAX, true));
+        assert_eq!(PropellerModel::deadzone_from_value(f64::NEG_INFINITY), (i8::MIN, true));
+        assert_eq!(PropellerModel::deadzone_from_value(127.6), (i8::MAX, true));
+        assert_eq!(PropellerModel::deadzone_from_value(127.4), (i8::MAX, false));
+        assert_eq!(PropellerModel::deadzone_from_value(-128.6), (i8::MIN, true));
+        assert_eq!(PropellerModel::deadzone_from_value(-12.4), (-12, false));
+    }
+
+    #[test]
+    fn deadzone_from_value_reports_clamping_exactly() {
+        let mut rng = StdRng::seed_from_u64(838);
+        for _ in 0..100_000 {
+            let value = arbitrary_f64(&mut rng);
+            let (deadzone, clamped) = PropellerModel::deadzone_from_value(value);
+            assert_eq!(clamped, deadzone as f64 != value.round(), "value: {}", value);
+        }
+    }
+
+    fn test_packet() -> SlaveParameterTunerPacket {
+        let propeller = Propeller { deadzone_lower: -10, deadzone_upper: 10, power_positive: 0.75, power_negative: 0.75, reversed: false, enabled: true };
+        let control_loop = ControlLoop { p: 1.0, i: 1.0, d: 1.0, enabled: true, bias: None };
+        SlaveParameterTunerPacket {
+            set_propeller_pwm_freq_calibration: 0.0,
+            set_propeller_parameters: [("front_left", propeller.clone()), ("front_right", propeller)].into_iter().map(|(key, propeller)| (key.to_string(), propeller)).collect(),
+            set_control_loop_parameters: [("depth_lock".to_string(), control_loop)].into_iter().collect(),
+            control_loop_feedback_display: HashMap::new(),
+            pwm_base_frequency: None,
+        }
+    }
+
+    fn upload_diff(old: &SlaveParameterTunerPacket, new: &SlaveParameterTunerPacket) -> Option<JsonValue> {
+        json_diff(&serde_json::to_value(old).unwrap(), &serde_json::to_value(new).unwrap(), UPLOAD_DIFF_DEPTH)
+    }
+
+    #[test]
+    fn upload_diff_omits_unchanged_fields() {
+        let old = test_packet();
+        let mut new = old.clone();
+        new.set_propeller_parameters.get_mut("front_left").unwrap().power_positive = 0.5;
+        let diff = upload_diff(&old, &new).unwrap();
+        let diff = diff.as_object().unwrap();
+        assert!(!diff.contains_key("set_propeller_pwm_freq_calibration"));
+        assert!(!diff.contains_key("set_control_loop_parameters"));
+        let propellers = diff["set_propeller_parameters"].as_object().unwrap();
+        assert!(!propellers.contains_key("front_right"));
+        // 发生变化的推进器整体发送，下位机仍可按完整的 `Propeller` 解析
+        let front_left: Propeller = serde_json::from_value(propellers["front_left"].clone()).unwrap();
+        assert_eq!(front_left, new.set_propeller_parameters["front_left"]);
+    }
+
+    #[test]
+    fn upload_diff_sends_whole_control_loop() {
+        let old = test_packet();
+        let mut new = old.clone();
+        new.set_control_loop_parameters.get_mut("depth_lock").unwrap().p = 2.0;
+        let diff = upload_diff(&old, &new).unwrap();
+        assert!(diff.get("set_propeller_parameters").is_none());
+        let depth_lock: ControlLoop = serde_json::from_value(diff["set_control_loop_parameters"]["depth_lock"].clone()).unwrap();
+        assert_eq!(depth_lock, new.set_control_loop_parameters["depth_lock"]);
+    }
+
+    #[test]
+    fn upload_diff_of_identical_packets_is_empty() {
+        assert_eq!(upload_diff(&test_packet(), &test_packet()), None);
+    }
+
+    #[test]
+    fn sanitize_replaces_non_finite_parameters() {
+        let mut packet = test_packet();
+        packet.set_propeller_pwm_freq_calibration = f64::NAN;
+        packet.set_propeller_parameters.get_mut("front_left").unwrap().power_positive = f64::INFINITY;
+        packet.set_propeller_parameters.get_mut("front_right").unwrap().power_negative = f64::NEG_INFINITY;
+        let depth_lock = packet.set_control_loop_parameters.get_mut("depth_lock").unwrap();
+        depth_lock.p = f64::NAN;
+        depth_lock.bias = Some(f64::NAN);
+        let fields = packet.sanitize(&PropellerLayout::default());
+        assert_eq!(fields.len(), 5);
+        assert_eq!(packet.set_propeller_pwm_freq_calibration, 0.0);
+        assert_eq!(packet.set_propeller_parameters["front_left"].power_positive, POWER_RANGE.1);
+        assert_eq!(packet.set_propeller_parameters["front_right"].power_negative, POWER_RANGE.0);
+        assert_eq!(packet.set_control_loop_parameters["depth_lock"].p, 1.0);
+        assert_eq!(packet.set_control_loop_parameters["depth_lock"].bias, Some(0.0));
+        // 修正后的参数不再包含会被序列化为 null 的数值
+        let json = serde_json::to_value(&packet).unwrap();
+        assert_eq!(serde_json::from_value::<SlaveParameterTunerPacket>(json).unwrap(), packet);
+    }
+
+    #[test]
+    fn received_non_finite_parameters_load_as_sanitized_values() {
+        let mut propellers = FactoryVec::new();
+        for key in ["front_left", "front_right"] {
+            propellers.push(PropellerModel { key: key.to_string(), ..Default::default() });
+        }
+        let mut control_loops = FactoryVec::new();
+        control_loops.push(ControlLoopModel::new("depth_lock"));
+        let mut packet = test_packet();
+        packet.set_propeller_parameters.get_mut("front_left").unwrap().power_positive = f64::NAN;
+        packet.set_propeller_parameters.get_mut("front_right").unwrap().power_negative = f64::INFINITY;
+        let depth_lock = packet.set_control_loop_parameters.get_mut("depth_lock").unwrap();
+        depth_lock.d = f64::NEG_INFINITY;
+        depth_lock.bias = Some(f64::NAN);
+        packet.control_loop_feedback_display.insert("depth_lock".to_string(), ControlLoopFeedbackDisplay { scale: f32::NAN, unit: "cm".to_string(), upper: 1.0, lower: 0.0 });
+        // 与 `ParametersReceived` 的处理顺序相同：先取出反馈显示方式，修正非有限值后载入模型
+        apply_feedback_display(&mut control_loops, &std::mem::take(&mut packet.control_loop_feedback_display));
+        assert_eq!(packet.sanitize(&PropellerLayout::default()).len(), 4);
+        load_parameters(&mut propellers, &mut control_loops, &packet);
+        let front_left = propellers.get(0).unwrap();
+        assert_eq!((*front_left.get_power_positive(), *front_left.get_power_negative(), *front_left.get_power_linked()), (0.75, 0.75, true));
+        let front_right = propellers.get(1).unwrap();
+        assert_eq!((*front_right.get_power_positive(), *front_right.get_power_negative(), *front_right.get_power_linked()), (0.75, POWER_RANGE.1, false));
+        let depth_lock = control_loops.get(0).unwrap();
+        assert_eq!((*depth_lock.get_p(), *depth_lock.get_i(), *depth_lock.get_d()), (1.0, 1.0, PID_RANGE.0));
+        assert_eq!((*depth_lock.get_bias(), *depth_lock.get_bias_on_device()), (0.0, true));
+        assert_eq!(*depth_lock.get_feedback_display(), ControlLoopModel::default_feedback_display("depth_lock")); // 无效的显示方式不被采用
+        // 载入后的模型可以重新生成有效的参数包
+        let propeller_parameters = PropellerModel::vec_to_map(propellers.iter().collect());
+        let control_loop_parameters = ControlLoopModel::vec_to_map(control_loops.iter().collect());
+        assert!(propeller_parameters.values().all(|propeller| propeller.power_positive.is_finite() && propeller.power_negative.is_finite()));
+        assert!(control_loop_parameters.values().all(|control_loop| [control_loop.p, control_loop.i, control_loop.d].iter().all(|value| value.is_finite())));
+    }
+
+    #[test]
+    fn sanitize_keeps_finite_parameters() {
+        let mut packet = test_packet();
+        assert!(packet.sanitize(&PropellerLayout::default()).is_empty());
+        assert_eq!(packet, test_packet());
+    }
+
+    #[test]
+    fn second_start_debug_is_rejected() {
+        let mut tcp_msg_sender = None;
+        let (mut spawned, mut blocked, mut started) = (Vec::new(), 0, Vec::new());
+        for _ in 0..2 {
+            started.push(start_debug_session(&mut tcp_msg_sender, true,
+                |_tcp_sender, tcp_receiver| {
+                    spawned.push(tcp_receiver);
+                    task::spawn(async { Ok(()) })
+                },
+                |handle| {
+                    blocked += 1;
+                    task::block_on(handle).unwrap();
+                }));
+        }
+        assert_eq!(started, vec![true, false]);
+        assert_eq!((spawned.len(), blocked), (1, 1));
+        assert!(tcp_msg_sender.is_some());
+        // 第二次请求没有向第一个会话写入任何消息
+        let tcp_receiver = &spawned[0];
+        assert!(matches!(tcp_receiver.try_recv(), Ok(SlaveParameterTunerTcpMsg::SetDebugModeEnabled(true))));
+        assert!(matches!(tcp_receiver.try_recv(), Ok(SlaveParameterTunerTcpMsg::SetPreviewLatched(true))));
+        assert!(tcp_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn serialized_deadzones_stay_ordered() {
+        let mut rng = StdRng::seed_from_u64(8382);
+        for _ in 0..1000 {
+            let mut propeller = PropellerModel { key: "front_left".to_string(), ..Default::default() };
+            for _ in 0..20 {
+                let (value, _) = PropellerModel::deadzone_from_value(arbitrary_f64(&mut rng));
+                if rng.gen() {
+                    propeller.update_deadzone_lower(value);
+                } else {
+                    propeller.update_deadzone_upper(value);
+                }
+                let json = serde_json::to_value(PropellerModel::vec_to_map(vec![&propeller])).unwrap();
+                let (lower, upper) = (json["front_left"]["deadzone_lower"].as_i64().unwrap(), json["front_left"]["deadzone_upper"].as_i64().unwrap());
+                assert!(i8::try_from(lower).is_ok() && i8::try_from(upper).is_ok(), "lower: {}, upper: {}", lower, upper);
+                assert!(lower <= upper, "lower: {}, upper: {}", lower, upper);
+            }
+        }
+    }
+}
@@ -19,7 +19,7 @@
 use std::{str::FromStr, fmt::Debug};
 
 use glib::Sender;
-use gtk::{Align, Label, Box as GtkBox, Entry, Inhibit, Orientation, ScrolledWindow, Separator, StringList, Switch, Viewport, SpinButton, prelude::*};
+use gtk::{Align, Label, Box as GtkBox, Button, Entry, Inhibit, Orientation, ScrolledWindow, Separator, StringList, Switch, Viewport, SpinButton, prelude::*};
 use adw::{ActionRow, PreferencesGroup, prelude::*, ComboRow, ExpanderRow};
 use relm4::{WidgetPlus, send, MicroModel, MicroWidgets};
 use relm4_macros::micro_widget;
@@ -29,7 +29,7 @@ use derivative::*;
 use url::Url;
 
 use crate::{preferences::PreferencesModel, slave::video::{VideoDecoder, ColorspaceConversion, VideoCodecProvider, VideoCodec}};
-use super::{SlaveMsg, video::{VideoAlgorithm, VideoEncoder}};
+use super::{SlaveMsg, video::{VideoAlgorithm, VideoEncoder}, discovery::discovery_dialog};
 
 #[tracker::track(pub)]
 #[derive(Debug, Derivative, PartialEq, Clone)]
@@ -60,6 +60,8 @@ pub struct SlaveConfigModel {
     pub appsink_queue_leaky_enabled: bool,
     #[derivative(Default(value="PreferencesModel::default().default_video_latency"))]
     pub video_latency: u32,
+    #[derivative(Default(value="PreferencesModel::default().slave_discovery_broadcast_port"))]
+    pub discovery_broadcast_port: u16,
 }
 
 impl SlaveConfigModel {
@@ -75,6 +77,7 @@ impl SlaveConfigModel {
             reencode_recording_video: preferences.get_default_reencode_recording_video().clone(),
             appsink_queue_leaky_enabled: preferences.get_default_appsink_queue_leaky_enabled().clone(),
             video_latency: preferences.get_default_video_latency().clone(),
+            discovery_broadcast_port: *preferences.get_slave_discovery_broadcast_port(),
             ..Default::default()
         }
     }
@@ -174,7 +177,7 @@ impl MicroWidgets<SlaveConfigModel> for SlaveConfigWidgets {
                             add = &ActionRow {
                                 set_title: "连接 URL",
                                 set_subtitle: "连接下位机使用的 URL",
-                                add_suffix = &Entry {
+                                add_suffix: slave_url_entry = &Entry {
                                     set_text: model.get_slave_url().to_string().as_str(),
                                     set_width_request: 160,
                                     set_valign: Align::Center,
@@ -187,6 +190,12 @@ impl MicroWidgets<SlaveConfigModel> for SlaveConfigWidgets {
                                         }
                                     }
                                 },
+                                add_suffix: discovery_button = &Button {
+                                    set_icon_name: "system-search-symbolic",
+                                    set_tooltip_text: Some("搜索局域网中的下位机"),
+                                    set_valign: Align::Center,
+                                    add_css_class: "flat",
+                                },
                             },
                         },
                         append = &PreferencesGroup {
@@ -389,6 +398,15 @@ impl MicroWidgets<SlaveConfigModel> for SlaveConfigWidgets {
             },
         }
     }
+    fn post_init() {
+        let broadcast_port = model.discovery_broadcast_port;
+        discovery_button.connect_clicked(glib::clone!(@weak slave_url_entry => move |button| {
+            let window = button.root().and_then(|root| root.downcast::<gtk::Window>().ok());
+            discovery_dialog(window.as_ref(), broadcast_port, glib::clone!(@weak slave_url_entry => move |url| {
+                slave_url_entry.set_text(url.as_str()); // 经由 connect_changed 更新连接 URL
+            }));
+        }));
+    }
 }
 // Local Variables:
 // eval: (local-set-key
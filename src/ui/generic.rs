@@ -16,13 +16,19 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::path::PathBuf;
+use std::{path::{Path, PathBuf}, rc::Rc, cell::{Cell, RefCell}, time::{Duration, Instant}};
 
-use gtk::{FileChooserNative, FileFilter, prelude::*, FileChooserAction, MessageDialog, ResponseType};
+use adw::ActionRow;
+use glib::{clone, SignalHandlerId};
+use gtk::{FileChooserNative, FileFilter, prelude::*, FileChooserAction, MessageDialog, ResponseType, SpinButton, Scale};
 
-pub fn select_path<T, F>(action: FileChooserAction, filters: &[FileFilter], parent_window: &T, callback: F) -> FileChooserNative
+/// 显示文件选择对话框，对话框在用户作出选择前持有自身的引用，响应后释放，调用者无需保留返回值；
+/// `initial_folder` 不存在时使用对话框的默认位置
+pub fn select_path<T, F>(action: FileChooserAction, filters: &[FileFilter], initial_folder: Option<&Path>, parent_window: &T, callback: F) -> FileChooserNative
 where T: IsA<gtk::Window>,
       F: 'static + Fn(Option<PathBuf>) -> () {
+    let holder: Rc<RefCell<Option<FileChooserNative>>> = Rc::new(RefCell::new(None));
+    let response_holder = holder.clone();
     relm4_macros::view! {
         file_chooser = FileChooserNative {
             set_action: action,
@@ -35,11 +41,8 @@ where T: IsA<gtk::Window>,
             connect_response => move |dialog, res_ty| {
                 match res_ty {
                     gtk::ResponseType::Accept => {
-                        if let Some(file) = dialog.file() {
-                            if let Some(path) = file.path() {
-                                callback(Some(path));
-                                return;
-                            }
+                        if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                            callback(Some(path));
                         }
                     },
                     gtk::ResponseType::Cancel => {
@@ -47,9 +50,18 @@ where T: IsA<gtk::Window>,
                     },
                     _ => (),
                 }
+                dialog.destroy();
+                response_holder.borrow_mut().take(); // 释放对话框对自身的引用
             },
         }
     }
+    if let Some(folder) = initial_folder.filter(|folder| folder.is_dir()) {
+        if let Err(err) = file_chooser.set_current_folder(Some(&gio::File::for_path(folder))) {
+            log::warn!("无法打开文件夹 {}：{}", folder.to_str().unwrap_or_default(), err);
+        }
+    }
+    file_chooser.add_weak_ref_notify_local(|| log::debug!("文件选择对话框已释放"));
+    *holder.borrow_mut() = Some(file_chooser.clone());
     file_chooser.show();
     file_chooser
 }
@@ -71,3 +83,80 @@ pub fn error_message<T>(title: &str, msg: &str, window: Option<&T>) -> MessageDi
     dialog.show();
     dialog
 }
+
+pub fn info_message<T>(title: &str, msg: &str, window: Option<&T>) -> MessageDialog where T: IsA<gtk::Window> {
+    relm4_macros::view! {
+        dialog = MessageDialog {
+            set_message_type: gtk::MessageType::Info,
+            set_text: Some(msg),
+            set_title: Some(title),
+            set_modal: true,
+            set_transient_for: window,
+            add_button: args!("确定", ResponseType::Ok),
+            connect_response => |dialog, _response| {
+                dialog.destroy();
+            }
+        }
+    }
+    dialog.show();
+    dialog
+}
+
+/// 用于 `SpinButton` 的 `input` 信号，在键盘输入的值将被修正时高亮所在行并提示有效范围
+pub fn validate_spin_button_input(button: &SpinButton) -> Option<Result<f64, ()>> {
+    let text = button.text();
+    let adjustment = button.adjustment();
+    let (lower, upper) = (adjustment.lower(), adjustment.upper());
+    let digits = button.digits() as usize;
+    let row = button.ancestor(ActionRow::static_type()).unwrap_or_else(|| button.clone().upcast());
+    let error = match text.trim().parse::<f64>() {
+        Ok(value) if value >= lower && value <= upper => None,
+        Ok(value) => Some(format!("输入值 {} 超出有效范围 {:.*} ~ {:.*}，已自动修正", value, digits, lower, digits, upper)),
+        Err(_) => Some(format!("无法识别输入值“{}”，有效范围为 {:.*} ~ {:.*}", text, digits, lower, digits, upper)),
+    };
+    match error {
+        Some(error) => {
+            row.add_css_class("error");
+            button.set_tooltip_text(Some(&error));
+        },
+        None => {
+            row.remove_css_class("error");
+            button.set_tooltip_text(None);
+        },
+    }
+    None
+}
+
+/// 拖动滑块时回调的最小间隔
+pub const SCALE_DEBOUNCE_INTERVAL: Duration = Duration::from_millis(50);
+
+pub trait ScaleDebounceExt {
+    /// 与 `connect_value_changed` 相同，但拖动过程中每 `SCALE_DEBOUNCE_INTERVAL` 至多回调一次，
+    /// 间隔内被合并的变化会在间隔结束时以最新值补发，因此松开滑块后的最终值总会送出
+    fn connect_value_changed_debounced<F: Fn(&Scale) + 'static>(&self, callback: F) -> SignalHandlerId;
+}
+
+impl ScaleDebounceExt for Scale {
+    fn connect_value_changed_debounced<F: Fn(&Scale) + 'static>(&self, callback: F) -> SignalHandlerId {
+        let callback = Rc::new(callback);
+        let last_emitted: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+        let pending = Rc::new(Cell::new(false));
+        self.connect_value_changed(move |scale| {
+            match last_emitted.get().map(|instant| instant.elapsed()) {
+                Some(elapsed) if elapsed < SCALE_DEBOUNCE_INTERVAL => {
+                    if !pending.replace(true) {
+                        glib::timeout_add_local_once(SCALE_DEBOUNCE_INTERVAL - elapsed, clone!(@weak scale, @strong callback, @strong last_emitted, @strong pending => move || {
+                            pending.set(false);
+                            last_emitted.set(Some(Instant::now()));
+                            callback(&scale);
+                        }));
+                    }
+                },
+                _ => {
+                    last_emitted.set(Some(Instant::now()));
+                    callback(scale);
+                },
+            }
+        })
+    }
+}
@@ -16,41 +16,91 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::time::{Duration, Instant};
+
 use gtk::prelude::*;
 use gio::subclass::prelude::*;
 
-use self::imp::FnBoxedPoint;
-
 /// A [Point] describes a single datapoint in a [GraphView]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Point {
     pub value: f32,
+    pub timestamp: Option<glib::DateTime>,
+}
+
+impl ToString for Point {
+    fn to_string(&self) -> String {
+        match self.timestamp.as_ref().and_then(|timestamp| timestamp.format("%H:%M:%S").ok()) {
+            Some(time) => format!("{:.2}（{}）", self.value, time),
+            None => format!("{:.2}", self.value),
+        }
+    }
 }
 
 static HALF_X_PADDING: f32 = 20.0;
 static HALF_Y_PADDING: f32 = 20.0;
+static DEFAULT_MAX_REDRAW_FPS: u32 = 30;
+static MAX_ZOOM: f32 = 100.0;
+static ZOOM_STEP: f32 = 1.25;
+
+/// Formats an axis label with at most two decimals, omitting trailing zeros.
+fn format_axis_value(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+/// Maps a cursor X coordinate to the index of the nearest sample.
+fn nearest_index(x: f32, width: f32, point_num: usize) -> Option<usize> {
+    if point_num == 0 || width <= 0.0 {
+        return None;
+    }
+    let ratio = ((x - HALF_X_PADDING) / width).clamp(0.0, 1.0);
+    Some((ratio * (point_num - 1) as f32).round() as usize)
+}
+
+/// Splits `values` into `columns` equal buckets and returns the indices of the minimum and maximum
+/// of each bucket, in sample order, so that peaks survive when there are more samples than columns.
+pub fn decimate_indices(values: &[f32], columns: usize) -> Vec<usize> {
+    let columns = columns.max(1);
+    let mut indices = Vec::with_capacity(columns * 2);
+    if values.is_empty() {
+        return indices;
+    }
+    for column in 0..columns {
+        let start = column * values.len() / columns;
+        let end = ((column + 1) * values.len() / columns).max(start + 1).min(values.len());
+        if start >= end {
+            continue;
+        }
+        let bucket = &values[start..end];
+        let (min_index, _) = bucket.iter().enumerate().min_by(|(_, a), (_, b)| a.total_cmp(b)).unwrap();
+        let (max_index, _) = bucket.iter().enumerate().max_by(|(_, a), (_, b)| a.total_cmp(b)).unwrap();
+        match min_index.cmp(&max_index) {
+            std::cmp::Ordering::Less => indices.extend([start + min_index, start + max_index]),
+            std::cmp::Ordering::Greater => indices.extend([start + max_index, start + min_index]),
+            std::cmp::Ordering::Equal => indices.push(start + min_index),
+        }
+    }
+    indices
+}
+
+/// Aggregates `points` into at most two points per column, see [decimate_indices].
+fn decimate(points: &[Point], columns: usize) -> Vec<Point> {
+    let values: Vec<f32> = points.iter().map(|point| point.value).collect();
+    decimate_indices(&values, columns).into_iter().map(|index| points[index].clone()).collect()
+}
 
 mod imp {
-    use super::{Point, HALF_X_PADDING, HALF_Y_PADDING};
+    use super::{Point, HALF_X_PADDING, HALF_Y_PADDING, DEFAULT_MAX_REDRAW_FPS, ZOOM_STEP};
     use gtk::{
-        gdk::prelude::*,
+        gdk::{self, prelude::*},
         glib::{self, clone},
         pango,
         prelude::*,
         subclass::prelude::*,
     };
-    use std::{cell::RefCell, f64::consts::PI, rc::Rc};
-
-    #[derive(Clone, glib::Boxed)]
-    #[boxed_type(name = "FnBoxedPoint")]
-    #[allow(clippy::type_complexity)]
-    pub struct FnBoxedPoint(pub Rc<RefCell<Option<Box<dyn Fn(&Point) -> String>>>>);
-    
-    impl FnBoxedPoint {
-        pub fn new(func: Option<Box<dyn Fn(&Point) -> String>>) -> Self {
-            Self(Rc::new(RefCell::new(func)))
-        }
-    }
+    use std::{cell::RefCell, f64::consts::PI, time::Instant};
 
     #[derive(Debug)]
     pub struct HoverPoint {
@@ -63,14 +113,33 @@ mod imp {
         pub height: f32,
         pub width: f32,
         pub points: Vec<Point>,
+        pub gaps: Vec<usize>,
         pub scale_x: f32,
         pub scale_y: f32,
         pub upper_value: f32,
         pub lower_value: f32,
+        pub max_redraw_fps: u32,
+        pub last_redraw: Option<Instant>,
+        pub redraw_pending: bool,
+        pub graph_color: Option<gdk::RGBA>,
+        pub grid_color: Option<gdk::RGBA>,
+        pub hover_func: Option<Box<dyn Fn(&Point) -> String>>,
+        pub hover_x: Option<f32>,
+        pub value_scale: f32,
+        pub unit_label: String,
+        pub zoom: f32,
+    }
+
+    impl GraphViewMut {
+        /// The bounds actually drawn, i.e. `upper_value` and `lower_value` divided by the zoom factor, so that zooming stays centered on zero.
+        pub fn visible_bounds(&self) -> (f32, f32) {
+            (self.upper_value / self.zoom, self.lower_value / self.zoom)
+        }
     }
 
     pub struct GraphView {
         pub inner: RefCell<GraphViewMut>,
+        pub dark_notify_handler: RefCell<Option<glib::SignalHandlerId>>, // 连接在全局的 StyleManager 上，销毁时需断开
     }
 
     #[glib::object_subclass]
@@ -84,12 +153,24 @@ mod imp {
                 inner: RefCell::new(GraphViewMut {
                     height: 0.0,
                     points: Vec::new(),
+                    gaps: Vec::new(),
                     scale_x: 0.0,
                     scale_y: 0.0,
                     width: 0.0,
                     upper_value: 100.0,
                     lower_value: -100.0,
+                    max_redraw_fps: DEFAULT_MAX_REDRAW_FPS,
+                    last_redraw: None,
+                    redraw_pending: false,
+                    graph_color: None,
+                    grid_color: None,
+                    hover_func: None,
+                    hover_x: None,
+                    value_scale: 1.0,
+                    unit_label: String::new(),
+                    zoom: 1.0,
                 }),
+                dark_notify_handler: RefCell::new(None),
             }
         }
 
@@ -102,21 +183,30 @@ mod imp {
         fn snapshot(&self, widget: &Self::Type, snapshot: &gtk::Snapshot) {
             let mut inner = self.inner.borrow_mut();
 
+            inner.last_redraw = Some(Instant::now());
             inner.height = widget.height() as f32 - HALF_Y_PADDING * 2.0;
             inner.width = widget.width() as f32 - HALF_X_PADDING * 2.0;
+            let (upper_value, lower_value) = inner.visible_bounds();
+
+            // 数据点仅在绘制时换算为显示单位，原始数值保持不变
+            let scaled: Vec<Point> = inner.points.iter().map(|point| Point { value: point.value * inner.value_scale, ..point.clone() }).collect();
+            // 数据点多于像素列数时，每列仅保留其覆盖样本的最小值与最大值
+            let columns = inner.width.max(1.0) as usize;
+            let decimated = (scaled.len() > columns).then(|| super::decimate(&scaled, columns));
+            let point_num = decimated.as_ref().map_or(scaled.len(), Vec::len);
             
-            if inner.points.is_empty() {
+            if point_num == 0 {
                 inner.scale_x = inner.width;
                 inner.scale_y = inner.height / 10000.0;
 
             } else {
                 // If we have more than one points, we don't want an empty point at the end of the graph
-                inner.scale_x = if inner.points.len() > 1 {
-                    inner.width / (inner.points.len() - 1) as f32
+                inner.scale_x = if point_num > 1 {
+                    inner.width / (point_num - 1) as f32
                 } else {
                     inner.width as f32
                 };
-                inner.scale_y = inner.height / (inner.upper_value - inner.lower_value);
+                inner.scale_y = inner.height / (upper_value - lower_value);
             };
 
             let cr = snapshot.append_cairo(&gtk::graphene::Rect::new(
@@ -125,8 +215,14 @@ mod imp {
                 widget.width() as f32,
                 widget.height() as f32,
             ));
+            let inner = inner;
+            let points: &[Point] = decimated.as_deref().unwrap_or(&scaled);
             let style_context = widget.style_context();
-            let background_color = style_context.lookup_color("insensitive_fg_color").unwrap();
+            // 未显式指定颜色时跟随当前主题（亮色/暗色）的前景色与强调色
+            let background_color = inner.grid_color.clone().unwrap_or_else(|| {
+                let fg_color = style_context.lookup_color("window_fg_color").unwrap_or_else(|| style_context.color());
+                gdk::RGBA::new(fg_color.red(), fg_color.green(), fg_color.blue(), fg_color.alpha() * 0.5)
+            });
 
             GdkCairoContextExt::set_source_rgba(&cr, &background_color);
             /*
@@ -146,9 +242,11 @@ mod imp {
                     f64::from(HALF_X_PADDING),
                     f64::from(mul * i as f32 + HALF_Y_PADDING),
                 );
-                let layout = widget.create_pango_layout(Some(
-                    &(inner.lower_value + (inner.upper_value - inner.lower_value) / 4.0 * (4 - i) as f32).to_string(),
-                ));
+                let layout = widget.create_pango_layout(Some(&format!(
+                    "{}{}",
+                    super::format_axis_value(lower_value + (upper_value - lower_value) / 4.0 * (4 - i) as f32),
+                    inner.unit_label,
+                )));
                 let (_, extents) = layout.extents();
 
                 cr.rel_move_to(0.0, pango::units_to_double(extents.height()) * -1.0);
@@ -156,6 +254,16 @@ mod imp {
             }
 
             cr.stroke().expect("Couldn't stroke on Cairo Context");
+
+            if inner.zoom > 1.0 {
+                let layout = widget.create_pango_layout(Some(&format!("×{:.1}", inner.zoom)));
+                let (_, extents) = layout.extents();
+                cr.move_to(
+                    f64::from(inner.width + HALF_X_PADDING) - pango::units_to_double(extents.width()),
+                    f64::from(HALF_Y_PADDING),
+                );
+                pangocairo::show_layout(&cr, &layout);
+            }
             cr.restore().unwrap();
 
             /*
@@ -164,7 +272,7 @@ mod imp {
 
             cr.save().unwrap();
 
-            for (i, _point) in inner.points.iter().enumerate() {
+            for (i, _point) in points.iter().enumerate() {
                 let layout = widget.create_pango_layout(None);
                 let (_, extents) = layout.extents();
 
@@ -180,21 +288,28 @@ mod imp {
             cr.stroke().expect("Couldn't stroke on Cairo Context");
             cr.restore().unwrap();
 
-            if inner.points.is_empty() {
+            if points.is_empty() {
                 return;
             }
 
+            // 放大后超出可见范围的数据不绘制到坐标轴标签上
+            cr.save().unwrap();
+            cr.rectangle(0.0, f64::from(HALF_Y_PADDING), f64::from(inner.width + HALF_X_PADDING * 2.0), f64::from(inner.height));
+            cr.clip();
+
             /*
                 Draw a point for each datapoint
             */
             cr.save().unwrap();
 
-            let graph_color = style_context.lookup_color("accent_bg_color").unwrap();
+            let graph_color = inner.graph_color.clone()
+                .or_else(|| style_context.lookup_color("accent_bg_color"))
+                .unwrap_or_else(|| style_context.color());
             GdkCairoContextExt::set_source_rgba(&cr, &graph_color);
             cr.set_line_width(4.0);
-            for (i, point) in inner.points.iter().enumerate() {
+            for (i, point) in points.iter().enumerate() {
                 let x = f64::from(i as f32 * inner.scale_x + HALF_X_PADDING);
-                let y = f64::from(inner.height - (point.value - inner.lower_value) * inner.scale_y + HALF_Y_PADDING);
+                let y = f64::from(inner.height - (point.value - lower_value) * inner.scale_y + HALF_Y_PADDING);
 
                 cr.move_to(x, y);
                 cr.arc(x, y, 1.0, 0.0, 2.0 * PI);
@@ -212,22 +327,22 @@ mod imp {
             cr.move_to(
                 f64::from(HALF_X_PADDING),
                 f64::from(
-                    inner.height - (inner.points.get(0).unwrap().value - inner.lower_value) * inner.scale_y
+                    inner.height - (points.get(0).unwrap().value - lower_value) * inner.scale_y
                         + HALF_Y_PADDING,
                 ),
             );
 
-            for (i, point) in inner.points.iter().enumerate() {
-                let next_value = if (i + 1) >= inner.points.len() {
+            for (i, point) in points.iter().enumerate() {
+                let next_value = if (i + 1) >= points.len() {
                     break;
                 } else {
-                    inner.points.get(i + 1).unwrap().value - inner.lower_value
+                    points.get(i + 1).unwrap().value - lower_value
                 };
                 let smoothness_factor = 0.5;
 
                 cr.curve_to(
                     f64::from((i as f32 + smoothness_factor) * inner.scale_x + HALF_X_PADDING),
-                    f64::from(inner.height - (point.value - inner.lower_value) * inner.scale_y + HALF_Y_PADDING),
+                    f64::from(inner.height - (point.value - lower_value) * inner.scale_y + HALF_Y_PADDING),
                     f64::from(
                         ((i + 1) as f32 - smoothness_factor) * inner.scale_x + HALF_X_PADDING,
                     ),
@@ -240,7 +355,7 @@ mod imp {
             cr.line_to(
                 f64::from(inner.width + HALF_X_PADDING),
                 f64::from(
-                    inner.height - (inner.points.last().unwrap().value - inner.lower_value) * inner.scale_y
+                    inner.height - (points.last().unwrap().value - lower_value) * inner.scale_y
                         + HALF_Y_PADDING,
                 ),
             );
@@ -268,6 +383,37 @@ mod imp {
                 .expect("Couldn't stroke on Cairo Context");
             cr.fill().expect("Couldn't fill Cairo Context");
             cr.restore().unwrap();
+
+            /*
+                Draw a dashed line before each sample that follows a gap
+            */
+            cr.save().unwrap();
+            GdkCairoContextExt::set_source_rgba(&cr, &background_color);
+            cr.set_line_width(1.5);
+            cr.set_dash(&[4.0, 4.0], 0.0);
+            for &index in inner.gaps.iter().filter(|&&index| index > 0 && index < inner.points.len()) {
+                // 与悬停标记相同，按原始样本序号换算横坐标，不受抽稀影响
+                let x = f64::from((index as f32 - 0.5) / (inner.points.len().max(2) - 1) as f32 * inner.width + HALF_X_PADDING);
+                cr.move_to(x, f64::from(HALF_Y_PADDING));
+                cr.line_to(x, f64::from(inner.height + HALF_Y_PADDING));
+            }
+            cr.stroke().expect("Couldn't stroke on Cairo Context");
+            cr.restore().unwrap();
+            cr.restore().unwrap();
+
+            /*
+                Draw the hovered point
+            */
+            if let Some(index) = inner.hover_x.and_then(|x| super::nearest_index(x, inner.width, inner.points.len())) {
+                cr.save().unwrap();
+                let x = f64::from(index as f32 / (inner.points.len().max(2) - 1) as f32 * inner.width + HALF_X_PADDING);
+                let y = f64::from(inner.height - (scaled[index].value - lower_value) * inner.scale_y + HALF_Y_PADDING);
+                GdkCairoContextExt::set_source_rgba(&cr, &graph_color);
+                cr.set_line_width(2.0);
+                cr.arc(x, y, 4.0, 0.0, 2.0 * PI);
+                cr.stroke().expect("Couldn't stroke on Cairo Context");
+                cr.restore().unwrap();
+            }
         }
     }
 
@@ -291,7 +437,32 @@ mod imp {
             motion_controller.connect_motion(
                 clone!(@weak obj => move|c, x, y| obj.on_motion_event(x, y, false, c)),
             );
+            motion_controller.connect_leave(
+                clone!(@weak obj => move |_| obj.on_leave_event()),
+            );
             obj.add_controller(&motion_controller);
+
+            // Ctrl + 滚轮缩放纵轴，不带修饰键时交给外层的滚动窗口
+            let scroll_controller = gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+            scroll_controller.connect_scroll(clone!(@weak obj => @default-return gtk::Inhibit(false), move |controller, _, dy| {
+                if !controller.current_event_state().contains(gdk::ModifierType::CONTROL_MASK) {
+                    return gtk::Inhibit(false);
+                }
+                obj.set_zoom(obj.zoom() * ZOOM_STEP.powf(-dy as f32));
+                gtk::Inhibit(true)
+            }));
+            obj.add_controller(&scroll_controller);
+
+            let handler = adw::StyleManager::default().connect_dark_notify(
+                clone!(@weak obj => move |_| obj.queue_draw()),
+            );
+            self.dark_notify_handler.replace(Some(handler));
+        }
+
+        fn dispose(&self, _obj: &Self::Type) {
+            if let Some(handler) = self.dark_notify_handler.take() {
+                adw::StyleManager::default().disconnect(handler);
+            }
         }
         fn properties() -> &'static [glib::ParamSpec] {
             use once_cell::sync::Lazy;
@@ -314,7 +485,16 @@ mod imp {
                         f32::MAX,
                         0.0,
                         glib::ParamFlags::READWRITE,
-                    )
+                    ),
+                    glib::ParamSpecFloat::new(
+                        "zoom",
+                        "zoom",
+                        "zoom",
+                        1.0,
+                        super::MAX_ZOOM,
+                        1.0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
                 ]
             });
 
@@ -337,6 +517,19 @@ mod imp {
                     self.inner.borrow_mut().lower_value = value.get().unwrap();
                     obj.queue_draw();
                 }
+                "zoom" => {
+                    let zoom: f32 = value.get().unwrap();
+                    let changed = {
+                        let mut inner = self.inner.borrow_mut();
+                        let changed = inner.zoom != zoom;
+                        inner.zoom = zoom;
+                        changed
+                    };
+                    if changed {
+                        obj.notify("zoom");
+                        obj.queue_draw();
+                    }
+                }
                 _ => unimplemented!(),
             }
         }
@@ -345,6 +538,7 @@ mod imp {
             match pspec.name() {
                 "upper-value" => self.inner.borrow().upper_value.to_value(),
                 "lower-value" => self.inner.borrow().lower_value.to_value(),
+                "zoom" => self.inner.borrow().zoom.to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -381,7 +575,8 @@ impl GraphView {
     /// # Arguments
     /// * `hover_func` - A function that takes a `Point` and renders it to a string that is displayed as tooltip on the graph.
     pub fn set_hover_func(&self, hover_func: Option<Box<dyn Fn(&Point) -> String>>) {
-        self.set_property("hover-func", FnBoxedPoint::new(hover_func))
+        self.imp().inner.borrow_mut().hover_func = hover_func;
+        self.update_hover();
     }
 
     /// Set the limit (e.g. step goal) that is marked in the graph.
@@ -407,11 +602,81 @@ impl GraphView {
         //     -1,
         // );
 
-        let mut inner = self.imp().inner.borrow_mut();
+        self.imp().inner.borrow_mut().points = points;
+        self.update_hover();
+        self.queue_throttled_draw();
+    }
+
+    /// Sets the indices of the points preceded by a gap in the data (e.g. a lost connection), each marked with a dashed line.
+    pub fn set_gaps(&self, gaps: Vec<usize>) {
+        self.imp().inner.borrow_mut().gaps = gaps;
+        self.queue_throttled_draw();
+    }
+
+    /// Set the maximum number of repaints per second caused by [GraphView::set_points], `0` disables throttling.
+    pub fn set_max_redraw_fps(&self, max_redraw_fps: u32) {
+        self.imp().inner.borrow_mut().max_redraw_fps = max_redraw_fps;
+    }
+
+    /// Set the color of the trace, `None` follows the accent color of the current theme.
+    pub fn set_graph_color(&self, graph_color: Option<gdk::RGBA>) {
+        self.imp().inner.borrow_mut().graph_color = graph_color;
+        self.queue_draw();
+    }
+
+    /// Set the color of the gridlines and labels, `None` follows the foreground color of the current theme.
+    pub fn set_grid_color(&self, grid_color: Option<gdk::RGBA>) {
+        self.imp().inner.borrow_mut().grid_color = grid_color;
+        self.queue_draw();
+    }
+
+    /// Set the factor applied to the values of points before they are drawn or shown in the tooltip,
+    /// the bounds set by [GraphView::set_upper_value] and [GraphView::set_lower_value] are in scaled units.
+    pub fn set_value_scale(&self, value_scale: f32) {
+        self.imp().inner.borrow_mut().value_scale = value_scale;
+        self.update_hover();
+        self.queue_draw();
+    }
+
+    pub fn value_scale(&self) -> f32 {
+        self.imp().inner.borrow().value_scale
+    }
 
-        inner.points = points;
+    /// Set the unit appended to the axis labels and the tooltip, e.g. `"°"` or `"m"`.
+    pub fn set_unit_label(&self, unit_label: &str) {
+        self.imp().inner.borrow_mut().unit_label = unit_label.to_string();
+        self.update_hover();
         self.queue_draw();
     }
+
+    pub fn unit_label(&self) -> String {
+        self.imp().inner.borrow().unit_label.clone()
+    }
+
+    pub fn max_redraw_fps(&self) -> u32 {
+        self.imp().inner.borrow().max_redraw_fps
+    }
+
+    fn queue_throttled_draw(&self) {
+        let mut inner = self.imp().inner.borrow_mut();
+        if inner.redraw_pending {
+            return;             // 已有待执行的重绘，新数据点会在那时一并绘制
+        }
+        let delay = match (inner.max_redraw_fps, inner.last_redraw) {
+            (0, _) | (_, None) => Duration::ZERO,
+            (fps, Some(last_redraw)) => (Duration::from_secs(1) / fps).saturating_sub(last_redraw.elapsed()),
+        };
+        if delay.is_zero() {
+            drop(inner);
+            self.queue_draw();
+        } else {
+            inner.redraw_pending = true;
+            glib::timeout_add_local_once(delay, glib::clone!(@weak self as obj => move || {
+                obj.imp().inner.borrow_mut().redraw_pending = false;
+                obj.queue_draw();
+            }));
+        }
+    }
     
     pub fn set_upper_value(&self, upper_value: f32) {
         self.set_property("upper-value", upper_value)
@@ -429,9 +694,20 @@ impl GraphView {
         self.property("lower-value")
     }
 
+    /// Magnify the vertical axis by `zoom` (clamped to `1.0..=100.0`) around zero, the visible range becomes
+    /// `lower_value / zoom ..= upper_value / zoom` while the bounds themselves stay unchanged.
+    pub fn set_zoom(&self, zoom: f32) {
+        let zoom = if zoom.is_finite() { zoom.clamp(1.0, MAX_ZOOM) } else { 1.0 };
+        self.set_property("zoom", zoom);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.property("zoom")
+    }
+
     fn on_motion_event(
         &self,
-        _x: f64,
+        x: f64,
         _y: f64,
         allow_touch: bool,
         controller: &impl IsA<gtk::EventController>,
@@ -444,6 +720,32 @@ impl GraphView {
                 }
             }
         }
+        self.imp().inner.borrow_mut().hover_x = Some(x as f32);
+        self.update_hover();
+        self.queue_draw();
+    }
+
+    fn on_leave_event(&self) {
+        self.imp().inner.borrow_mut().hover_x = None;
+        self.update_hover();
+        self.queue_draw();
+    }
+
+    /// Refreshes the readout tooltip of the sample under the cursor, `hover_func` receives the scaled point.
+    fn update_hover(&self) {
+        let inner = self.imp().inner.borrow();
+        let text = inner.hover_x.and_then(|x| nearest_index(x, inner.width, inner.points.len())).map(|index| {
+            let point = Point { value: inner.points[index].value * inner.value_scale, ..inner.points[index].clone() };
+            match &inner.hover_func {
+                Some(hover_func) => hover_func(&point),
+                None => match point.timestamp.as_ref().and_then(|timestamp| timestamp.format("%H:%M:%S").ok()) {
+                    Some(time) => format!("{:.2}{}（{}）", point.value, inner.unit_label, time),
+                    None => format!("{:.2}{}", point.value, inner.unit_label),
+                },
+            }
+        });
+        drop(inner);
+        self.set_tooltip_text(text.as_deref());
     }
 }
 